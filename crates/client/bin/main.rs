@@ -1,7 +1,10 @@
 use bevy::{prelude::*, window::PresentMode};
+use eternal_config::mods::ModsPlugin;
 
 fn main() {
     App::new()
+        // Mods register asset sources, so they must be added before `AssetPlugin`.
+        .add_plugins(ModsPlugin::default())
         .add_plugins((DefaultPlugins
             .set(ImagePlugin::default_nearest())
             .set(WindowPlugin {
@@ -4,6 +4,7 @@ use bevy::{
 };
 
 use crate::player::PlayerCamera;
+use eternal_config::input::InputActions;
 use eternal_grid::{
     grid::{self, GridId, LayerIndex},
     tile::{self, TileId},
@@ -65,14 +66,14 @@ fn on_map_click(release: On<Pointer<Release>>, mut grid: Single<&mut GridId>) {
 }
 
 fn toggle_camera(
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Res<InputActions>,
     debug_singleton: Single<(Entity, &mut Camera), (With<DebugCamera>, Without<PlayerCamera>)>,
     player_singleton: Single<(Entity, &mut Camera), (With<PlayerCamera>, Without<DebugCamera>)>,
     map: Single<Entity, With<GridId>>,
     mut commands: Commands,
     mut cache: Local<Option<Entity>>,
 ) {
-    if input.just_pressed(KeyCode::KeyP) {
+    if actions.just_pressed("toggle_debug_camera") {
         let (debug_entity, mut debug_cam) = debug_singleton.into_inner();
         let (player_entity, mut player_cam) = player_singleton.into_inner();
 
@@ -122,10 +123,10 @@ fn zoom(
 
 fn pan(
     mut motion_msgs: MessageReader<MouseMotion>,
-    input: Res<ButtonInput<MouseButton>>,
+    actions: Res<InputActions>,
     singleton: Single<(&mut Transform, &Projection, &DebugCamera)>,
 ) {
-    if !input.pressed(MouseButton::Middle) {
+    if !actions.pressed("camera_pan") {
         return;
     }
 
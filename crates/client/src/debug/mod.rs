@@ -3,6 +3,7 @@ use bevy::app::{App, Plugin};
 pub mod camera;
 mod diagnostics;
 mod inspector;
+mod ui_config_errors;
 mod ui_settings;
 mod ui_tile_map;
 
@@ -16,6 +17,7 @@ impl Plugin for DebugPlugin {
             ui_settings::UiDebugSettingsPlugin,
             camera::DebugCameraPlugin,
             diagnostics::DiagnosticsPlugin,
+            ui_config_errors::UiConfigErrorsPlugin,
         ));
     }
 }
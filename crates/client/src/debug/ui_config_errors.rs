@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use eternal_config::errors::ConfigErrors;
+use eternal_ui::window::{WindowConfig, window};
+
+pub struct UiConfigErrorsPlugin;
+
+impl Plugin for UiConfigErrorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_config_errors.run_if(resource_changed::<ConfigErrors>),
+        );
+    }
+}
+
+#[derive(Component)]
+struct ConfigErrorsUi;
+
+#[derive(Component)]
+struct ConfigErrorsText;
+
+/// Shows a window listing every config error, like RON files failing to parse on hot reload.
+/// The window is removed once all errors are fixed.
+fn update_config_errors(
+    errors: Res<ConfigErrors>,
+    q_ui_roots: Query<Entity, With<ConfigErrorsUi>>,
+    mut q_text: Query<&mut Text, With<ConfigErrorsText>>,
+    mut commands: Commands,
+) {
+    if errors.is_empty() {
+        for entity in q_ui_roots {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let s = errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if let Ok(mut text) = q_text.single_mut() {
+        text.0 = s;
+        return;
+    }
+
+    commands.spawn((
+        Name::new("Config Errors"),
+        window(
+            WindowConfig {
+                title: "[Debug] Config Errors".to_string(),
+                bottom: px(1.0),
+                left: px(1.0),
+                ..default()
+            },
+            (
+                Text::new(s),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 0.4, 0.4)),
+                ConfigErrorsText,
+            ),
+        ),
+        ConfigErrorsUi,
+    ));
+}
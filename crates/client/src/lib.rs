@@ -1,16 +1,12 @@
 use bevy::prelude::*;
 
-use eternal_config::ConfigPlugin;
-use eternal_grid::{ecs::TileRegistry, grid};
-use eternal_procgen::biome::BiomeRegistry;
+use eternal_config::{
+    ConfigPlugin, errors::ConfigErrors, input::InputActionsPlugin, server::configs_ready,
+};
+use eternal_procgen::{atlas::Atlas, biome::BiomeRegistry};
 use eternal_ui::UiPlugin;
 
-use crate::{
-    debug::DebugPlugin,
-    effects::EffectsPlugin,
-    player::{Player, PlayerPlugin},
-    world::WorldPlugin,
-};
+use crate::{debug::DebugPlugin, effects::EffectsPlugin, player::PlayerPlugin, world::WorldPlugin};
 
 mod debug;
 mod effects;
@@ -27,13 +23,16 @@ impl Plugin for ClientPlugin {
             .add_plugins((
                 EffectsPlugin,
                 ConfigPlugin,
+                InputActionsPlugin,
                 WorldPlugin,
                 PlayerPlugin,
                 UiPlugin,
             ))
             .init_state::<ClientState>()
-            .add_systems(OnEnter(ClientState::Playing), setup)
-            .add_systems(Update, loading.run_if(in_state(ClientState::Loading)));
+            .add_systems(
+                Update,
+                loading.run_if(in_state(ClientState::Loading).and(configs_ready)),
+            );
     }
 }
 
@@ -44,19 +43,13 @@ enum ClientState {
     Playing,
 }
 
-fn setup(mut commands: Commands) {
-    commands.spawn((
-        Player,
-        Transform::from_translation(grid::grid_to_world(140, 100).extend(0.0)),
-    ));
-}
-
 fn loading(
     biome_registry: Res<BiomeRegistry>,
-    tile_registry: Res<TileRegistry>,
+    atlas: Option<Res<Atlas>>,
+    config_errors: Res<ConfigErrors>,
     mut next_state: ResMut<NextState<ClientState>>,
 ) {
-    if !biome_registry.is_ready() || tile_registry.is_empty() {
+    if !biome_registry.is_ready() || atlas.is_none() || !config_errors.is_empty() {
         return;
     }
 
@@ -1,10 +1,6 @@
 use avian2d::prelude::LinearVelocity;
-use bevy::{
-    app::Plugin,
-    ecs::component::Component,
-    input::{ButtonInput, keyboard::KeyCode},
-    prelude::*,
-};
+use bevy::{app::Plugin, ecs::component::Component, prelude::*};
+use eternal_config::input::InputActions;
 
 use crate::player::{Player, PlayerCamera};
 
@@ -79,21 +75,21 @@ fn on_add_player(add: On<Add, Player>, mut commands: Commands, asset_server: Res
 
 fn move_player(
     singleton: Single<(&PlayerController, &mut LinearVelocity)>,
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Res<InputActions>,
 ) {
     let mut direction = Vec2::ZERO;
     let (controller, mut velocity) = singleton.into_inner();
 
-    if input.pressed(KeyCode::KeyW) {
+    if actions.pressed("move_up") {
         direction.y += 1.0;
     }
-    if input.pressed(KeyCode::KeyS) {
+    if actions.pressed("move_down") {
         direction.y -= 1.0;
     }
-    if input.pressed(KeyCode::KeyA) {
+    if actions.pressed("move_left") {
         direction.x -= 1.0;
     }
-    if input.pressed(KeyCode::KeyD) {
+    if actions.pressed("move_right") {
         direction.x += 1.0;
     }
 
@@ -157,19 +153,8 @@ fn update_looking_at_pointer(
     pointer.translation = (looking_at.dir * distance).extend(-0.01);
 }
 
-fn trigger_action(
-    input: Res<ButtonInput<MouseButton>>,
-    mut commands: Commands,
-    mut cooldown: Local<f32>,
-    time: Res<Time>,
-) {
-    if *cooldown > 0.0 {
-        *cooldown -= time.delta_secs();
-        return;
-    }
-
-    if input.pressed(MouseButton::Left) {
-        *cooldown = 0.5;
+fn trigger_action(actions: Res<InputActions>, mut commands: Commands) {
+    if actions.triggered("action") {
         commands.trigger(PlayerAction);
     }
 }
@@ -0,0 +1,253 @@
+use avian2d::prelude::{Collider, RigidBody, Sensor};
+use bevy::{math::U16Vec2, platform::collections::HashMap, prelude::*, sprite::Anchor};
+use eternal_config::{
+    flora::{CollisionShape, FloraConfig, FloraRegistryConfig},
+    server::{ConfigAssetUpdated, ConfigServer, Configs},
+};
+use eternal_grid::ecs::TileRegistry;
+
+use crate::{
+    player::PlayerActionHit,
+    world::{
+        grid::{self, GridId, GridIdChanged, LayerIndex},
+        tile::{self, TileId, TileInfo},
+    },
+};
+
+pub struct FloraPlugin;
+
+impl Plugin for FloraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloraRegistry>()
+            .init_resource::<FloraTiles>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                spawn_all_flora.run_if(
+                    resource_changed::<FloraRegistry>
+                        .or(resource_changed::<TileRegistry>)
+                        .or(any_match_filter::<Added<GridId>>),
+                ),
+            )
+            .add_observer(on_grid_id_changed);
+    }
+}
+
+/// Sprite and collider used to spawn a flora entity.
+#[derive(Debug, Clone)]
+pub struct FloraInfo {
+    pub image: Handle<Image>,
+    pub rect: Rect,
+    /// Point of the sprite placed at the center of the tile, relative to the sprite center.
+    pub anchor: Vec2,
+    pub collision_shape: Option<CollisionShape>,
+}
+
+impl FloraInfo {
+    fn from_config(config: &FloraConfig, image: Handle<Image>) -> Self {
+        let FloraConfig {
+            sprite_region,
+            sprite_size,
+            anchor,
+            collision_shape,
+            ..
+        } = config;
+
+        let min = sprite_region.as_vec2();
+        let size = sprite_size.as_vec2().max(Vec2::ONE);
+
+        // Config anchor is in pixels from the top-left corner, while sprite anchor goes from
+        // (-0.5, -0.5) at bottom-left to (0.5, 0.5) at top-right.
+        let anchor = Vec2::new(
+            anchor.x as f32 / size.x - 0.5,
+            0.5 - anchor.y as f32 / size.y,
+        );
+
+        Self {
+            image,
+            rect: Rect::from_corners(min, min + size),
+            anchor,
+            collision_shape: collision_shape.clone(),
+        }
+    }
+
+    fn collider(&self) -> Option<Collider> {
+        self.collision_shape.as_ref().map(|shape| match shape {
+            CollisionShape::Circle(radius) => Collider::circle(radius * tile::SIZE.x as f32),
+        })
+    }
+}
+
+/// Flora sprites, indexed by the name of the wall tile they replace.
+#[derive(Debug, Default, Resource, Deref)]
+pub struct FloraRegistry(HashMap<String, FloraInfo>);
+
+impl FloraRegistry {
+    pub fn get(&self, info: &TileInfo) -> Option<&FloraInfo> {
+        self.0.get(info.name.as_ref())
+    }
+
+    pub fn is_flora(&self, info: &TileInfo) -> bool {
+        self.0.contains_key(info.name.as_ref())
+    }
+}
+
+/// Flora entity spawned on a wall tile. Despawned when the tile changes.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+pub struct Flora {
+    pub tile: U16Vec2,
+}
+
+/// Flora entities by the tile position they are linked to.
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+struct FloraTiles(HashMap<U16Vec2, Entity>);
+
+fn setup(mut config_server: ConfigServer) {
+    config_server
+        .load::<FloraRegistryConfig>("config/flora.ron")
+        .observe(on_flora_registry_config_updated);
+}
+
+fn on_flora_registry_config_updated(
+    updated: On<ConfigAssetUpdated>,
+    configs: Configs<FloraRegistryConfig>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let Some(config) = configs.get(updated.id()) else {
+        error!("Failed to get flora registry config");
+        return;
+    };
+
+    let registry = config
+        .iter()
+        .map(|flora| {
+            let image = asset_server.load(flora.sprite_sheet.clone());
+            (flora.name.clone(), FloraInfo::from_config(flora, image))
+        })
+        .collect::<HashMap<_, _>>();
+
+    debug!("Loaded flora registry: {registry:?}");
+
+    commands.insert_resource(FloraRegistry(registry));
+}
+
+fn spawn_all_flora(
+    grid: Option<Single<&GridId>>,
+    tile_registry: Res<TileRegistry>,
+    flora_registry: Res<FloraRegistry>,
+    mut flora_tiles: ResMut<FloraTiles>,
+    mut commands: Commands,
+) {
+    for (_, entity) in flora_tiles.drain() {
+        commands.entity(entity).try_despawn();
+    }
+
+    let Some(grid) = grid else {
+        return;
+    };
+
+    for (x, y, id) in grid[LayerIndex::Wall].positions() {
+        spawn_flora(
+            U16Vec2::new(x, y),
+            id,
+            &tile_registry,
+            &flora_registry,
+            &mut flora_tiles,
+            &mut commands,
+        );
+    }
+}
+
+fn on_grid_id_changed(
+    changed: On<GridIdChanged>,
+    grid: Single<&GridId>,
+    tile_registry: Res<TileRegistry>,
+    flora_registry: Res<FloraRegistry>,
+    mut flora_tiles: ResMut<FloraTiles>,
+    mut commands: Commands,
+) {
+    let GridIdChanged(layer, positions) = &*changed;
+
+    if *layer != LayerIndex::Wall {
+        return;
+    }
+
+    for &position in positions {
+        if let Some(entity) = flora_tiles.remove(&position) {
+            commands.entity(entity).try_despawn();
+        }
+
+        let id = grid[LayerIndex::Wall].get(position.x, position.y);
+        spawn_flora(
+            position,
+            id,
+            &tile_registry,
+            &flora_registry,
+            &mut flora_tiles,
+            &mut commands,
+        );
+    }
+}
+
+fn spawn_flora(
+    position: U16Vec2,
+    id: &TileId,
+    tile_registry: &TileRegistry,
+    flora_registry: &FloraRegistry,
+    flora_tiles: &mut FloraTiles,
+    commands: &mut Commands,
+) {
+    let Some(info) = tile_registry
+        .get(id)
+        .and_then(|info| flora_registry.get(info))
+    else {
+        return;
+    };
+
+    let center = grid::grid_to_world(position.x, position.y) + tile::SIZE.as_vec2() / 2.0;
+    // Flora further down is drawn in front of the flora behind it.
+    let z = LayerIndex::Wall.height() + 0.5 - position.y as f32 / grid::DIMS.y as f32 * 0.1;
+
+    let mut entity = commands.spawn((
+        Name::new(format!("Flora {position}")),
+        Flora { tile: position },
+        Sprite {
+            image: info.image.clone(),
+            rect: Some(info.rect),
+            ..default()
+        },
+        Anchor(info.anchor),
+        Transform::from_translation(center.extend(z)),
+    ));
+
+    // Player actions only hit colliders, so flora which doesn't block movement has a sensor.
+    match info.collider() {
+        Some(collider) => entity.insert((RigidBody::Static, collider)),
+        None => entity.insert((
+            RigidBody::Static,
+            Sensor,
+            Collider::rectangle(tile::SIZE.x as f32, tile::SIZE.y as f32),
+        )),
+    };
+
+    entity.observe(on_flora_hit_by_player);
+
+    flora_tiles.insert(position, entity.id());
+}
+
+fn on_flora_hit_by_player(
+    hit: On<PlayerActionHit>,
+    q_flora: Query<&Flora>,
+    grid: Single<&GridId>,
+    mut flora_tiles: ResMut<FloraTiles>,
+    mut commands: Commands,
+) {
+    let Ok(&Flora { tile }) = q_flora.get(hit.event_target()) else {
+        return;
+    };
+
+    flora_tiles.remove(&tile);
+    commands.entity(hit.event_target()).try_despawn();
+    grid[LayerIndex::Wall].queue(tile.x, tile.y, TileId::default());
+}
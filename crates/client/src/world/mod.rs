@@ -1,13 +1,23 @@
 use std::time::Duration;
 
 use bevy::{math::U16Vec2, prelude::*};
-use eternal_procgen::{ProcGenPlugin, biome::BiomeRegistry, map::Map};
+use eternal_procgen::{
+    ProcGenPlugin, WorldSeed,
+    atlas::{ActiveMap, Atlas, AtlasRegion},
+    biome::BiomeRegistry,
+    generator::{GeneratorContext, MapGeneratorRegistry},
+    map::Map,
+    prefab::PrefabRegistry,
+    validation::MapValidation,
+};
 
 use crate::{
     ClientState,
+    player::Player,
     run_conditions::timeout,
     world::{
         actions::ActionsPlugin,
+        flora::FloraPlugin,
         physics::PhysicsPlugin,
         renderer::{MapRendererPlugin, tilemap::Tilemap},
     },
@@ -19,6 +29,7 @@ use eternal_grid::{
 };
 
 mod actions;
+pub mod flora;
 pub mod physics;
 pub mod renderer;
 
@@ -31,6 +42,7 @@ impl Plugin for WorldPlugin {
                 MapRendererPlugin,
                 PhysicsPlugin,
                 ActionsPlugin,
+                FloraPlugin,
                 ProcGenPlugin,
             ))
             .add_systems(OnEnter(ClientState::Playing), spawn_map)
@@ -48,19 +60,47 @@ fn spawn_map(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     biome_registry: Res<BiomeRegistry>,
+    prefab_registry: Res<PrefabRegistry>,
+    generator_registry: Res<MapGeneratorRegistry>,
+    validation: Res<MapValidation>,
+    atlas: Res<Atlas>,
+    active_map: Res<ActiveMap>,
+    seed: Res<WorldSeed>,
 ) {
     let tilemap = Tilemap {
         atlas_texture: asset_server.load("sheets/terrain.png"),
-        atlas_dims: UVec2::new(4, 4),
+        atlas_dims: UVec2::new(4, 5),
     };
 
-    let forest = biome_registry
-        .get_biome("Forest")
-        .expect("Forest biome should always exists");
+    let ctx = GeneratorContext {
+        biomes: &biome_registry,
+        prefabs: &prefab_registry,
+        region: Some(AtlasRegion {
+            atlas: &atlas,
+            map: **active_map,
+        }),
+        seed: **seed,
+        spawn_clearance: validation.get("overworld").spawn_clearance,
+    };
 
-    let Map {
-        tile, elevation, ..
-    } = eternal_procgen::generate_map(forest);
+    let (
+        Map {
+            tile,
+            elevation,
+            spawn,
+            ..
+        },
+        report,
+    ) = generator_registry
+        .generate_validated("overworld", &ctx, &validation)
+        .expect("Overworld map generator should always exists");
+
+    if !report.is_valid() {
+        warn!(
+            "Map is invalid after {} attempts: {:?}",
+            report.attempts, report.issues
+        );
+    }
 
     debug!("Generated ids!");
 
@@ -71,6 +111,16 @@ fn spawn_map(
         elevation,
         GridVisible::new(),
     ));
+
+    let spawn = spawn.unwrap_or_else(|| {
+        warn!("Map has no spawn, spawning player on the map center");
+        grid::DIMS.as_u16vec2() / 2
+    });
+
+    commands.spawn((
+        Player,
+        Transform::from_translation(grid::grid_to_world(spawn.x, spawn.y).extend(0.0)),
+    ));
 }
 
 fn update_tile_visibility(
@@ -1,7 +1,13 @@
 use avian2d::prelude::*;
 use bevy::prelude::*;
 
-use eternal_grid::grid::{GridId, LayerIndex};
+use eternal_grid::{
+    ecs::TileRegistry,
+    grid::{GridId, LayerIndex},
+    tile,
+};
+
+use crate::world::flora::FloraRegistry;
 
 pub struct PhysicsPlugin;
 
@@ -20,7 +26,14 @@ impl Plugin for PhysicsPlugin {
                 ..default()
             },
         )
-        .add_systems(Update, update_wall_collider)
+        .add_systems(
+            Update,
+            update_wall_collider.run_if(
+                resource_changed::<FloraRegistry>
+                    .or(resource_changed::<TileRegistry>)
+                    .or(any_match_filter::<Changed<GridId>>),
+            ),
+        )
         .add_observer(on_add_grid);
     }
 }
@@ -30,7 +43,9 @@ pub fn on_add_grid(add: On<Add, GridId>, mut commands: Commands) {
 }
 
 pub fn update_wall_collider(
-    singleton: Single<(Entity, &GridId), Changed<GridId>>,
+    singleton: Single<(Entity, &GridId)>,
+    tile_registry: Res<TileRegistry>,
+    flora_registry: Res<FloraRegistry>,
     mut commands: Commands,
 ) {
     let (entity, grid) = singleton.into_inner();
@@ -38,7 +53,9 @@ pub fn update_wall_collider(
     let walls = grid[LayerIndex::Wall]
         .positions()
         .filter_map(|(x, y, &id)| {
-            if id.is_none() {
+            // Flora has its own collider
+            let info = tile_registry.get(&id).unwrap_or(&tile::NONE_INFO);
+            if id.is_none() || flora_registry.is_flora(info) {
                 None
             } else {
                 Some(IVec2::new(x as i32, y as i32))
@@ -19,7 +19,7 @@ const SHADER_PATH: &str = "shaders/tilemap_chunk_material.wgsl";
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 pub struct TilePod {
-    pub index: u16,  // Red channel
+    pub index: u16,  // Red channel. Atlas index of the tile variant at this position.
     pub weight: u8,  // Green channel.
     pub outline: u8, // Green channel.
 }
@@ -12,13 +12,15 @@ use bevy::{
 mod material;
 
 use eternal_grid::ecs::TileRegistry;
+use eternal_procgen::WorldSeed;
 pub use material::{TilePod, TilemapChunkMaterial};
 
 use crate::{
     ClientState,
     world::{
+        flora::FloraRegistry,
         grid::{self, GridId, GridIdChanged, LAYER_SIZE, LAYERS, LAYERS_COUNT, LayerIndex},
-        tile::{self},
+        tile::{self, TileId, TileInfo},
     },
 };
 
@@ -31,8 +33,11 @@ impl Plugin for TilemapPlugin {
         app.add_plugins(Material2dPlugin::<TilemapChunkMaterial>::default())
             .add_systems(
                 Update,
-                (update_tilemap_chunk_material
-                    .run_if(resource_changed::<TileRegistry>.or(state_changed::<ClientState>)),)
+                (update_tilemap_chunk_material.run_if(
+                    resource_changed::<TileRegistry>
+                        .or(resource_changed::<FloraRegistry>)
+                        .or(state_changed::<ClientState>),
+                ),)
                     .run_if(in_state(ClientState::Playing)),
             )
             .add_observer(on_grid_id_changed);
@@ -53,7 +58,7 @@ impl Default for Tilemap {
     fn default() -> Self {
         Self {
             atlas_texture: Default::default(),
-            atlas_dims: UVec2::new(4, 4),
+            atlas_dims: UVec2::new(4, 5),
         }
     }
 }
@@ -210,9 +215,23 @@ fn get_data_pod_layer(layer: LayerIndex, tile_data_image: &mut Image) -> &mut [T
     )[begin..end]
 }
 
+/// Info used to render the given tile. Flora is rendered as sprites, so it's left empty.
+fn tile_info<'a>(
+    tile_info_map: &'a TileRegistry,
+    flora_registry: &FloraRegistry,
+    id: &TileId,
+) -> &'a TileInfo {
+    match tile_info_map.get(id) {
+        Some(info) if !flora_registry.is_flora(info) => info,
+        _ => &tile::NONE_INFO,
+    }
+}
+
 fn update_tilemap_chunk_material(
     tilemap: Single<(&GridId, &TilemapCache)>,
     tile_info_map: Res<TileRegistry>,
+    flora_registry: Res<FloraRegistry>,
+    seed: Res<WorldSeed>,
     mut materials: ResMut<Assets<TilemapChunkMaterial>>,
     mut images: ResMut<Assets<Image>>,
 ) {
@@ -238,9 +257,10 @@ fn update_tilemap_chunk_material(
             .enumerate()
             .for_each(|(idx, pod)| {
                 let id = grid_layer[idx];
-                let info = tile_info_map.get(&id).unwrap_or(&tile::NONE_INFO);
+                let info = tile_info(&tile_info_map, &flora_registry, &id);
+                let (x, y) = grid::from_index(idx);
 
-                pod.index = info.atlas_index;
+                pod.index = info.atlas_index_at(x, y, **seed);
                 pod.weight = match info.blend_tech {
                     tile::BlendTech::None => u8::MAX,
                     tile::BlendTech::Weight(w) => w,
@@ -254,6 +274,8 @@ fn on_grid_id_changed(
     changed: On<GridIdChanged>,
     tilemap: Single<(&GridId, &TilemapCache)>,
     tile_info_map: Res<TileRegistry>,
+    flora_registry: Res<FloraRegistry>,
+    seed: Res<WorldSeed>,
     mut materials: ResMut<Assets<TilemapChunkMaterial>>,
     mut images: ResMut<Assets<Image>>,
 ) {
@@ -277,10 +299,10 @@ fn on_grid_id_changed(
 
     for &U16Vec2 { x, y } in positions {
         let id = grid_layer.get(x, y);
-        let info = tile_info_map.get(id).unwrap_or(&tile::NONE_INFO);
+        let info = tile_info(&tile_info_map, &flora_registry, id);
 
         let pod = &mut tile_data_pods[grid::to_index(x, y)];
-        pod.index = info.atlas_index;
+        pod.index = info.atlas_index_at(x, y, **seed);
         pod.weight = match info.blend_tech {
             tile::BlendTech::None => u8::MAX,
             tile::BlendTech::Weight(w) => w,
@@ -1,51 +1,135 @@
-use crate::server::{ConfigServerPlugin, FromConfig};
+use crate::{
+    flora::FloraSpawnRegistryConfig,
+    noise::NoiseStackConfig,
+    prefab::StructureSpawnRegistryConfig,
+    resource::ResourceSpawnRegistryConfig,
+    road::RoadConfig,
+    server::{ConfigRef, ConfigServerPlugin, FromConfig},
+    tile::TileConfigList,
+};
 use bevy::prelude::*;
 
 pub(crate) struct BiomeConfigPlugin;
 impl Plugin for BiomeConfigPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
-            ConfigServerPlugin::<BiomeRegistryConfig>::default(),
-            ConfigServerPlugin::<BiomePalletConfig>::default(),
+            ConfigServerPlugin::<BiomeRegistryConfig>::default().depends_on::<TileConfigList>(),
+            ConfigServerPlugin::<BiomePalletConfig>::default().depends_on::<TileConfigList>(),
         ));
     }
 }
 
+/// Configs of a single biome, which may be shared with other biomes.
 #[derive(Reflect, Default, Debug, Clone)]
 pub struct BiomeConfig {
     pub name: String,
-    pub terrain_noise: String,
-    pub terrain_pallet: String,
-    pub flora: String,
-    pub flora_noise: String,
+    pub terrain_noise: ConfigRef<NoiseStackConfig>,
+    pub terrain_pallet: ConfigRef<BiomePalletConfig>,
+    pub flora: ConfigRef<FloraSpawnRegistryConfig>,
+    pub flora_noise: ConfigRef<NoiseStackConfig>,
+    pub structures: ConfigRef<StructureSpawnRegistryConfig>,
+    pub resources: ConfigRef<ResourceSpawnRegistryConfig>,
+    pub resource_noise: ConfigRef<NoiseStackConfig>,
+    pub roads: ConfigRef<RoadConfig>,
 }
 
 #[derive(Reflect, Default, Debug, Clone, Deref)]
 pub struct BiomeRegistryConfig(pub Vec<BiomeConfig>);
 
 impl FromConfig for BiomeRegistryConfig {
-    type InnerType = Vec<(String, String)>;
+    type InnerType = Vec<BiomeConfig>;
 
     fn from_inner(asset: Self::InnerType) -> Self {
-        Self(
-            asset
-                .into_iter()
-                .map(|(name, path)| BiomeConfig {
-                    name,
-                    terrain_noise: format!("config/procgen/{path}/terrain_noise.ron"),
-                    terrain_pallet: format!("config/procgen/{path}/terrain_pallet.ron"),
-                    flora: format!("config/procgen/{path}/flora.ron"),
-                    flora_noise: format!("config/procgen/{path}/flora_noise.ron"),
-                })
-                .collect(),
-        )
+        Self(asset)
     }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
+}
+
+/// Range, from `min` inclusive to `max` exclusive, a pallet channel value must be in.
+#[derive(Reflect, Debug, Clone)]
+pub struct PalletRangeConfig {
+    /// `elevation`, `slope` or the name of a terrain noise layer, like `moisture`.
+    pub channel: String,
+    #[reflect(default = "default_range_min")]
+    pub min: f32,
+    #[reflect(default = "default_range_max")]
+    pub max: f32,
+}
+
+fn default_range_min() -> f32 {
+    f32::NEG_INFINITY
+}
+
+fn default_range_max() -> f32 {
+    f32::INFINITY
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub struct PalletEntryConfig {
+    pub tile: String,
+    /// Ranges which must all match. An entry without ranges matches everywhere.
+    #[reflect(default)]
+    pub ranges: Vec<PalletRangeConfig>,
+    /// Relative chance of this entry being picked among other equally matching entries.
+    #[reflect(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// Tiles of a single layer. When several entries match, the ones with the most ranges win and
+/// one of them is randomly picked, using their weights.
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct PalletLayerConfig {
+    /// Tile placed when no entry matches.
+    pub default: String,
+    #[reflect(default)]
+    pub entries: Vec<PalletEntryConfig>,
 }
 
 #[derive(Reflect, Default, Debug, Clone)]
 pub struct BiomePalletConfig {
-    pub floor: Vec<(f32, String)>,
-    pub wall: Vec<(f32, String)>,
+    pub floor: PalletLayerConfig,
+    pub wall: PalletLayerConfig,
+    /// Floor tile placed on atlas rivers.
+    #[reflect(default = "default_river")]
+    pub river: String,
+    /// Floor tile placed on both sides of atlas rivers.
+    #[reflect(default = "default_river_bank")]
+    pub river_bank: String,
+    /// Floor tiles preferred for the player spawn. Any walkable tile is used when none of them
+    /// is available.
+    #[reflect(default)]
+    pub spawn: Vec<String>,
+    /// Floor tiles which can't be walked on, like lakes. The river tile is never walkable.
+    #[reflect(default)]
+    pub unwalkable: Vec<String>,
+    /// Repairs floor tiles placed next to tiles they aren't allowed to. Disabled when `None`.
+    #[reflect(default)]
+    pub repair: Option<AdjacencyRepairConfig>,
+}
+
+/// Floor tiles breaking adjacency rules are generated again, together with the tiles around them,
+/// using only the floor tiles of the pallet.
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct AdjacencyRepairConfig {
+    /// Distance, in tiles, around each broken adjacency which is generated again.
+    pub radius: u8,
+    /// How many times each repair is tried, growing the radius by one tile each time.
+    pub max_attempts: u32,
+}
+
+fn default_river() -> String {
+    "WATER".to_string()
+}
+
+fn default_river_bank() -> String {
+    "SAND".to_string()
 }
 
 impl FromConfig for BiomePalletConfig {
@@ -54,4 +138,105 @@ impl FromConfig for BiomePalletConfig {
     fn from_inner(asset: Self::InnerType) -> Self {
         asset
     }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::deserialize_config;
+
+    pub use super::*;
+
+    #[test]
+    fn deserialize_pallet() {
+        // Arrange
+        const PALLET: &str = r#"
+(
+    floor: (
+        default: "STONE",
+        entries: [
+            (tile: "WATER", ranges: [(channel: "elevation", max: -0.2)]),
+            (
+                tile: "MUD",
+                ranges: [
+                    (channel: "elevation", min: -0.2, max: 0.1),
+                    (channel: "moisture", min: 0.5),
+                ],
+                weight: 2.0,
+            ),
+        ],
+    ),
+    wall: (default: "NONE"),
+    repair: Some((radius: 2, max_attempts: 3)),
+)
+    "#;
+
+        // Act
+        let config = deserialize_config::<BiomePalletConfig>(PALLET.as_bytes());
+
+        // Assert
+        assert_eq!(&config.floor.default, "STONE");
+        assert_eq!(config.floor.entries.len(), 2);
+        let water = &config.floor.entries[0];
+        assert_eq!(water.weight, 1.0);
+        assert_eq!(water.ranges[0].min, f32::NEG_INFINITY);
+        assert_eq!(water.ranges[0].max, -0.2);
+        let mud = &config.floor.entries[1];
+        assert_eq!(mud.weight, 2.0);
+        assert_eq!(&mud.ranges[1].channel, "moisture");
+        assert_eq!(mud.ranges[1].max, f32::INFINITY);
+        assert_eq!(&config.wall.default, "NONE");
+        assert!(config.wall.entries.is_empty());
+        assert_eq!(&config.river, "WATER");
+        assert!(matches!(
+            config.repair,
+            Some(AdjacencyRepairConfig {
+                radius: 2,
+                max_attempts: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn biome_config_refs() {
+        // Arrange
+        const BIOMES: &str = r#"[
+    (
+        name: "Forest",
+        terrain_noise: "forest/terrain_noise.ron",
+        terrain_pallet: "forest/terrain_pallet.ron",
+        flora: "forest/flora.ron",
+        flora_noise: "shared/flora_noise.ron",
+        structures: "forest/structures.ron",
+        resources: "forest/resources.ron",
+        resource_noise: "more_ores://config/resource_noise.ron",
+        roads: "forest/roads.ron",
+    ),
+]
+"#;
+
+        // Act
+        let config = deserialize_config::<BiomeRegistryConfig>(BIOMES.as_bytes());
+        let saved = crate::server::serialize_config(&config).unwrap();
+
+        // Assert
+        let forest = &config[0];
+        let parent = "config/procgen/biomes.ron";
+        assert_eq!(
+            forest.terrain_noise.resolve(parent).unwrap(),
+            "config/procgen/forest/terrain_noise.ron"
+        );
+        assert_eq!(
+            forest.flora_noise.resolve(parent).unwrap(),
+            "config/procgen/shared/flora_noise.ron"
+        );
+        assert_eq!(
+            forest.resource_noise.resolve(parent).unwrap(),
+            "more_ores://config/resource_noise.ron"
+        );
+        assert_eq!(saved, BIOMES);
+    }
 }
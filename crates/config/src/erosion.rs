@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+use crate::server::{ConfigServerPlugin, FromConfig};
+
+pub(crate) struct ErosionConfigPlugin;
+impl Plugin for ErosionConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((ConfigServerPlugin::<ErosionConfig>::default(),));
+    }
+}
+
+/// Particle based hydraulic erosion settings. Each droplet runs downhill, picking sediment up
+/// when it speeds up and dropping it when it slows down.
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct HydraulicErosionConfig {
+    pub droplets: u32,
+    pub max_lifetime: u32,
+    /// How much a droplet keeps its previous direction, from 0.0 to 1.0.
+    pub inertia: f32,
+    /// Sediment carried by a droplet per unit of speed, water and slope.
+    pub capacity: f32,
+    /// Minimum sediment capacity, so droplets keep eroding on flat terrain.
+    pub min_capacity: f32,
+    pub erode_speed: f32,
+    pub deposit_speed: f32,
+    pub evaporate_speed: f32,
+    pub gravity: f32,
+}
+
+/// Thermal erosion settings. Material slides down on slopes steeper than the talus.
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct ThermalErosionConfig {
+    pub iterations: u32,
+    /// Maximum stable elevation difference between neighbor cells.
+    pub talus: f32,
+    /// How much of the excess material is moved on each iteration, from 0.0 to 1.0.
+    pub rate: f32,
+}
+
+/// River tracing settings.
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct RiverConfig {
+    /// Elevation below which cells are considered sea.
+    pub sea_level: f32,
+    /// Number of upstream cells needed for a cell to become part of a river.
+    pub flow_threshold: f32,
+    /// River width, in map tiles, at the river source.
+    pub min_width: f32,
+    /// River width, in map tiles, at the river mouth.
+    pub max_width: f32,
+    /// Sand bank width, in map tiles, on each side of the river.
+    pub bank_width: f32,
+}
+
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct ErosionConfig {
+    pub hydraulic: HydraulicErosionConfig,
+    pub thermal: ThermalErosionConfig,
+    pub river: RiverConfig,
+}
+
+impl FromConfig for ErosionConfig {
+    type InnerType = Self;
+
+    fn from_inner(inner: Self::InnerType) -> Self {
+        inner
+    }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::deserialize_config;
+
+    pub use super::*;
+
+    #[test]
+    fn deserialize_erosion() {
+        // Arrange
+        const EROSION: &str = r#"
+(
+    hydraulic: (
+        droplets: 1000,
+        max_lifetime: 30,
+        inertia: 0.05,
+        capacity: 4.0,
+        min_capacity: 0.01,
+        erode_speed: 0.3,
+        deposit_speed: 0.3,
+        evaporate_speed: 0.01,
+        gravity: 4.0,
+    ),
+    thermal: (
+        iterations: 5,
+        talus: 0.02,
+        rate: 0.5,
+    ),
+    river: (
+        sea_level: 0.0,
+        flow_threshold: 50.0,
+        min_width: 1.0,
+        max_width: 5.0,
+        bank_width: 1.5,
+    ),
+)
+    "#;
+        // Act
+        let config = deserialize_config::<ErosionConfig>(EROSION.as_bytes());
+
+        // Assert
+        assert_eq!(config.hydraulic.droplets, 1000);
+        assert_eq!(config.hydraulic.max_lifetime, 30);
+        assert_eq!(config.hydraulic.gravity, 4.0);
+        assert_eq!(config.thermal.iterations, 5);
+        assert_eq!(config.thermal.talus, 0.02);
+        assert_eq!(config.river.flow_threshold, 50.0);
+        assert_eq!(config.river.bank_width, 1.5);
+    }
+}
@@ -0,0 +1,796 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use bevy::{
+    asset::{AssetTrackingSystems, UntypedAssetId},
+    ecs::system::SystemParam,
+    prelude::*,
+    reflect::Enum,
+};
+
+use crate::{
+    biome::{BiomePalletConfig, PalletLayerConfig},
+    flora::{FloraRegistryConfig, FloraSpawnRegistryConfig},
+    generator::{MapGeneratorConfig, MapGeneratorRegistryConfig},
+    mods::{BASE_LAYER, EntryOrigin, LoadOrder},
+    prefab::{PrefabCellConfig, PrefabRegistryConfig, StructureSpawnRegistryConfig},
+    resource::ResourceSpawnRegistryConfig,
+    road::RoadConfig,
+    rule::PlacementRuleConfig,
+    server::{ConfigAsset, FromConfig},
+    spans,
+    tile::TileConfigList,
+};
+
+/// Tile name which is always valid, meaning no tile at all.
+const NONE_TILE: &str = "NONE";
+
+pub(crate) struct ConfigErrorsPlugin;
+impl Plugin for ConfigErrorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConfigErrors>().add_systems(
+            PreUpdate,
+            update_reference_errors.after(AssetTrackingSystems).run_if(
+                on_message::<AssetEvent<ConfigAsset<TileConfigList>>>
+                    .or(on_message::<AssetEvent<ConfigAsset<BiomePalletConfig>>>)
+                    .or(on_message::<AssetEvent<ConfigAsset<RoadConfig>>>)
+                    .or(on_message::<AssetEvent<ConfigAsset<FloraSpawnRegistryConfig>>>)
+                    .or(on_message::<AssetEvent<ConfigAsset<FloraRegistryConfig>>>)
+                    .or(on_message::<AssetEvent<ConfigAsset<ResourceSpawnRegistryConfig>>>)
+                    .or(on_message::<AssetEvent<ConfigAsset<StructureSpawnRegistryConfig>>>)
+                    .or(on_message::<AssetEvent<ConfigAsset<PrefabRegistryConfig>>>)
+                    .or(on_message::<AssetEvent<ConfigAsset<MapGeneratorRegistryConfig>>>),
+            ),
+        );
+    }
+}
+
+/// A config which failed to load or references something which doesn't exist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// Asset path of the config, like `config/tiles.ron`, or of the mod which supplied the
+    /// broken part of it, like `more_trees://config/tiles.ron`.
+    pub path: String,
+    /// Line, starting at 1, where the error was found, when known.
+    pub line: Option<usize>,
+    /// Column, starting at 1, where the error was found, when known.
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "{}:{line}:{column}: {}", self.path, self.message)
+            }
+            (Some(line), None) => write!(f, "{}:{line}: {}", self.path, self.message),
+            _ => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+impl ConfigError {
+    /// Points errors on configs changed by mods to the layer which supplied them. Merged lists
+    /// are rewritten, so their lines don't match any file and the entry name is given instead.
+    pub(crate) fn attribute(mut self, load_order: &LoadOrder) -> Self {
+        let Some(origins) = load_order.origins(&self.path) else {
+            return self;
+        };
+
+        let origin = match origins.as_slice() {
+            [whole @ EntryOrigin { entry: None, .. }] => Some(whole.clone()),
+            // Merged lists have one entry per line, after the opening bracket.
+            entries => self
+                .line
+                .and_then(|line| entries.get(line.checked_sub(2)?))
+                .cloned(),
+        };
+
+        let Some(origin) = origin else {
+            self.line = None;
+            self.column = None;
+            return self;
+        };
+
+        if origin.layer != BASE_LAYER {
+            self.path = format!("{}://{}", origin.layer, self.path);
+        }
+
+        if let Some(entry) = origin.entry {
+            self.line = None;
+            self.column = None;
+            self.message = format!("{} (entry {entry})", self.message);
+        }
+
+        self
+    }
+}
+
+/// Every config error currently known. Load errors are kept until the same config loads
+/// successfully, while reference errors are checked again each time a config changes.
+#[derive(Resource, Default, Debug)]
+pub struct ConfigErrors {
+    load: BTreeMap<String, ConfigError>,
+    /// Asset path of the load errors of each config asset.
+    load_paths: HashMap<UntypedAssetId, String>,
+    references: Vec<ConfigError>,
+}
+
+impl ConfigErrors {
+    pub fn is_empty(&self) -> bool {
+        self.load.is_empty() && self.references.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.load.len() + self.references.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ConfigError> {
+        self.load.values().chain(&self.references)
+    }
+
+    /// Sets the load error of the given asset path. The error path may point to a mod instead.
+    pub(crate) fn set_load_error(&mut self, id: UntypedAssetId, path: String, error: ConfigError) {
+        self.load_paths.insert(id, path.clone());
+        self.load.insert(path, error);
+    }
+
+    pub(crate) fn clear_load_error(&mut self, path: &str) {
+        self.load_paths.retain(|_, p| p != path);
+        self.load.remove(path);
+    }
+
+    /// Clears the load error of a config asset which is no longer loaded.
+    pub(crate) fn clear_unused(&mut self, id: UntypedAssetId) {
+        if let Some(path) = self.load_paths.remove(&id) {
+            self.load.remove(&path);
+        }
+    }
+}
+
+/// Resolves tile and prefab names used by configs against the names of every loaded tile list
+/// and prefab registry.
+struct ReferenceChecker<'a> {
+    tiles: HashSet<&'a str>,
+    /// `None` while no prefab registry is loaded, so structures aren't checked yet.
+    prefabs: Option<HashSet<&'a str>>,
+    errors: Vec<ConfigError>,
+}
+
+impl<'a> ReferenceChecker<'a> {
+    fn new(
+        tile_lists: &[&'a ConfigAsset<TileConfigList>],
+        prefabs: &[&'a ConfigAsset<PrefabRegistryConfig>],
+    ) -> Self {
+        let mut checker = Self {
+            tiles: HashSet::from([NONE_TILE]),
+            prefabs: (!prefabs.is_empty()).then(|| {
+                prefabs
+                    .iter()
+                    .flat_map(|asset| asset.config.iter())
+                    .map(|prefab| prefab.name.as_str())
+                    .collect()
+            }),
+            errors: Vec::new(),
+        };
+
+        for asset in tile_lists {
+            for (idx, tile) in asset.config.0.iter().enumerate() {
+                if !checker.tiles.insert(&tile.name) {
+                    let path = spans::field(&spans::index("", idx), "name");
+                    checker.error(asset, &path, format!("Duplicated tile {}", tile.name));
+                }
+            }
+        }
+
+        checker
+    }
+
+    fn error<C: FromConfig>(&mut self, asset: &ConfigAsset<C>, path: &str, message: String) {
+        let location = asset
+            .spans
+            .get(path)
+            .and_then(|offset| asset.locate(offset));
+        self.errors.push(ConfigError {
+            path: location
+                .as_ref()
+                .map_or_else(|| asset.path.clone(), |(path, ..)| path.clone()),
+            line: location.as_ref().map(|(_, line, _)| *line),
+            column: location.as_ref().map(|(.., column)| *column),
+            message,
+        });
+    }
+
+    /// Checks the tile name found at the given path of the config.
+    fn tile<C: FromConfig>(
+        &mut self,
+        asset: &ConfigAsset<C>,
+        path: &str,
+        name: &str,
+        context: &str,
+    ) {
+        if !self.tiles.contains(name) {
+            self.error(asset, path, format!("Unknown tile {name} on {context}"));
+        }
+    }
+
+    fn rule<C: FromConfig>(
+        &mut self,
+        asset: &ConfigAsset<C>,
+        path: &str,
+        rule: &PlacementRuleConfig,
+        context: &str,
+    ) {
+        let path = spans::field(path, rule.variant_name());
+        match rule {
+            PlacementRuleConfig::All(rules)
+            | PlacementRuleConfig::Any(rules)
+            | PlacementRuleConfig::Not(rules) => {
+                for (idx, rule) in rules.iter().enumerate() {
+                    self.rule(asset, &spans::index(&path, idx), rule, context);
+                }
+            }
+            PlacementRuleConfig::DistanceTo { tile, .. }
+            | PlacementRuleConfig::Neighbors { tile, .. } => {
+                self.tile(asset, &spans::field(&path, "tile"), tile, context);
+            }
+            PlacementRuleConfig::Noise { .. }
+            | PlacementRuleConfig::Elevation { .. }
+            | PlacementRuleConfig::Slope { .. }
+            | PlacementRuleConfig::EdgeDistance { .. } => {}
+        }
+    }
+
+    fn tile_list(&mut self, asset: &ConfigAsset<TileConfigList>) {
+        for (idx, tile) in asset.config.0.iter().enumerate() {
+            let path = spans::field(&spans::index("", idx), "neighbors");
+            for (neighbor_idx, neighbor) in tile.neighbors.iter().enumerate() {
+                self.tile(
+                    asset,
+                    &spans::index(&path, neighbor_idx),
+                    neighbor,
+                    &format!("tile {} neighbors", tile.name),
+                );
+            }
+        }
+    }
+
+    fn pallet_layer(
+        &mut self,
+        asset: &ConfigAsset<BiomePalletConfig>,
+        layer: &PalletLayerConfig,
+        name: &str,
+    ) {
+        self.tile(
+            asset,
+            &spans::field(name, "default"),
+            &layer.default,
+            &format!("{name} default"),
+        );
+        for (idx, entry) in layer.entries.iter().enumerate() {
+            let path = spans::index(&spans::field(name, "entries"), idx);
+            self.tile(
+                asset,
+                &spans::field(&path, "tile"),
+                &entry.tile,
+                &format!("{name} entries"),
+            );
+        }
+    }
+
+    fn pallet(&mut self, asset: &ConfigAsset<BiomePalletConfig>) {
+        let pallet = &asset.config;
+        self.pallet_layer(asset, &pallet.floor, "floor");
+        self.pallet_layer(asset, &pallet.wall, "wall");
+        self.tile(asset, "river", &pallet.river, "river");
+        self.tile(asset, "river_bank", &pallet.river_bank, "river_bank");
+        for (idx, tile) in pallet.spawn.iter().enumerate() {
+            self.tile(asset, &spans::index("spawn", idx), tile, "spawn");
+        }
+        for (idx, tile) in pallet.unwalkable.iter().enumerate() {
+            self.tile(asset, &spans::index("unwalkable", idx), tile, "unwalkable");
+        }
+    }
+
+    fn roads(&mut self, asset: &ConfigAsset<RoadConfig>) {
+        self.tile(asset, "tile", &asset.config.tile, "roads tile");
+        for (idx, (tile, _)) in asset.config.terrain_costs.iter().enumerate() {
+            self.tile(
+                asset,
+                &spans::index(&spans::index("terrain_costs", idx), 0),
+                tile,
+                "roads terrain_costs",
+            );
+        }
+    }
+
+    fn flora_spawns(&mut self, asset: &ConfigAsset<FloraSpawnRegistryConfig>) {
+        for (idx, spawn) in asset.config.iter().enumerate() {
+            let path = spans::index("", idx);
+            let context = format!("flora {}", spawn.name);
+            self.tile(asset, &spans::field(&path, "flora"), &spawn.flora, &context);
+            for (terrain_idx, terrain) in spawn.allowed_terrains.iter().enumerate() {
+                self.tile(
+                    asset,
+                    &spans::index(&spans::field(&path, "allowed_terrains"), terrain_idx),
+                    terrain,
+                    &format!("{context} allowed_terrains"),
+                );
+            }
+            if let Some(rule) = &spawn.rule {
+                let path = spans::field(&path, "rule");
+                self.rule(asset, &path, rule, &format!("{context} rule"));
+            }
+        }
+    }
+
+    fn flora(&mut self, asset: &ConfigAsset<FloraRegistryConfig>) {
+        for (idx, flora) in asset.config.iter().enumerate() {
+            let path = spans::field(&spans::index("", idx), "name");
+            self.tile(asset, &path, &flora.name, "flora registry");
+        }
+    }
+
+    fn resources(&mut self, asset: &ConfigAsset<ResourceSpawnRegistryConfig>) {
+        for (idx, resource) in asset.config.iter().enumerate() {
+            let path = spans::index("", idx);
+            let context = format!("resource {}", resource.name);
+            if let Some(tile) = &resource.tile {
+                self.tile(asset, &spans::field(&path, "tile"), tile, &context);
+            }
+            for (host_idx, host) in resource.hosts.iter().enumerate() {
+                self.tile(
+                    asset,
+                    &spans::index(&spans::field(&path, "hosts"), host_idx),
+                    host,
+                    &format!("{context} hosts"),
+                );
+            }
+            if let Some(rule) = &resource.rule {
+                let path = spans::field(&path, "rule");
+                self.rule(asset, &path, rule, &format!("{context} rule"));
+            }
+        }
+    }
+
+    fn structures(&mut self, asset: &ConfigAsset<StructureSpawnRegistryConfig>) {
+        for (idx, structure) in asset.config.iter().enumerate() {
+            let path = spans::index("", idx);
+            let context = format!("structure {}", structure.name);
+            if let Some(prefabs) = &self.prefabs
+                && !prefabs.contains(structure.prefab.as_str())
+            {
+                self.error(
+                    asset,
+                    &spans::field(&path, "prefab"),
+                    format!("Unknown prefab {} on {context}", structure.prefab),
+                );
+            }
+            if let Some(rule) = &structure.rule {
+                let path = spans::field(&path, "rule");
+                self.rule(asset, &path, rule, &format!("{context} rule"));
+            }
+        }
+    }
+
+    fn prefabs(&mut self, asset: &ConfigAsset<PrefabRegistryConfig>) {
+        for (idx, prefab) in asset.config.iter().enumerate() {
+            let legend = spans::field(&spans::index("", idx), "legend");
+            let context = format!("prefab {} legend", prefab.name);
+            for (cell_idx, (_, cell)) in prefab.legend.iter().enumerate() {
+                // Cells are the second item of each legend entry.
+                let path = spans::field(
+                    &spans::index(&spans::index(&legend, cell_idx), 1),
+                    cell.variant_name(),
+                );
+                match cell {
+                    PrefabCellConfig::Tile(tile) => {
+                        self.tile(asset, &path, tile, &context);
+                    }
+                    PrefabCellConfig::Optional(tile, _) => {
+                        self.tile(asset, &spans::index(&path, 0), tile, &context);
+                    }
+                    PrefabCellConfig::Random(tiles) => {
+                        for (tile_idx, (tile, _)) in tiles.iter().enumerate() {
+                            let path = spans::index(&spans::index(&path, tile_idx), 0);
+                            self.tile(asset, &path, tile, &context);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn generators(&mut self, asset: &ConfigAsset<MapGeneratorRegistryConfig>) {
+        for (idx, (name, generator)) in asset.config.iter().enumerate() {
+            // Generators are the second item of each entry.
+            let path = spans::field(
+                &spans::index(&spans::index("", idx), 1),
+                generator.variant_name(),
+            );
+            match generator {
+                MapGeneratorConfig::Biome(_) => {}
+                MapGeneratorConfig::Cave { floor, wall, .. }
+                | MapGeneratorConfig::Dungeon { floor, wall, .. } => {
+                    let context = format!("generator {name}");
+                    self.tile(asset, &spans::field(&path, "floor"), floor, &context);
+                    self.tile(asset, &spans::field(&path, "wall"), wall, &context);
+                }
+            }
+        }
+    }
+}
+
+/// Every loaded config which references tiles or prefabs by name.
+#[derive(Default)]
+struct CheckedConfigs<'a> {
+    tile_lists: Vec<&'a ConfigAsset<TileConfigList>>,
+    pallets: Vec<&'a ConfigAsset<BiomePalletConfig>>,
+    roads: Vec<&'a ConfigAsset<RoadConfig>>,
+    flora_spawns: Vec<&'a ConfigAsset<FloraSpawnRegistryConfig>>,
+    flora: Vec<&'a ConfigAsset<FloraRegistryConfig>>,
+    resources: Vec<&'a ConfigAsset<ResourceSpawnRegistryConfig>>,
+    structures: Vec<&'a ConfigAsset<StructureSpawnRegistryConfig>>,
+    prefabs: Vec<&'a ConfigAsset<PrefabRegistryConfig>>,
+    generators: Vec<&'a ConfigAsset<MapGeneratorRegistryConfig>>,
+}
+
+fn check_references(configs: &CheckedConfigs) -> Vec<ConfigError> {
+    // Nothing can be resolved until tiles are loaded.
+    if configs.tile_lists.is_empty() {
+        return Vec::new();
+    }
+
+    let mut checker = ReferenceChecker::new(&configs.tile_lists, &configs.prefabs);
+    configs
+        .tile_lists
+        .iter()
+        .for_each(|asset| checker.tile_list(asset));
+    configs
+        .pallets
+        .iter()
+        .for_each(|asset| checker.pallet(asset));
+    configs.roads.iter().for_each(|asset| checker.roads(asset));
+    configs
+        .flora_spawns
+        .iter()
+        .for_each(|asset| checker.flora_spawns(asset));
+    configs.flora.iter().for_each(|asset| checker.flora(asset));
+    configs
+        .resources
+        .iter()
+        .for_each(|asset| checker.resources(asset));
+    configs
+        .structures
+        .iter()
+        .for_each(|asset| checker.structures(asset));
+    configs
+        .prefabs
+        .iter()
+        .for_each(|asset| checker.prefabs(asset));
+    configs
+        .generators
+        .iter()
+        .for_each(|asset| checker.generators(asset));
+
+    checker.errors
+}
+
+#[derive(SystemParam)]
+struct CheckedAssets<'w> {
+    tile_lists: Res<'w, Assets<ConfigAsset<TileConfigList>>>,
+    pallets: Res<'w, Assets<ConfigAsset<BiomePalletConfig>>>,
+    roads: Res<'w, Assets<ConfigAsset<RoadConfig>>>,
+    flora_spawns: Res<'w, Assets<ConfigAsset<FloraSpawnRegistryConfig>>>,
+    flora: Res<'w, Assets<ConfigAsset<FloraRegistryConfig>>>,
+    resources: Res<'w, Assets<ConfigAsset<ResourceSpawnRegistryConfig>>>,
+    structures: Res<'w, Assets<ConfigAsset<StructureSpawnRegistryConfig>>>,
+    prefabs: Res<'w, Assets<ConfigAsset<PrefabRegistryConfig>>>,
+    generators: Res<'w, Assets<ConfigAsset<MapGeneratorRegistryConfig>>>,
+}
+
+fn loaded<C: FromConfig>(assets: &Assets<ConfigAsset<C>>) -> Vec<&ConfigAsset<C>> {
+    assets.iter().map(|(_, asset)| asset).collect()
+}
+
+fn update_reference_errors(
+    assets: CheckedAssets,
+    load_order: Option<Res<LoadOrder>>,
+    mut errors: ResMut<ConfigErrors>,
+) {
+    let references = check_references(&CheckedConfigs {
+        tile_lists: loaded(&assets.tile_lists),
+        pallets: loaded(&assets.pallets),
+        roads: loaded(&assets.roads),
+        flora_spawns: loaded(&assets.flora_spawns),
+        flora: loaded(&assets.flora),
+        resources: loaded(&assets.resources),
+        structures: loaded(&assets.structures),
+        prefabs: loaded(&assets.prefabs),
+        generators: loaded(&assets.generators),
+    });
+    let references = match load_order {
+        Some(load_order) => references
+            .into_iter()
+            .map(|error| error.attribute(&load_order))
+            .collect(),
+        None => references,
+    };
+
+    for error in &references {
+        error!("Invalid config. {error}");
+    }
+
+    errors.references = references;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{include, mods::merge, server::deserialize_inner_type};
+
+    fn asset<C: FromConfig>(path: &str, source: &str) -> ConfigAsset<C> {
+        asset_including(path, source, &[])
+    }
+
+    fn asset_including<C: FromConfig>(
+        path: &str,
+        source: &str,
+        included: &[(&str, &str)],
+    ) -> ConfigAsset<C> {
+        let mut sources = included
+            .iter()
+            .map(|(path, source)| (path.to_string(), source.to_string()))
+            .collect::<HashMap<_, _>>();
+        let resolved = include::resolve(path, source, &sources).unwrap();
+        let (config, spans) = deserialize_inner_type(resolved.text.as_bytes()).unwrap();
+        sources.insert(path.to_string(), source.to_string());
+        ConfigAsset {
+            config: C::from_inner(config),
+            path: path.to_string(),
+            sources,
+            map: resolved.map,
+            spans,
+            refs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn included_tile_names() {
+        // Arrange
+        let tiles = asset::<TileConfigList>(
+            "config/tiles.ron",
+            r##"[(kind: Terrain, name: "GRASS", atlas: "", atlas_index: 0, map_color: "#33cc33", outline: false, blend_tech: None)]"##,
+        );
+        let roads = asset_including::<RoadConfig>(
+            "forest/roads.ron",
+            r#"extends("../shared/roads.ron", tile: "GRAS")"#,
+            &[(
+                "shared/roads.ron",
+                r#"(
+    tile: "GRASS",
+    terrain_costs: [("SAND", 2.0)],
+)"#,
+            )],
+        );
+
+        // Act
+        let errors = check_references(&CheckedConfigs {
+            tile_lists: vec![&tiles],
+            roads: vec![&roads],
+            ..default()
+        });
+
+        // Assert
+        let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                "forest/roads.ron:1:38: Unknown tile GRAS on roads tile",
+                "shared/roads.ron:3:22: Unknown tile SAND on roads terrain_costs",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_tile_names() {
+        // Arrange
+        let tiles = asset::<TileConfigList>(
+            "config/tiles.ron",
+            r##"[
+    (kind: Terrain, name: "GRASS", atlas: "", atlas_index: 0, map_color: "#33cc33", outline: false, blend_tech: None),
+    (kind: Terrain, name: "WATER", atlas: "", atlas_index: 1, map_color: "#3333cc", outline: false, blend_tech: None, neighbors: ["SNAD"]),
+]"##,
+        );
+        let pallet = asset::<BiomePalletConfig>(
+            "forest/terrain_pallet.ron",
+            r#"(
+    floor: (default: "GRASS"),
+    wall: (default: "NONE"),
+    river_bank: "GRASS",
+)"#,
+        );
+        let roads = asset::<RoadConfig>(
+            "forest/roads.ron",
+            r#"(tile: "GRASS", terrain_costs: [("SAND", 2.0)])"#,
+        );
+        let flora_spawns = asset::<FloraSpawnRegistryConfig>(
+            "forest/flora.ron",
+            r#"[
+    (
+        name: "TREE",
+        flora: "TREE",
+        threshold: 0.0,
+        wall_spacing: 1,
+        floor_spacing: 1,
+        elevation_range: None,
+        allowed_terrains: ["GRASS", "GRAS"],
+        rule: Some(Not([DistanceTo(tile: "LAVA", min: 0, max: 1)])),
+    ),
+    (
+        name: "PINE",
+        flora: "GRASS",
+        threshold: 0.0,
+        wall_spacing: 1,
+        floor_spacing: 1,
+        elevation_range: None,
+        allowed_terrains: ["GRAS"],
+    ),
+]"#,
+        );
+
+        // Act
+        let errors = check_references(&CheckedConfigs {
+            tile_lists: vec![&tiles],
+            pallets: vec![&pallet],
+            roads: vec![&roads],
+            flora_spawns: vec![&flora_spawns],
+            ..default()
+        });
+
+        // Assert
+        let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                "config/tiles.ron:3:131: Unknown tile SNAD on tile WATER neighbors",
+                "forest/roads.ron:1:34: Unknown tile SAND on roads terrain_costs",
+                "forest/flora.ron:4:16: Unknown tile TREE on flora TREE",
+                "forest/flora.ron:9:37: Unknown tile GRAS on flora TREE allowed_terrains",
+                "forest/flora.ron:10:42: Unknown tile LAVA on flora TREE rule",
+                "forest/flora.ron:19:28: Unknown tile GRAS on flora PINE allowed_terrains",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_procgen_references() {
+        // Arrange
+        let tiles = asset::<TileConfigList>(
+            "config/tiles.ron",
+            r##"[(kind: Terrain, name: "STONE", atlas: "", atlas_index: 0, map_color: "#333333", outline: false, blend_tech: None)]"##,
+        );
+        let resources = asset::<ResourceSpawnRegistryConfig>(
+            "forest/resources.ron",
+            r#"[
+    (
+        name: "iron",
+        tile: Some("IRON"),
+        hosts: ["STONE", "STONE_WAL"],
+        shape: Blob(radius: (1, 2)),
+        count: (1, 1),
+        rule: Some(DistanceTo(tile: "WATER", min: 1, max: 3)),
+    ),
+]"#,
+        );
+        let prefabs = asset::<PrefabRegistryConfig>(
+            "config/prefabs.ron",
+            r#"[
+    (
+        name: "CAMP",
+        floor: ["ds"],
+        legend: [
+            ('d', Random([("STONE", 3.0), ("DIRT", 1.0)])),
+            ('s', Tile("STONE")),
+        ],
+    ),
+]"#,
+        );
+        let structures = asset::<StructureSpawnRegistryConfig>(
+            "forest/structures.ron",
+            r#"[(name: "ruins", prefab: "RUINS", count: (1, 2), flatness: 0.1, clearance: 2)]"#,
+        );
+        let generators = asset::<MapGeneratorRegistryConfig>(
+            "config/procgen/generators.ron",
+            r#"[
+    ("overworld", Biome("Forest")),
+    ("caves", Cave(floor: "STONE", wall: "STONE_WAL", fill: 0.45, iterations: 5, birth: 5, survival: 4)),
+]"#,
+        );
+
+        // Act
+        let errors = check_references(&CheckedConfigs {
+            tile_lists: vec![&tiles],
+            resources: vec![&resources],
+            structures: vec![&structures],
+            prefabs: vec![&prefabs],
+            generators: vec![&generators],
+            ..default()
+        });
+
+        // Assert
+        let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                "forest/resources.ron:4:20: Unknown tile IRON on resource iron",
+                "forest/resources.ron:5:26: Unknown tile STONE_WAL on resource iron hosts",
+                "forest/resources.ron:8:37: Unknown tile WATER on resource iron rule",
+                "forest/structures.ron:1:26: Unknown prefab RUINS on structure ruins",
+                "config/prefabs.ron:6:44: Unknown tile DIRT on prefab CAMP legend",
+                "config/procgen/generators.ron:3:42: Unknown tile STONE_WAL on generator caves",
+            ]
+        );
+    }
+
+    #[test]
+    fn modded_errors_point_to_layer() {
+        // Arrange
+        let (tiles_source, tile_origins) = merge(&[
+            (
+                BASE_LAYER,
+                r##"[(kind: Terrain, name: "GRASS", atlas: "", atlas_index: 0, map_color: "#33cc33", outline: false, blend_tech: None)]"##,
+            ),
+            (
+                "lava",
+                r##"[
+    (kind: Terrain, name: "LAVA", atlas: "", atlas_index: 1, map_color: "#cc3333", outline: false, blend_tech: None, neighbors: ["SNAD"]),
+]"##,
+            ),
+        ]);
+        let (pallet_source, pallet_origins) = merge(&[
+            (
+                BASE_LAYER,
+                r#"(floor: (default: "GRASS"), wall: (default: "NONE"))"#,
+            ),
+            (
+                "lava",
+                r#"(
+    floor: (default: "MAGMA"),
+    wall: (default: "NONE"),
+    river: "LAVA",
+    river_bank: "GRASS",
+)"#,
+            ),
+        ]);
+
+        let load_order = LoadOrder::default();
+        load_order.record("config/tiles.ron".to_string(), tile_origins);
+        load_order.record("forest/terrain_pallet.ron".to_string(), pallet_origins);
+
+        let tiles = asset::<TileConfigList>("config/tiles.ron", &tiles_source);
+        let pallet = asset::<BiomePalletConfig>("forest/terrain_pallet.ron", &pallet_source);
+
+        // Act
+        let errors = check_references(&CheckedConfigs {
+            tile_lists: vec![&tiles],
+            pallets: vec![&pallet],
+            ..default()
+        })
+        .into_iter()
+        .map(|error| error.attribute(&load_order).to_string())
+        .collect::<Vec<_>>();
+
+        // Assert
+        assert_eq!(
+            errors,
+            vec![
+                "lava://config/tiles.ron: Unknown tile SNAD on tile LAVA neighbors (entry LAVA)",
+                "lava://forest/terrain_pallet.ron:2:22: Unknown tile MAGMA on floor default",
+            ]
+        );
+    }
+}
@@ -1,13 +1,18 @@
 use bevy::prelude::*;
 
-use crate::server::{ConfigServerPlugin, FromConfig};
+use crate::{
+    rule::PlacementRuleConfig,
+    server::{ConfigServerPlugin, FromConfig},
+    tile::TileConfigList,
+};
 
 pub(crate) struct FloraConfigPlugin;
 impl Plugin for FloraConfigPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
-            ConfigServerPlugin::<FloraRegistryConfig>::default(),
-            ConfigServerPlugin::<FloraSpawnRegistryConfig>::default(),
+            ConfigServerPlugin::<FloraRegistryConfig>::default().depends_on::<TileConfigList>(),
+            ConfigServerPlugin::<FloraSpawnRegistryConfig>::default()
+                .depends_on::<TileConfigList>(),
         ));
     }
 }
@@ -24,6 +29,8 @@ pub struct FloraConfig {
     pub sprite_region: UVec2,
     pub sprite_size: UVec2,
     pub anchor: UVec2,
+    /// Shape blocking movement, in tiles. Flora without a shape can be walked through, but still
+    /// has a tile sized sensor, so it can be cut.
     pub collision_shape: Option<CollisionShape>,
 }
 
@@ -36,6 +43,10 @@ impl FromConfig for FloraRegistryConfig {
     fn from_inner<'a>(inner: Self::InnerType) -> Self {
         Self(inner)
     }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
 }
 
 #[derive(Reflect, Default, Debug, Clone)]
@@ -47,6 +58,29 @@ pub struct FloraSpawnConfig {
     pub floor_spacing: u8,
     pub elevation_range: Option<(f32, f32)>,
     pub allowed_terrains: Vec<String>,
+    /// Relative chance of being picked when more than one flora can spawn at the same spot.
+    #[reflect(default = "default_weight")]
+    pub weight: f32,
+    /// Minimum distance, in tiles, to other flora of the same kind.
+    #[reflect(default = "default_min_distance")]
+    pub min_distance: f32,
+    /// Minimum distance, in tiles, to flora of other kinds.
+    #[reflect(default = "default_min_distance")]
+    pub species_distance: f32,
+    /// Chance, from 0.0 to 1.0, of a spot near this flora to be taken by the same kind.
+    #[reflect(default)]
+    pub clustering: f32,
+    /// Extra placement rule which must pass for this flora to spawn.
+    #[reflect(default)]
+    pub rule: Option<PlacementRuleConfig>,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+fn default_min_distance() -> f32 {
+    1.0
 }
 
 #[derive(Reflect, Default, Debug, Clone, Deref)]
@@ -58,6 +92,10 @@ impl FromConfig for FloraSpawnRegistryConfig {
     fn from_inner<'a>(inner: Self::InnerType) -> Self {
         Self(inner)
     }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
 }
 
 #[cfg(test)]
@@ -79,6 +117,14 @@ mod tests {
         floor_spacing: 3,
         elevation_range: Some((0.0, 0.5)),
         allowed_terrains: ["GRASS"],
+        weight: 2.0,
+        min_distance: 3.0,
+        species_distance: 1.5,
+        clustering: 0.7,
+        rule: Some(All([
+            DistanceTo(tile: "WATER", min: 0, max: 2),
+            Not([EdgeDistance(min: 0, max: 4)]),
+        ])),
     ),
 ]
     "#;
@@ -95,6 +141,51 @@ mod tests {
         assert_eq!(config.floor_spacing, 3);
         assert_eq!(config.elevation_range, Some((0.0, 0.5)));
         assert_eq!(config.allowed_terrains, vec!["GRASS"]);
+        assert_eq!(config.weight, 2.0);
+        assert_eq!(config.min_distance, 3.0);
+        assert_eq!(config.species_distance, 1.5);
+        assert_eq!(config.clustering, 0.7);
+        let Some(PlacementRuleConfig::All(rules)) = &config.rule else {
+            panic!("Expected an All rule, got {:?}", config.rule);
+        };
+        assert!(matches!(
+            &rules[0],
+            PlacementRuleConfig::DistanceTo { tile, min: 0, max: 2 } if tile == "WATER"
+        ));
+        assert!(matches!(
+            &rules[1],
+            PlacementRuleConfig::Not(rules)
+                if matches!(rules[..], [PlacementRuleConfig::EdgeDistance { min: 0, max: 4 }])
+        ));
+    }
+
+    #[test]
+    fn deserialize_spawn_registry_defaults() {
+        // Arrange
+        const SPAWN: &str = r#"
+[
+    (
+        name: "BUSH",
+        flora: "BUSH",
+        threshold: 0.1,
+        wall_spacing: 0,
+        floor_spacing: 0,
+        elevation_range: None,
+        allowed_terrains: [],
+    ),
+]
+    "#;
+        // Act
+        let registry: FloraSpawnRegistryConfig =
+            deserialize_config::<FloraSpawnRegistryConfig>(SPAWN.as_bytes());
+
+        // Assert
+        let config = registry.first().unwrap();
+        assert_eq!(config.weight, 1.0);
+        assert_eq!(config.min_distance, 1.0);
+        assert_eq!(config.species_distance, 1.0);
+        assert_eq!(config.clustering, 0.0);
+        assert!(config.rule.is_none());
     }
 
     #[test]
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::{
+    server::{ConfigServerPlugin, FromConfig},
+    tile::TileConfigList,
+};
+
+pub(crate) struct MapGeneratorConfigPlugin;
+impl Plugin for MapGeneratorConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((ConfigServerPlugin::<MapGeneratorRegistryConfig>::default()
+            .depends_on::<TileConfigList>(),));
+    }
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub enum MapGeneratorConfig {
+    /// Terrain noise, rivers, structures and flora of the given biome.
+    Biome(String),
+    /// Cellular automata caves.
+    Cave {
+        floor: String,
+        wall: String,
+        /// Chance, from 0.0 to 1.0, of each tile starting as a wall.
+        fill: f32,
+        iterations: u32,
+        /// A floor becomes a wall when it has at least this many wall neighbors.
+        birth: u8,
+        /// A wall stays a wall when it has at least this many wall neighbors.
+        survival: u8,
+    },
+    /// Rooms connected by corridors, using binary space partitioning.
+    Dungeon {
+        floor: String,
+        wall: String,
+        min_room: u16,
+        max_room: u16,
+        /// How many times the map is split. Each split doubles the maximum number of rooms.
+        depth: u32,
+        corridor_width: u8,
+    },
+}
+
+/// Named map generators, so maps can choose how they are generated.
+#[derive(Reflect, Default, Debug, Clone, Deref)]
+pub struct MapGeneratorRegistryConfig(pub Vec<(String, MapGeneratorConfig)>);
+
+impl FromConfig for MapGeneratorRegistryConfig {
+    type InnerType = Vec<(String, MapGeneratorConfig)>;
+
+    fn from_inner(inner: Self::InnerType) -> Self {
+        Self(inner)
+    }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::deserialize_config;
+
+    pub use super::*;
+
+    #[test]
+    fn deserialize_generator_registry() {
+        // Arrange
+        const GENERATORS: &str = r#"
+[
+    ("overworld", Biome("Forest")),
+    (
+        "caves",
+        Cave(
+            floor: "DIRT",
+            wall: "STONE_WALL",
+            fill: 0.45,
+            iterations: 5,
+            birth: 5,
+            survival: 4,
+        ),
+    ),
+    (
+        "dungeon",
+        Dungeon(
+            floor: "STONE",
+            wall: "STONE_WALL",
+            min_room: 6,
+            max_room: 20,
+            depth: 5,
+            corridor_width: 2,
+        ),
+    ),
+]
+    "#;
+        // Act
+        let registry = deserialize_config::<MapGeneratorRegistryConfig>(GENERATORS.as_bytes());
+
+        // Assert
+        assert_eq!(registry.len(), 3);
+        assert!(matches!(
+            &registry[0],
+            (name, MapGeneratorConfig::Biome(biome)) if name == "overworld" && biome == "Forest"
+        ));
+        assert!(matches!(
+            &registry[1].1,
+            MapGeneratorConfig::Cave {
+                fill: 0.45,
+                iterations: 5,
+                birth: 5,
+                survival: 4,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &registry[2].1,
+            MapGeneratorConfig::Dungeon {
+                min_room: 6,
+                max_room: 20,
+                depth: 5,
+                corridor_width: 2,
+                ..
+            }
+        ));
+    }
+}
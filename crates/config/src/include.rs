@@ -0,0 +1,655 @@
+//! Config files can reuse other files using two directives, resolved before parsing:
+//!
+//! - `include("path")` is replaced by the whole content of the given file.
+//! - `extends("path", field: value, ...)` is replaced by the struct on the given file, with the
+//!   given fields replaced or added, like `extends("../templates/fbm.ron", seed: 7)`.
+//!
+//! Paths are relative to the file using the directive. Included content is placed on a single
+//! line, so errors on the including file still point to the right line, and the resolved source
+//! keeps a [`SourceMap`] to find the file each part of it was copied from.
+
+use std::{collections::HashMap, ops::Range};
+
+use thiserror::Error;
+
+const INCLUDE: &str = "include";
+const EXTENDS: &str = "extends";
+
+#[derive(Debug, Error)]
+pub enum IncludeError {
+    #[error("Failed to read included config {path}. {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Included config {0} wasn't read")]
+    NotFound(String),
+    #[error("Config {0} includes itself")]
+    Cycle(String),
+    #[error("Invalid {directive} directive. {reason}")]
+    Invalid {
+        directive: &'static str,
+        reason: String,
+    },
+    #[error("Config {0} can't be extended, since it isn't a struct with named fields")]
+    NotStruct(String),
+    #[error("Path {path} on {file} is outside the asset folder")]
+    OutsideRoot { file: String, path: String },
+}
+
+fn invalid(directive: &'static str, reason: impl Into<String>) -> IncludeError {
+    IncludeError::Invalid {
+        directive,
+        reason: reason.into(),
+    }
+}
+
+fn is_ident(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// Index right after the string, char, comment or single byte starting at `i`.
+fn skip(bytes: &[u8], i: usize) -> usize {
+    let len = bytes.len();
+    match (bytes[i], bytes.get(i + 1)) {
+        (b'"', _) => skip_string(bytes, i + 1, 0),
+        (b'r', Some(b'"' | b'#')) if i == 0 || !is_ident(bytes[i - 1]) => {
+            let hashes = bytes[i + 1..].iter().take_while(|&&b| b == b'#').count();
+            if bytes.get(i + 1 + hashes) == Some(&b'"') {
+                skip_string(bytes, i + 2 + hashes, hashes)
+            } else {
+                i + 1
+            }
+        }
+        (b'\'', next) => {
+            // Skips the escaped char, which may be a quote.
+            let start = if next == Some(&b'\\') { i + 3 } else { i + 2 };
+            bytes
+                .get(start..)
+                .and_then(|rest| rest.iter().position(|&b| b == b'\''))
+                .map_or(len, |p| start + p + 1)
+        }
+        (b'/', Some(b'/')) => bytes[i..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(len, |p| i + p),
+        (b'/', Some(b'*')) => {
+            let mut depth = 0;
+            let mut i = i;
+            while i < len {
+                match (bytes[i], bytes.get(i + 1)) {
+                    (b'/', Some(b'*')) => {
+                        depth += 1;
+                        i += 2;
+                    }
+                    (b'*', Some(b'/')) => {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            return i;
+                        }
+                    }
+                    _ => i += 1,
+                }
+            }
+            len
+        }
+        _ => i + 1,
+    }
+}
+
+/// Index right after the closing quote of a string starting at `i`.
+fn skip_string(bytes: &[u8], mut i: usize, hashes: usize) -> usize {
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if hashes == 0 => i += 2,
+            b'"' if bytes
+                .get(i + 1..i + 1 + hashes)
+                .is_some_and(|h| h.iter().all(|&b| b == b'#')) =>
+            {
+                return i + 1 + hashes;
+            }
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn is_comment(bytes: &[u8], i: usize) -> bool {
+    bytes[i] == b'/' && matches!(bytes.get(i + 1), Some(b'/' | b'*'))
+}
+
+/// Index of the bracket closing the one at `open`.
+fn closing(bytes: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i = skip(bytes, i);
+    }
+    None
+}
+
+/// Splits the given text on commas which aren't nested inside brackets or strings. Empty parts,
+/// like the one after a trailing comma, are skipped.
+pub(crate) fn split_top_level(text: &str) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i = skip(bytes, i);
+    }
+    parts.push(&text[start..]);
+
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Replaces comments and line breaks by spaces, placing everything on a single line. Every byte
+/// is kept on the same offset.
+pub(crate) fn flatten(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut flat = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let next = skip(bytes, i);
+        if is_comment(bytes, i) {
+            flat.resize(flat.len() + next - i, b' ');
+        } else {
+            flat.extend_from_slice(&bytes[i..next]);
+        }
+        i = next;
+    }
+
+    for byte in &mut flat {
+        if matches!(byte, b'\n' | b'\r') {
+            *byte = b' ';
+        }
+    }
+
+    // Only whole comments were replaced, so it is still valid UTF-8.
+    String::from_utf8_lossy(&flat).into_owned()
+}
+
+/// Directive starting at `i`, together with the index of its opening parenthesis.
+fn directive_at(bytes: &[u8], i: usize) -> Option<(&'static str, usize)> {
+    if i > 0 && is_ident(bytes[i - 1]) {
+        return None;
+    }
+
+    [INCLUDE, EXTENDS].into_iter().find_map(|directive| {
+        let end = i + directive.len();
+        if !bytes[i..].starts_with(directive.as_bytes()) {
+            return None;
+        }
+
+        let open = end
+            + bytes[end..]
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+        (bytes.get(open) == Some(&b'(')).then_some((directive, open))
+    })
+}
+
+fn parse_path(directive: &'static str, arg: Option<&&str>) -> Result<String, IncludeError> {
+    arg.and_then(|arg| arg.strip_prefix('"'))
+        .and_then(|arg| arg.strip_suffix('"'))
+        .filter(|path| !path.contains(['"', '\\']))
+        .map(str::to_string)
+        .ok_or_else(|| invalid(directive, "The first argument must be a path string"))
+}
+
+/// Joins a path, relative to the given file, to the folder of that file. Fails if the path goes
+/// above the root of the file path, usually the asset folder.
+pub fn join(file: &str, relative: &str) -> Result<String, IncludeError> {
+    let mut components = file.split('/').collect::<Vec<_>>();
+    components.pop();
+
+    for component in relative.split('/') {
+        match component {
+            "." | "" => {}
+            ".." => {
+                components.pop().ok_or_else(|| IncludeError::OutsideRoot {
+                    file: file.to_string(),
+                    path: relative.to_string(),
+                })?;
+            }
+            _ => components.push(component),
+        }
+    }
+
+    Ok(components.join("/"))
+}
+
+/// Paths, relative to the given source file, used by every directive on it.
+pub fn references(source: &str) -> Result<Vec<String>, IncludeError> {
+    let bytes = source.as_bytes();
+    let mut paths = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some((directive, open)) = directive_at(bytes, i) {
+            let close = closing(bytes, open).ok_or_else(|| invalid(directive, "Missing `)`"))?;
+            let args = split_top_level(&source[open + 1..close]);
+            paths.push(parse_path(directive, args.first())?);
+            // Arguments may have directives too.
+            i = open + 1;
+            continue;
+        }
+        i = skip(bytes, i);
+    }
+
+    Ok(paths)
+}
+
+/// Reads every file referenced by the given source, and by the files it references. Keys of the
+/// returned map are the joined paths.
+pub fn read_sources(
+    path: &str,
+    source: &str,
+    mut read: impl FnMut(&str) -> std::io::Result<String>,
+) -> Result<HashMap<String, String>, IncludeError> {
+    let mut sources = HashMap::new();
+    let mut pending = references(source)?
+        .into_iter()
+        .map(|relative| join(path, &relative))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    while let Some(path) = pending.pop() {
+        if sources.contains_key(&path) {
+            continue;
+        }
+
+        let source = read(&path).map_err(|source| IncludeError::Read {
+            path: path.clone(),
+            source,
+        })?;
+        for relative in references(&source)? {
+            pending.push(join(&path, &relative)?);
+        }
+        sources.insert(path, source);
+    }
+
+    Ok(sources)
+}
+
+/// Line and column, both starting at 1, of the given byte offset.
+pub(crate) fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Offset of the file, and path of that file, where a part of a resolved source starts.
+#[derive(Debug, Clone)]
+struct Segment {
+    start: usize,
+    path: String,
+    offset: usize,
+}
+
+/// Maps offsets of a resolved source to the files, and offsets on them, they were copied from.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap(Vec<Segment>);
+
+impl SourceMap {
+    /// Path of the file the given offset was copied from, along with the offset on that file.
+    pub fn locate(&self, offset: usize) -> Option<(&str, usize)> {
+        let idx = self
+            .0
+            .partition_point(|s| s.start <= offset)
+            .checked_sub(1)?;
+        let segment = &self.0[idx];
+        Some((&segment.path, segment.offset + offset - segment.start))
+    }
+}
+
+/// Source with every directive replaced.
+#[derive(Debug, Default)]
+pub struct Resolved {
+    pub text: String,
+    pub map: SourceMap,
+}
+
+impl Resolved {
+    /// Appends text copied from the given offset of a file.
+    fn push_source(&mut self, text: &str, path: &str, offset: usize) {
+        if text.is_empty() {
+            return;
+        }
+
+        self.map.0.push(Segment {
+            start: self.text.len(),
+            path: path.to_string(),
+            offset,
+        });
+        self.text.push_str(text);
+    }
+
+    /// Appends part of another resolved source, keeping where it was copied from.
+    fn push_slice(&mut self, from: &Resolved, range: Range<usize>) {
+        let segments = &from.map.0;
+        let first = segments
+            .partition_point(|s| s.start <= range.start)
+            .saturating_sub(1);
+        for segment in segments[first..].iter().take_while(|s| s.start < range.end) {
+            let start = segment.start.max(range.start);
+            self.map.0.push(Segment {
+                start: self.text.len() + start - range.start,
+                path: segment.path.clone(),
+                offset: segment.offset + start - segment.start,
+            });
+        }
+        self.text.push_str(&from.text[range]);
+    }
+
+    /// Appends a slice of the text of any of the given sources. Text which isn't part of them,
+    /// like separators, is mapped as if it followed the last appended part.
+    fn push_part(&mut self, part: &str, from: [&Resolved; 2]) {
+        match from
+            .into_iter()
+            .find_map(|resolved| Some((resolved, subrange(&resolved.text, part)?)))
+        {
+            Some((resolved, range)) => self.push_slice(resolved, range),
+            None => self.text.push_str(part),
+        }
+    }
+}
+
+/// Range of the given part inside the text it was sliced from.
+fn subrange(text: &str, part: &str) -> Option<Range<usize>> {
+    let start = (part.as_ptr() as usize).checked_sub(text.as_ptr() as usize)?;
+    (start + part.len() <= text.len()).then_some(start..start + part.len())
+}
+
+/// Replaces every directive on the given source, using the files read by [`read_sources`].
+pub fn resolve(
+    path: &str,
+    source: &str,
+    sources: &HashMap<String, String>,
+) -> Result<Resolved, IncludeError> {
+    resolve_source(path, source, 0, sources, &mut vec![path.to_string()])
+}
+
+/// Resolves a part of a file, starting at the given offset of it.
+fn resolve_source(
+    path: &str,
+    source: &str,
+    offset: usize,
+    sources: &HashMap<String, String>,
+    stack: &mut Vec<String>,
+) -> Result<Resolved, IncludeError> {
+    let bytes = source.as_bytes();
+    let mut resolved = Resolved::default();
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let Some((directive, open)) = directive_at(bytes, i) else {
+            i = skip(bytes, i);
+            continue;
+        };
+
+        let close = closing(bytes, open).ok_or_else(|| invalid(directive, "Missing `)`"))?;
+        let args = resolve_source(
+            path,
+            &source[open + 1..close],
+            offset + open + 1,
+            sources,
+            stack,
+        )?;
+        let parts = split_top_level(&args.text);
+        let included = join(path, &parse_path(directive, parts.first())?)?;
+        let content = include(&included, sources, stack)?;
+
+        let value = if directive == INCLUDE {
+            if parts.len() > 1 {
+                return Err(invalid(directive, "Only a path is expected"));
+            }
+            content
+        } else {
+            extend(&included, &content, &args, &parts[1..])?
+        };
+
+        resolved.push_source(&source[copied..i], path, offset + copied);
+        resolved.push_slice(&value, 0..value.text.len());
+        copied = close + 1;
+        i = close + 1;
+    }
+    resolved.push_source(&source[copied..], path, offset + copied);
+
+    Ok(resolved)
+}
+
+fn include(
+    path: &str,
+    sources: &HashMap<String, String>,
+    stack: &mut Vec<String>,
+) -> Result<Resolved, IncludeError> {
+    if stack.iter().any(|p| p == path) {
+        return Err(IncludeError::Cycle(path.to_string()));
+    }
+
+    let source = sources
+        .get(path)
+        .ok_or_else(|| IncludeError::NotFound(path.to_string()))?;
+
+    stack.push(path.to_string());
+    let resolved = resolve_source(path, source, 0, sources, stack);
+    stack.pop();
+
+    let mut resolved = resolved?;
+    resolved.text = flatten(&resolved.text);
+    let trimmed = resolved.text.trim_start();
+    let start = resolved.text.len() - trimmed.len();
+    let end = start + trimmed.trim_end().len();
+
+    let mut content = Resolved::default();
+    content.push_slice(&resolved, start..end);
+    Ok(content)
+}
+
+/// Splits a field, like `seed: 42`, into its name and value.
+pub(crate) fn parse_field(field: &str) -> Option<(&str, &str)> {
+    let (name, value) = field.split_once(':')?;
+    let name = name.trim();
+    (!name.is_empty() && name.bytes().all(is_ident)).then_some((name, value.trim()))
+}
+
+/// Replaces the fields of the struct on `base` by the given overrides, sliced from `args`.
+fn extend(
+    path: &str,
+    base: &Resolved,
+    args: &Resolved,
+    overrides: &[&str],
+) -> Result<Resolved, IncludeError> {
+    let not_struct = || IncludeError::NotStruct(path.to_string());
+
+    let text = base.text.as_str();
+    let bytes = text.as_bytes();
+    let open = bytes.iter().take_while(|&&b| is_ident(b)).count();
+    if bytes.get(open) != Some(&b'(') || closing(bytes, open) != Some(bytes.len() - 1) {
+        return Err(not_struct());
+    }
+
+    let mut fields = split_top_level(&text[open + 1..text.len() - 1])
+        .into_iter()
+        .map(|field| parse_field(field).ok_or_else(not_struct))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for field in overrides {
+        let (name, value) = parse_field(field)
+            .ok_or_else(|| invalid(EXTENDS, format!("Expected a field, got {field}")))?;
+        match fields.iter_mut().find(|(n, _)| *n == name) {
+            Some(base) => base.1 = value,
+            None => fields.push((name, value)),
+        }
+    }
+
+    let mut extended = Resolved::default();
+    extended.push_slice(base, 0..open + 1);
+    for (idx, (name, value)) in fields.into_iter().enumerate() {
+        if idx > 0 {
+            extended.text.push_str(", ");
+        }
+        extended.push_part(name, [base, args]);
+        extended.text.push_str(": ");
+        extended.push_part(value, [base, args]);
+    }
+    extended.push_slice(base, text.len() - 1..text.len());
+
+    Ok(extended)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FBM: &str = r#"
+// Shared by every biome.
+Fbm(
+    seed: 42,
+    frequency: 0.025,
+    octaves: 4,
+    lacunarity: 2.0,
+    persistence: 0.5,
+)
+    "#;
+
+    fn sources() -> HashMap<String, String> {
+        HashMap::from([
+            ("config/templates/fbm.ron".to_string(), FBM.to_string()),
+            (
+                "config/templates/main.ron".to_string(),
+                r#"("main", Alias("base"))"#.to_string(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn extends_overrides_fields() {
+        // Arrange
+        let source = r#"[
+    ("base", extends("../templates/fbm.ron", seed: 7, frequency: 0.1)),
+    include("../templates/main.ron"),
+]"#;
+
+        // Act
+        let resolved = resolve("config/forest/noise.ron", source, &sources()).unwrap();
+
+        // Assert
+        assert_eq!(
+            resolved.text,
+            r#"[
+    ("base", Fbm(seed: 7, frequency: 0.1, octaves: 4, lacunarity: 2.0, persistence: 0.5)),
+    ("main", Alias("base")),
+]"#
+        );
+    }
+
+    #[test]
+    fn references_are_joined() {
+        // Arrange
+        let source = r#"[extends("../templates/fbm.ron", seed: 1), "include(\"a.ron\")"]"#;
+        let mut read = Vec::new();
+
+        // Act
+        let sources = read_sources("config/forest/noise.ron", source, |path| {
+            read.push(path.to_string());
+            Ok(FBM.to_string())
+        })
+        .unwrap();
+
+        // Assert
+        assert_eq!(read, vec!["config/templates/fbm.ron"]);
+        assert!(sources.contains_key("config/templates/fbm.ron"));
+    }
+
+    #[test]
+    fn include_cycle() {
+        // Arrange
+        let sources = HashMap::from([(
+            "config/a.ron".to_string(),
+            r#"include("a.ron")"#.to_string(),
+        )]);
+
+        // Act
+        let result = resolve("config/a.ron", r#"include("a.ron")"#, &sources);
+
+        // Assert
+        assert!(matches!(result, Err(IncludeError::Cycle(path)) if path == "config/a.ron"));
+    }
+
+    #[test]
+    fn resolved_offsets_point_to_included_files() {
+        // Arrange
+        let source = r#"[
+    ("base", extends("../templates/fbm.ron", seed: 7)),
+    include("../templates/main.ron"),
+]"#;
+        let sources = sources();
+        let resolved = resolve("config/forest/noise.ron", source, &sources).unwrap();
+        let locate = |text: &str| {
+            let offset = resolved.text.find(text).unwrap();
+            resolved.map.locate(offset).unwrap()
+        };
+
+        // Act
+        let seed = locate("7");
+        let octaves = locate("octaves");
+        let alias = locate("Alias");
+        let closing = locate("),\n]");
+
+        // Assert
+        assert_eq!(seed, ("config/forest/noise.ron", source.find("7").unwrap()));
+        assert_eq!(
+            octaves,
+            ("config/templates/fbm.ron", FBM.find("octaves").unwrap())
+        );
+        assert_eq!(
+            alias,
+            (
+                "config/templates/main.ron",
+                sources["config/templates/main.ron"].find("Alias").unwrap()
+            )
+        );
+        assert_eq!(closing.0, "config/templates/main.ron");
+    }
+
+    #[test]
+    fn join_above_root() {
+        // Act
+        let inside = join("config/forest/noise.ron", "../../tiles.ron");
+        let outside = join("config/forest/noise.ron", "../../../tiles.ron");
+
+        // Assert
+        assert_eq!(inside.unwrap(), "tiles.ron");
+        assert!(matches!(outside, Err(IncludeError::OutsideRoot { .. })));
+    }
+}
@@ -0,0 +1,508 @@
+//! Named input actions, like `move_up` or `action`, bound to keys, mouse buttons or gamepad
+//! buttons on `config/input.ron`. Systems query [`InputActions`] by action name instead of
+//! reading raw input, so bindings can be changed on the config or rebound at runtime.
+
+use bevy::{
+    asset::UntypedAssetId,
+    input::{
+        ButtonInput, InputSystems,
+        gamepad::{Gamepad, GamepadButton},
+        keyboard::KeyCode,
+        mouse::MouseButton,
+    },
+    platform::collections::HashMap,
+    prelude::*,
+};
+
+use crate::server::{ConfigAssetUpdated, ConfigServer, ConfigServerPlugin, Configs, FromConfig};
+
+pub(crate) struct InputConfigPlugin;
+impl Plugin for InputConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((ConfigServerPlugin::<InputBindingsConfig>::default(),));
+    }
+}
+
+/// Loads input bindings from `config/input.ron` and keeps [`InputActions`] updated.
+pub struct InputActionsPlugin;
+
+impl Plugin for InputActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputActions>()
+            .add_systems(Startup, setup)
+            .add_systems(PreUpdate, update_input_actions.after(InputSystems))
+            .add_observer(save_input_bindings);
+    }
+}
+
+/// Saves the current bindings, including runtime rebinds, back to `config/input.ron`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SaveInputBindings;
+
+/// Asset of the loaded input bindings config, where bindings are saved.
+#[derive(Resource)]
+struct InputBindingsAsset(UntypedAssetId);
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Button of any connected gamepad.
+    Gamepad(GamepadButton),
+}
+
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct InputActionConfig {
+    pub name: String,
+    pub bindings: Vec<InputBinding>,
+    /// Minimum time, in seconds, between two triggers of this action while it's held.
+    #[reflect(default)]
+    pub cooldown: f32,
+}
+
+#[derive(Reflect, Default, Debug, Clone, Deref)]
+pub struct InputBindingsConfig(pub Vec<InputActionConfig>);
+
+impl FromConfig for InputBindingsConfig {
+    type InnerType = Vec<InputActionConfig>;
+
+    fn from_inner<'a>(inner: Self::InnerType) -> Self {
+        Self(inner)
+    }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct ActionState {
+    bindings: Vec<InputBinding>,
+    cooldown: f32,
+    pressed: bool,
+    just_pressed: bool,
+    just_released: bool,
+    triggered: bool,
+    last_triggered: Option<f32>,
+}
+
+/// State of every named input action, updated on [`PreUpdate`] from the current input.
+///
+/// Unknown actions are never pressed, so systems keep working when an action is removed from
+/// the config.
+#[derive(Debug, Default, Resource)]
+pub struct InputActions(HashMap<String, ActionState>);
+
+impl InputActions {
+    pub fn from_config(config: &InputBindingsConfig) -> Self {
+        let mut actions = Self::default();
+        actions.apply_config(config);
+        actions
+    }
+
+    /// Replaces all bindings with the ones on the given config. State of actions which are still
+    /// bound is kept.
+    pub fn apply_config(&mut self, config: &InputBindingsConfig) {
+        let actions = config
+            .iter()
+            .map(|action| {
+                let state = self.0.remove(&action.name).unwrap_or_default();
+                (
+                    action.name.clone(),
+                    ActionState {
+                        bindings: action.bindings.clone(),
+                        cooldown: action.cooldown,
+                        ..state
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        self.0 = actions;
+    }
+
+    /// Current bindings as a config, sorted by action name. See [`SaveInputBindings`].
+    pub fn to_config(&self) -> InputBindingsConfig {
+        let mut actions = self
+            .0
+            .iter()
+            .map(|(name, state)| InputActionConfig {
+                name: name.clone(),
+                bindings: state.bindings.clone(),
+                cooldown: state.cooldown,
+            })
+            .collect::<Vec<_>>();
+        actions.sort_by(|a, b| a.name.cmp(&b.name));
+
+        InputBindingsConfig(actions)
+    }
+
+    /// Replaces the bindings of the given action, creating it when it doesn't exist.
+    pub fn rebind(&mut self, action: &str, bindings: Vec<InputBinding>) {
+        self.0.entry(action.to_string()).or_default().bindings = bindings;
+    }
+
+    pub fn bindings(&self, action: &str) -> &[InputBinding] {
+        self.0
+            .get(action)
+            .map_or(&[], |state| state.bindings.as_slice())
+    }
+
+    /// Whether any binding of the given action is held.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.0.get(action).is_some_and(|state| state.pressed)
+    }
+
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.0.get(action).is_some_and(|state| state.just_pressed)
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        self.0.get(action).is_some_and(|state| state.just_released)
+    }
+
+    /// Whether the given action is held and its cooldown is over. Held actions trigger once each
+    /// cooldown, or on every frame when there is no cooldown.
+    pub fn triggered(&self, action: &str) -> bool {
+        self.0.get(action).is_some_and(|state| state.triggered)
+    }
+
+    /// Updates every action using `binding` to get the state of its bindings. `now` is the
+    /// elapsed time, in seconds, used by cooldowns.
+    pub fn update(&mut self, now: f32, binding: impl Fn(&InputBinding) -> BindingState) {
+        for state in self.0.values_mut() {
+            let was_pressed = state.pressed;
+
+            state.pressed = state.bindings.iter().any(|b| binding(b).pressed);
+            // Bindings pressed and released on the same frame are never seen as held.
+            state.just_pressed =
+                !was_pressed && state.bindings.iter().any(|b| binding(b).just_pressed);
+            state.just_released =
+                !state.pressed && state.bindings.iter().any(|b| binding(b).just_released);
+            state.triggered = (state.pressed || state.just_pressed)
+                && state
+                    .last_triggered
+                    .is_none_or(|last| now - last >= state.cooldown);
+
+            if state.triggered {
+                state.last_triggered = Some(now);
+            }
+        }
+    }
+}
+
+/// State of a single binding on the current frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BindingState {
+    pub pressed: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+}
+
+fn setup(mut config_server: ConfigServer) {
+    config_server
+        .load::<InputBindingsConfig>("config/input.ron")
+        .observe(on_input_bindings_config_updated);
+}
+
+fn on_input_bindings_config_updated(
+    updated: On<ConfigAssetUpdated>,
+    configs: Configs<InputBindingsConfig>,
+    mut actions: ResMut<InputActions>,
+    mut commands: Commands,
+) {
+    let Some(config) = configs.get(updated.id()) else {
+        error!("Failed to get input bindings config");
+        return;
+    };
+
+    actions.apply_config(config);
+    commands.insert_resource(InputBindingsAsset(updated.id()));
+
+    debug!("Loaded input bindings: {:?}", actions.to_config());
+}
+
+fn save_input_bindings(
+    _: On<SaveInputBindings>,
+    actions: Res<InputActions>,
+    asset: Option<Res<InputBindingsAsset>>,
+    config_server: ConfigServer,
+) {
+    let Some(asset) = asset else {
+        error!("Input bindings can't be saved before they are loaded");
+        return;
+    };
+
+    if let Err(e) = config_server.save(asset.0, &actions.to_config()) {
+        error!("Failed to save input bindings. {e}");
+    }
+}
+
+fn update_input_actions(
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    mouse: Option<Res<ButtonInput<MouseButton>>>,
+    gamepads: Query<&Gamepad>,
+    time: Res<Time>,
+    mut actions: ResMut<InputActions>,
+) {
+    actions.update(time.elapsed_secs(), |binding| match binding {
+        InputBinding::Key(key) => keys.as_ref().map_or_else(default, |keys| BindingState {
+            pressed: keys.pressed(*key),
+            just_pressed: keys.just_pressed(*key),
+            just_released: keys.just_released(*key),
+        }),
+        InputBinding::Mouse(button) => mouse.as_ref().map_or_else(default, |mouse| BindingState {
+            pressed: mouse.pressed(*button),
+            just_pressed: mouse.just_pressed(*button),
+            just_released: mouse.just_released(*button),
+        }),
+        InputBinding::Gamepad(button) => BindingState {
+            pressed: gamepads.iter().any(|gamepad| gamepad.pressed(*button)),
+            just_pressed: gamepads.iter().any(|gamepad| gamepad.just_pressed(*button)),
+            just_released: gamepads
+                .iter()
+                .any(|gamepad| gamepad.just_released(*button)),
+        },
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::server::{
+        ConfigStatusPlugin, asset_folder, deserialize_config, serialize_config, update_until,
+    };
+
+    use super::*;
+
+    const INPUT: &str = r#"
+[
+    (
+        name: "move_up",
+        bindings: [Key(KeyW), Gamepad(DPadUp)],
+    ),
+    (
+        name: "action",
+        bindings: [Mouse(Left)],
+        cooldown: 0.5,
+    ),
+]
+    "#;
+
+    fn app() -> App {
+        let config = deserialize_config::<InputBindingsConfig>(INPUT.as_bytes());
+
+        let mut app = App::new();
+        app.init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<Time>()
+            .insert_resource(InputActions::from_config(&config))
+            .add_systems(Update, update_input_actions)
+            .add_systems(Last, clear_input);
+        app
+    }
+
+    /// Clears the input of the frame, like the input plugin does before reading new input.
+    fn clear_input(
+        mut keys: ResMut<ButtonInput<KeyCode>>,
+        mut mouse: ResMut<ButtonInput<MouseButton>>,
+    ) {
+        keys.clear();
+        mouse.clear();
+    }
+
+    #[test]
+    fn deserialize_bindings() {
+        // Act
+        let config = deserialize_config::<InputBindingsConfig>(INPUT.as_bytes());
+
+        // Assert
+        assert_eq!(config.len(), 2);
+        assert_eq!(&config[0].name, "move_up");
+        assert_eq!(
+            config[0].bindings,
+            vec![
+                InputBinding::Key(KeyCode::KeyW),
+                InputBinding::Gamepad(GamepadButton::DPadUp)
+            ]
+        );
+        assert_eq!(config[0].cooldown, 0.0);
+        assert_eq!(
+            config[1].bindings,
+            vec![InputBinding::Mouse(MouseButton::Left)]
+        );
+        assert_eq!(config[1].cooldown, 0.5);
+    }
+
+    #[test]
+    fn actions_follow_synthetic_input() {
+        // Arrange
+        let mut app = app();
+
+        // Act
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyW);
+        app.update();
+
+        // Assert
+        let actions = app.world().resource::<InputActions>();
+        assert!(actions.pressed("move_up"));
+        assert!(actions.just_pressed("move_up"));
+        assert!(!actions.pressed("action"));
+        assert!(!actions.pressed("unknown"));
+
+        // Act
+        app.update();
+
+        // Assert
+        let actions = app.world().resource::<InputActions>();
+        assert!(actions.pressed("move_up"));
+        assert!(!actions.just_pressed("move_up"));
+
+        // Act
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .release(KeyCode::KeyW);
+        app.update();
+
+        // Assert
+        let actions = app.world().resource::<InputActions>();
+        assert!(!actions.pressed("move_up"));
+        assert!(actions.just_released("move_up"));
+    }
+
+    #[test]
+    fn press_and_release_on_same_frame() {
+        // Arrange
+        let mut app = app();
+
+        // Act
+        {
+            let mut mouse = app.world_mut().resource_mut::<ButtonInput<MouseButton>>();
+            mouse.press(MouseButton::Left);
+            mouse.release(MouseButton::Left);
+        }
+        app.update();
+
+        // Assert
+        let actions = app.world().resource::<InputActions>();
+        assert!(!actions.pressed("action"));
+        assert!(actions.just_pressed("action"));
+        assert!(actions.just_released("action"));
+        assert!(actions.triggered("action"));
+
+        // Act
+        app.update();
+
+        // Assert
+        let actions = app.world().resource::<InputActions>();
+        assert!(!actions.just_pressed("action"));
+        assert!(!actions.just_released("action"));
+        assert!(!actions.triggered("action"));
+    }
+
+    #[test]
+    fn triggered_respects_cooldown() {
+        // Arrange
+        let mut app = app();
+        app.world_mut()
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(MouseButton::Left);
+
+        // Act
+        app.update();
+        let first = app.world().resource::<InputActions>().triggered("action");
+
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(200));
+        app.update();
+        let during_cooldown = app.world().resource::<InputActions>().triggered("action");
+
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(300));
+        app.update();
+        let after_cooldown = app.world().resource::<InputActions>().triggered("action");
+
+        // Assert
+        assert!(first);
+        assert!(!during_cooldown);
+        assert!(after_cooldown);
+    }
+
+    #[test]
+    fn rebind_at_runtime() {
+        // Arrange
+        let mut app = app();
+        app.world_mut()
+            .resource_mut::<InputActions>()
+            .rebind("move_up", vec![InputBinding::Key(KeyCode::ArrowUp)]);
+
+        // Act
+        {
+            let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keys.press(KeyCode::KeyW);
+            keys.press(KeyCode::ArrowUp);
+        }
+        app.update();
+
+        // Assert
+        let actions = app.world().resource::<InputActions>();
+        assert!(actions.pressed("move_up"));
+        assert_eq!(
+            actions.bindings("move_up"),
+            &[InputBinding::Key(KeyCode::ArrowUp)]
+        );
+
+        // Act
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .release(KeyCode::ArrowUp);
+        app.update();
+
+        // Assert
+        assert!(!app.world().resource::<InputActions>().pressed("move_up"));
+    }
+
+    #[test]
+    fn save_rebinds() {
+        // Arrange
+        let assets = asset_folder("input", &[("config/input.ron", INPUT)]);
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                file_path: assets.to_string_lossy().into_owned(),
+                ..default()
+            },
+            InputConfigPlugin,
+            ConfigStatusPlugin,
+            InputActionsPlugin,
+        ));
+        update_until(&mut app, |world| {
+            world.contains_resource::<InputBindingsAsset>()
+        });
+
+        app.world_mut()
+            .resource_mut::<InputActions>()
+            .rebind("move_up", vec![InputBinding::Key(KeyCode::ArrowUp)]);
+        let expected =
+            serialize_config(&app.world().resource::<InputActions>().to_config()).unwrap();
+
+        // Act
+        app.world_mut().trigger(SaveInputBindings);
+        let path = assets.join("config/input.ron");
+        update_until(&mut app, |_| {
+            std::fs::read_to_string(&path).is_ok_and(|saved| saved == expected)
+        });
+
+        // Assert
+        assert_eq!(
+            app.world().resource::<InputActions>().bindings("move_up"),
+            &[InputBinding::Key(KeyCode::ArrowUp)]
+        );
+    }
+}
@@ -2,16 +2,31 @@ use bevy::prelude::*;
 use thiserror::Error;
 
 use crate::{
-    biome::BiomeConfigPlugin, flora::FloraConfigPlugin, noise::NoiseStackConfigPlugin,
-    tile::TileConfigPlugin,
+    biome::BiomeConfigPlugin, erosion::ErosionConfigPlugin, errors::ConfigErrorsPlugin,
+    flora::FloraConfigPlugin, generator::MapGeneratorConfigPlugin, input::InputConfigPlugin,
+    noise::NoiseStackConfigPlugin, prefab::PrefabConfigPlugin, resource::ResourceConfigPlugin,
+    road::RoadConfigPlugin, server::ConfigStatusPlugin, tile::TileConfigPlugin,
+    validation::MapValidationConfigPlugin,
 };
 
 pub mod biome;
 pub mod color;
+pub mod erosion;
+pub mod errors;
 pub mod flora;
+pub mod generator;
+pub mod include;
+pub mod input;
+pub mod mods;
 pub mod noise;
+pub mod prefab;
+pub mod resource;
+pub mod road;
+pub mod rule;
 pub mod server;
+mod spans;
 pub mod tile;
+pub mod validation;
 
 pub struct ConfigPlugin;
 
@@ -22,6 +37,15 @@ impl Plugin for ConfigPlugin {
             FloraConfigPlugin,
             TileConfigPlugin,
             NoiseStackConfigPlugin,
+            PrefabConfigPlugin,
+            ErosionConfigPlugin,
+            MapGeneratorConfigPlugin,
+            MapValidationConfigPlugin,
+            ResourceConfigPlugin,
+            RoadConfigPlugin,
+            InputConfigPlugin,
+            ConfigErrorsPlugin,
+            ConfigStatusPlugin,
         ));
     }
 }
@@ -36,10 +60,33 @@ pub enum ConfigAssetLoaderError {
     RonError(#[from] ron::error::Error),
     #[error("Could not parse RON: {0}")]
     RonSpannedError(#[from] ron::error::SpannedError),
+    #[error("Could not parse RON of included config {path}: {error}")]
+    IncludedRonError {
+        path: String,
+        error: Box<ron::error::SpannedError>,
+    },
     #[error("Failed to read asset: {0}")]
     ReadAssetError(#[from] bevy::asset::ReadAssetBytesError),
+    #[error("Failed to include config: {0}")]
+    Include(#[from] include::IncludeError),
     #[error("Failed to deserialize asset. Reflect Error: {0}")]
     Reflect(String),
     #[error("Failed to load asset: {0}")]
     Error(Box<dyn std::error::Error + Send + Sync + 'static>),
 }
+
+#[derive(Debug, Error)]
+pub enum ConfigSaveError {
+    #[error("Config {0} has no asset path")]
+    NotLoaded(bevy::asset::UntypedAssetId),
+    #[error("Config uses include or extends directives, which would be lost")]
+    HasIncludes,
+    #[error("Config {0} is changed by mods, so mod entries would be saved on the base file")]
+    Modded(String),
+    #[error("Failed to check config includes: {0}")]
+    Include(#[from] include::IncludeError),
+    #[error("Could not serialize RON: {0}")]
+    RonError(#[from] ron::error::Error),
+    #[error("Failed to write config: {0}")]
+    Write(String),
+}
@@ -0,0 +1,576 @@
+//! Mods are folders inside `mods/`, with the same layout as `assets/`. They are loaded in the
+//! order given by `mods/load_order.ron`, from lowest to highest priority:
+//!
+//! ```ron
+//! ["more_trees", "hardcore"]
+//! ```
+//!
+//! Assets are read through every layer, starting with the base `assets/` folder. Registry
+//! configs, which are lists of named entries, are merged: entries with the same name are
+//! replaced and new ones are appended. Entries are named by a `name` field, like on
+//! `config/tiles.ron`, or by a leading string, like the `("main", ...)` layers of a noise stack
+//! or the generators of `config/procgen/generators.ron`, so a mod can override a single layer of
+//! a stack. Any other file, like a sprite sheet, is taken from the highest priority layer which
+//! has it. Folders list the files of every layer. Each mod is also registered as a named asset
+//! source, so `more_trees://sheets/trees.png` always reads from that mod.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    path::Path,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use bevy::{
+    asset::io::{
+        AssetReader, AssetReaderError, AssetSource, AssetSourceId, AssetWatcher, ErasedAssetReader,
+        PathStream, Reader, VecReader, file::FileAssetReader,
+    },
+    prelude::*,
+    tasks::futures_lite::{StreamExt, stream},
+};
+use thiserror::Error;
+
+use crate::{include, server::configs_ready};
+
+/// Name of the layer with the files of the `assets/` folder.
+pub const BASE_LAYER: &str = "base";
+
+const LOAD_ORDER_FILE: &str = "load_order.ron";
+
+#[derive(Debug, Error)]
+pub enum ModsError {
+    #[error("Failed to read {LOAD_ORDER_FILE}. {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse {LOAD_ORDER_FILE}. {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+/// Registers the mods as asset layers. Must be added before `AssetPlugin`, which is part of
+/// `DefaultPlugins`, since asset sources can't be registered after it.
+pub struct ModsPlugin {
+    /// Base assets folder, the same as `AssetPlugin::file_path`.
+    pub file_path: String,
+    /// Folder with the mods and their load order, relative to the same root as `file_path`.
+    pub mods_path: String,
+}
+
+impl Default for ModsPlugin {
+    fn default() -> Self {
+        Self {
+            file_path: "assets".to_string(),
+            mods_path: "mods".to_string(),
+        }
+    }
+}
+
+impl Plugin for ModsPlugin {
+    fn build(&self, app: &mut App) {
+        let mods = read_load_order(&self.mods_path).unwrap_or_else(|e| {
+            error!("Mods disabled. {e}");
+            Vec::new()
+        });
+
+        let load_order = LoadOrder {
+            mods: mods.clone(),
+            ..default()
+        };
+        app.insert_resource(load_order.clone());
+
+        if mods.is_empty() {
+            return;
+        }
+
+        info!("Loading mods: {}", mods.join(", "));
+        app.add_systems(Update, log_manifest.run_if(configs_ready.and(run_once)));
+
+        let mut layers = vec![(BASE_LAYER.to_string(), self.file_path.clone())];
+        for name in mods {
+            let path = format!("{}/{name}", self.mods_path);
+            app.register_asset_source(
+                name.clone(),
+                AssetSource::build().with_reader(AssetSource::get_default_reader(path.clone())),
+            );
+            layers.push((name, path));
+        }
+
+        let watched = layers
+            .iter()
+            .map(|(_, path)| path.clone())
+            .collect::<Vec<_>>();
+
+        app.register_asset_source(
+            AssetSourceId::Default,
+            AssetSource::build()
+                .with_reader(move || {
+                    Box::new(LayeredReader {
+                        layers: layers
+                            .iter()
+                            .map(|(name, path)| {
+                                (
+                                    name.clone(),
+                                    AssetSource::get_default_reader(path.clone())(),
+                                )
+                            })
+                            .collect(),
+                        load_order: load_order.clone(),
+                    })
+                })
+                .with_writer(AssetSource::get_default_writer(self.file_path.clone()))
+                .with_watcher(move |sender| {
+                    // Mods mirror the assets layout, so changes on any layer map to the same path.
+                    let watchers = watched
+                        .iter()
+                        .filter_map(|path| {
+                            AssetSource::get_default_watcher(
+                                path.clone(),
+                                Duration::from_millis(300),
+                            )(sender.clone())
+                        })
+                        .collect::<Vec<_>>();
+
+                    (!watchers.is_empty()).then(|| {
+                        Box::new(LayeredWatcher {
+                            _watchers: watchers,
+                        }) as Box<dyn AssetWatcher>
+                    })
+                }),
+        );
+    }
+}
+
+fn read_load_order(mods_path: &str) -> Result<Vec<String>, ModsError> {
+    let path = FileAssetReader::get_base_path()
+        .join(mods_path)
+        .join(LOAD_ORDER_FILE);
+
+    match std::fs::read_to_string(path) {
+        Ok(source) => Ok(ron::from_str(&source)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn log_manifest(load_order: Res<LoadOrder>) {
+    info!("{}", load_order.manifest());
+}
+
+/// Layer which supplied an entry of a config changed by mods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryOrigin {
+    /// Name of the list entry, or `None` when the whole file was taken from the layer.
+    pub entry: Option<String>,
+    pub layer: String,
+}
+
+/// Mods being loaded and, for every asset changed by them, which layer supplied each entry.
+#[derive(Resource, Clone, Default)]
+pub struct LoadOrder {
+    mods: Vec<String>,
+    origins: Arc<RwLock<BTreeMap<String, Vec<EntryOrigin>>>>,
+}
+
+impl LoadOrder {
+    /// Mods, from lowest to highest priority.
+    pub fn mods(&self) -> &[String] {
+        &self.mods
+    }
+
+    /// Origin of each entry of the given asset path, if any mod changed it.
+    pub fn origins(&self, path: &str) -> Option<Vec<EntryOrigin>> {
+        self.origins.read().ok()?.get(path).cloned()
+    }
+
+    pub fn is_modded(&self, path: &str) -> bool {
+        self.origins
+            .read()
+            .is_ok_and(|origins| origins.contains_key(path))
+    }
+
+    /// Lists every asset changed by mods, along with the layer which supplied each entry.
+    pub fn manifest(&self) -> String {
+        let mut manifest = format!("Load order: {BASE_LAYER}");
+        for name in &self.mods {
+            let _ = write!(manifest, ", {name}");
+        }
+
+        let Ok(origins) = self.origins.read() else {
+            return manifest;
+        };
+
+        for (path, entries) in origins.iter() {
+            let _ = write!(manifest, "\n{path}");
+            for origin in entries {
+                let entry = origin.entry.as_deref().unwrap_or("(whole file)");
+                let _ = write!(manifest, "\n    {entry}: {}", origin.layer);
+            }
+        }
+
+        manifest
+    }
+
+    pub(crate) fn record(&self, path: String, origins: Vec<EntryOrigin>) {
+        if let Ok(mut map) = self.origins.write() {
+            map.insert(path, origins);
+        }
+    }
+
+    /// Forgets the origins of an asset which is no longer changed by any mod.
+    pub(crate) fn forget(&self, path: &str) {
+        if let Ok(mut map) = self.origins.write() {
+            map.remove(path);
+        }
+    }
+}
+
+/// Default asset reader, which reads each asset through the base folder and every mod.
+struct LayeredReader {
+    layers: Vec<(String, Box<dyn ErasedAssetReader>)>,
+    load_order: LoadOrder,
+}
+
+async fn read_layer(
+    reader: &dyn ErasedAssetReader,
+    path: &Path,
+) -> Result<Option<Vec<u8>>, AssetReaderError> {
+    match reader.read(path).await {
+        Ok(mut reader) => {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(Some(bytes))
+        }
+        Err(AssetReaderError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl AssetReader for LayeredReader {
+    async fn read<'a>(&'a self, path: &'a Path) -> Result<impl Reader + 'a, AssetReaderError> {
+        // Only configs are merged, so other files are taken from the first layer found.
+        let is_config = path.extension().is_some_and(|ext| ext == "ron");
+
+        let mut found = Vec::new();
+        for (name, reader) in self.layers.iter().rev() {
+            if let Some(bytes) = read_layer(reader.as_ref(), path).await? {
+                found.push((name.as_str(), String::from_utf8_lossy(&bytes).into_owned()));
+                if !is_config {
+                    return Ok(VecReader::new(bytes));
+                }
+            }
+        }
+        found.reverse();
+
+        let asset_path = path.to_string_lossy().replace('\\', "/");
+        let bytes = match found.as_slice() {
+            [] => return Err(AssetReaderError::NotFound(path.to_path_buf())),
+            [(BASE_LAYER, source)] => {
+                self.load_order.forget(&asset_path);
+                source.clone().into_bytes()
+            }
+            layers => {
+                let layers = layers
+                    .iter()
+                    .map(|(name, source)| (*name, source.as_str()))
+                    .collect::<Vec<_>>();
+                let (merged, origins) = merge(&layers);
+                debug!(
+                    "Config {asset_path} merged from {}",
+                    layers
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                self.load_order.record(asset_path, origins);
+                merged.into_bytes()
+            }
+        };
+
+        Ok(VecReader::new(bytes))
+    }
+
+    async fn read_meta<'a>(&'a self, path: &'a Path) -> Result<impl Reader + 'a, AssetReaderError> {
+        for (_, reader) in self.layers.iter().rev() {
+            match reader.read_meta(path).await {
+                Err(AssetReaderError::NotFound(_)) => continue,
+                result => return result,
+            }
+        }
+
+        Err(AssetReaderError::NotFound(path.to_path_buf()))
+    }
+
+    async fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<PathStream>, AssetReaderError> {
+        // Files of every layer, since mods may add files to the folder.
+        let mut paths = BTreeSet::new();
+        let mut found = false;
+        for (_, reader) in &self.layers {
+            match reader.read_directory(path).await {
+                Ok(mut layer_paths) => {
+                    found = true;
+                    while let Some(path) = layer_paths.next().await {
+                        paths.insert(path);
+                    }
+                }
+                Err(AssetReaderError::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        if !found {
+            return Err(AssetReaderError::NotFound(path.to_path_buf()));
+        }
+
+        Ok(Box::new(stream::iter(paths)))
+    }
+
+    async fn is_directory<'a>(&'a self, path: &'a Path) -> Result<bool, AssetReaderError> {
+        for (_, reader) in &self.layers {
+            if reader.is_directory(path).await.unwrap_or_default() {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Keeps the watchers of every layer alive.
+struct LayeredWatcher {
+    _watchers: Vec<Box<dyn AssetWatcher>>,
+}
+
+impl AssetWatcher for LayeredWatcher {}
+
+/// Entries of a registry config, like `[(name: "GRASS"), (name: "SAND")]` or
+/// `[("base", Fbm(seed: 1)), ("main", Alias("base"))]`, along with their names. `None` when the
+/// config isn't a list or any entry has no name.
+fn registry_entries(source: &str) -> Option<Vec<(String, String)>> {
+    let flat = include::flatten(source);
+    let inner = flat.trim().strip_prefix('[')?.strip_suffix(']')?;
+
+    include::split_top_level(inner)
+        .into_iter()
+        .map(|entry| Some((entry_name(entry)?, entry.to_string())))
+        .collect()
+}
+
+/// Value of the `name` field of a list entry, or its first value on tuple entries.
+fn entry_name(entry: &str) -> Option<String> {
+    let inner = entry.strip_prefix('(')?.strip_suffix(')')?;
+    let values = include::split_top_level(inner);
+    let value = match values.first().and_then(|first| include::parse_field(first)) {
+        Some(_) => {
+            values
+                .into_iter()
+                .filter_map(include::parse_field)
+                .find(|(name, _)| *name == "name")?
+                .1
+        }
+        None => values.first()?,
+    };
+
+    Some(value.strip_prefix('"')?.strip_suffix('"')?.to_string())
+}
+
+/// Merges the given layers, from lowest to highest priority, returning the merged source and
+/// the layer which supplied each entry. Registry configs are merged by entry name, any other
+/// config is taken from the last layer.
+pub fn merge(layers: &[(&str, &str)]) -> (String, Vec<EntryOrigin>) {
+    let registries = layers
+        .iter()
+        .map(|(name, source)| Some((*name, registry_entries(source)?)))
+        .collect::<Option<Vec<_>>>();
+
+    let Some(registries) = registries else {
+        let (layer, source) = layers.last().expect("At least one layer");
+        let origin = EntryOrigin {
+            entry: None,
+            layer: layer.to_string(),
+        };
+        return (source.to_string(), vec![origin]);
+    };
+
+    let mut merged: Vec<(String, String, &str)> = Vec::new();
+    for (layer, entries) in registries {
+        for (name, entry) in entries {
+            match merged.iter_mut().find(|(n, _, _)| *n == name) {
+                Some(existing) => *existing = (name, entry, layer),
+                None => merged.push((name, entry, layer)),
+            }
+        }
+    }
+
+    let source = merged
+        .iter()
+        .map(|(_, entry, _)| format!("    {entry},\n"))
+        .collect::<String>();
+    let origins = merged
+        .into_iter()
+        .map(|(name, _, layer)| EntryOrigin {
+            entry: Some(name),
+            layer: layer.to_string(),
+        })
+        .collect();
+
+    (format!("[\n{source}]\n"), origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use bevy::tasks::block_on;
+
+    use super::*;
+    use crate::server::asset_folder;
+
+    #[test]
+    fn merge_layers() {
+        // Arrange
+        const TILES: &str = r#"[
+    // Base tiles.
+    (name: "GRASS", kind: Terrain),
+    (name: "SAND", kind: Terrain),
+]"#;
+        const MOD_TILES: &str = r#"[(name: "SAND", kind: Wall), (name: "LAVA", kind: Terrain)]"#;
+        const BIOMES: &str = r#"[(name: "Forest", flora: "forest/flora.ron")]"#;
+        const MOD_BIOMES: &str = r#"[(name: "Desert", flora: "desert/flora.ron")]"#;
+        const GENERATORS: &str = r#"[("overworld", Biome("Forest")), ("caves", Biome("Cave"))]"#;
+        const MOD_GENERATORS: &str = r#"[("overworld", Biome("Desert"))]"#;
+        const NOISE_STACK: &str = r#"[
+    ("base", Fbm(seed: 1)),
+    ("ridges", Billow(seed: 2)),
+    ("main", Multiply(source_1: "base", source_2: "ridges")),
+]"#;
+        const MOD_NOISE_STACK: &str = r#"[("flat", Fbm(seed: 3)), ("main", Alias("flat"))]"#;
+
+        // Act
+        let (tiles, tile_origins) = merge(&[(BASE_LAYER, TILES), ("lava", MOD_TILES)]);
+        let (biomes, _) = merge(&[(BASE_LAYER, BIOMES), ("desert", MOD_BIOMES)]);
+        let (generators, _) = merge(&[(BASE_LAYER, GENERATORS), ("desert", MOD_GENERATORS)]);
+        let (noise, noise_origins) =
+            merge(&[(BASE_LAYER, "Fbm(seed: 1)"), ("hard", "Fbm(seed: 2)")]);
+        let (stack, stack_origins) = merge(&[(BASE_LAYER, NOISE_STACK), ("flat", MOD_NOISE_STACK)]);
+
+        // Assert
+        assert_eq!(
+            tiles,
+            r#"[
+    (name: "GRASS", kind: Terrain),
+    (name: "SAND", kind: Wall),
+    (name: "LAVA", kind: Terrain),
+]
+"#
+        );
+        let origin = |entry: &str, layer: &str| EntryOrigin {
+            entry: Some(entry.to_string()),
+            layer: layer.to_string(),
+        };
+        assert_eq!(
+            tile_origins,
+            vec![
+                origin("GRASS", BASE_LAYER),
+                origin("SAND", "lava"),
+                origin("LAVA", "lava")
+            ]
+        );
+        assert_eq!(
+            biomes,
+            r#"[
+    (name: "Forest", flora: "forest/flora.ron"),
+    (name: "Desert", flora: "desert/flora.ron"),
+]
+"#
+        );
+        assert_eq!(
+            generators,
+            r#"[
+    ("overworld", Biome("Desert")),
+    ("caves", Biome("Cave")),
+]
+"#
+        );
+        assert_eq!(noise, "Fbm(seed: 2)");
+        assert_eq!(
+            noise_origins,
+            vec![EntryOrigin {
+                entry: None,
+                layer: "hard".to_string()
+            }]
+        );
+        assert_eq!(
+            stack,
+            r#"[
+    ("base", Fbm(seed: 1)),
+    ("ridges", Billow(seed: 2)),
+    ("main", Alias("flat")),
+    ("flat", Fbm(seed: 3)),
+]
+"#
+        );
+        assert_eq!(
+            stack_origins,
+            vec![
+                origin("base", BASE_LAYER),
+                origin("ridges", BASE_LAYER),
+                origin("main", "flat"),
+                origin("flat", "flat")
+            ]
+        );
+    }
+
+    #[test]
+    fn layered_reader() {
+        // Arrange
+        let base = asset_folder(
+            "layers_base",
+            &[
+                ("config/tiles.ron", r#"[(name: "GRASS")]"#),
+                ("config/flora.ron", r#"[(name: "TREE")]"#),
+            ],
+        );
+        let lava = asset_folder(
+            "layers_lava",
+            &[
+                ("config/tiles.ron", r#"[(name: "LAVA")]"#),
+                ("config/lava.ron", "[]"),
+            ],
+        );
+        let reader = LayeredReader {
+            layers: [(BASE_LAYER, &*base), ("lava", &*lava)]
+                .into_iter()
+                .map(|(name, path)| {
+                    let reader: Box<dyn ErasedAssetReader> = Box::new(FileAssetReader::new(path));
+                    (name.to_string(), reader)
+                })
+                .collect(),
+            load_order: LoadOrder::default(),
+        };
+        let tiles = Path::new("config/tiles.ron");
+
+        // Act
+        let listing = block_on(async {
+            let paths = AssetReader::read_directory(&reader, Path::new("config"))
+                .await
+                .unwrap();
+            paths.collect::<Vec<_>>().await
+        });
+        block_on(AssetReader::read(&reader, tiles)).unwrap();
+        let modded = reader.load_order.is_modded("config/tiles.ron");
+        std::fs::remove_file(lava.join(tiles)).unwrap();
+        block_on(AssetReader::read(&reader, tiles)).unwrap();
+
+        // Assert
+        assert_eq!(
+            listing,
+            ["config/flora.ron", "config/lava.ron", "config/tiles.ron"].map(PathBuf::from)
+        );
+        assert!(modded);
+        assert!(!reader.load_order.is_modded("config/tiles.ron"));
+    }
+}
@@ -165,4 +165,8 @@ impl FromConfig for NoiseStackConfig {
     fn from_inner<'a>(inner: Self::InnerType) -> Self {
         Self(inner)
     }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
 }
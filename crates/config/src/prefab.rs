@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+
+use crate::{
+    rule::PlacementRuleConfig,
+    server::{ConfigServerPlugin, FromConfig},
+    tile::TileConfigList,
+};
+
+pub(crate) struct PrefabConfigPlugin;
+impl Plugin for PrefabConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ConfigServerPlugin::<PrefabRegistryConfig>::default().depends_on::<TileConfigList>(),
+            ConfigServerPlugin::<StructureSpawnRegistryConfig>::default()
+                .depends_on::<TileConfigList>(),
+        ));
+    }
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub enum PrefabCellConfig {
+    /// Always places the given tile.
+    Tile(String),
+    /// Places the given tile with a chance, from 0.0 to 1.0, otherwise keeps the generated tile.
+    Optional(String, f32),
+    /// Picks one of the given tiles, using the weights as relative chances.
+    Random(Vec<(String, f32)>),
+}
+
+/// Multi-layer tile pattern, which is stamped into the map by procgen.
+///
+/// Each layer is a list of rows, from top to bottom, where each character is mapped to a cell
+/// using the `legend`. Characters not found on `legend`, like spaces, keep the generated tile.
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct PrefabConfig {
+    pub name: String,
+    #[reflect(default)]
+    pub floor: Vec<String>,
+    #[reflect(default)]
+    pub wall: Vec<String>,
+    pub legend: Vec<(char, PrefabCellConfig)>,
+    /// Player spawn marker, as the column and row of the layers, counted from the top left.
+    #[reflect(default)]
+    pub spawn: Option<(u16, u16)>,
+    /// Where roads connect to the prefab, as the column and row of the layers, counted from the
+    /// top left. Roads reach the closest tile around the prefab when `None`.
+    #[reflect(default)]
+    pub entrance: Option<(u16, u16)>,
+    /// Generates a new floor each time the prefab is stamped, using `floor` as an example. Only
+    /// `Tile` cells are generated, placing tiles next to each other only as they are on `floor`.
+    #[reflect(default)]
+    pub generate_floor: bool,
+}
+
+#[derive(Reflect, Default, Debug, Clone, Deref)]
+pub struct PrefabRegistryConfig(pub Vec<PrefabConfig>);
+
+impl FromConfig for PrefabRegistryConfig {
+    type InnerType = Vec<PrefabConfig>;
+
+    fn from_inner(inner: Self::InnerType) -> Self {
+        Self(inner)
+    }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
+}
+
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct StructureSpawnConfig {
+    /// Structure type, stored on map metadata, like "ruins" or "camp".
+    pub name: String,
+    pub prefab: String,
+    /// Minimum and maximum number of structures to place on each map.
+    pub count: (u32, u32),
+    /// Maximum elevation difference inside the prefab footprint.
+    pub flatness: f32,
+    /// Space, in tiles, around the prefab footprint which must be free of walls and structures.
+    pub clearance: u8,
+    /// Placement rule checked on the center of the prefab footprint.
+    #[reflect(default)]
+    pub rule: Option<PlacementRuleConfig>,
+}
+
+#[derive(Reflect, Default, Debug, Clone, Deref)]
+pub struct StructureSpawnRegistryConfig(pub Vec<StructureSpawnConfig>);
+
+impl FromConfig for StructureSpawnRegistryConfig {
+    type InnerType = Vec<StructureSpawnConfig>;
+
+    fn from_inner(inner: Self::InnerType) -> Self {
+        Self(inner)
+    }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::deserialize_config;
+
+    pub use super::*;
+
+    #[test]
+    fn deserialize_prefab_registry() {
+        // Arrange
+        const PREFABS: &str = r##"
+[
+    (
+        name: "RUINS",
+        floor: [
+            "ddd",
+            "d.d",
+        ],
+        wall: [
+            "#?#",
+        ],
+        legend: [
+            ('d', Tile("DIRT")),
+            ('#', Optional("STONE_WALL", 0.5)),
+            ('?', Random([("STONE_WALL", 1.0), ("NONE", 2.0)])),
+        ],
+        spawn: Some((1, 1)),
+    ),
+]
+    "##;
+        // Act
+        let registry = deserialize_config::<PrefabRegistryConfig>(PREFABS.as_bytes());
+
+        // Assert
+        let config = registry.first().unwrap();
+        assert_eq!(&config.name, "RUINS");
+        assert_eq!(config.floor, vec!["ddd", "d.d"]);
+        assert_eq!(config.wall, vec!["#?#"]);
+        assert_eq!(config.legend.len(), 3);
+        assert_eq!(config.spawn, Some((1, 1)));
+        assert!(matches!(&config.legend[0], ('d', PrefabCellConfig::Tile(tile)) if tile == "DIRT"));
+        assert!(matches!(
+            &config.legend[1],
+            ('#', PrefabCellConfig::Optional(tile, 0.5)) if tile == "STONE_WALL"
+        ));
+        assert!(matches!(
+            &config.legend[2],
+            ('?', PrefabCellConfig::Random(tiles)) if tiles.len() == 2
+        ));
+    }
+
+    #[test]
+    fn deserialize_structure_registry() {
+        // Arrange
+        const STRUCTURES: &str = r#"
+[
+    (
+        name: "ruins",
+        prefab: "RUINS",
+        count: (1, 3),
+        flatness: 0.1,
+        clearance: 2,
+        rule: Some(Elevation(min: 0.0, max: 0.2)),
+    ),
+]
+    "#;
+        // Act
+        let registry = deserialize_config::<StructureSpawnRegistryConfig>(STRUCTURES.as_bytes());
+
+        // Assert
+        let config = registry.first().unwrap();
+        assert_eq!(&config.name, "ruins");
+        assert_eq!(&config.prefab, "RUINS");
+        assert_eq!(config.count, (1, 3));
+        assert_eq!(config.flatness, 0.1);
+        assert_eq!(config.clearance, 2);
+        assert!(matches!(
+            config.rule,
+            Some(PlacementRuleConfig::Elevation { min: 0.0, max: 0.2 })
+        ));
+    }
+}
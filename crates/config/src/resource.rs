@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+
+use crate::{
+    rule::PlacementRuleConfig,
+    server::{ConfigServerPlugin, FromConfig},
+    tile::TileConfigList,
+};
+
+pub(crate) struct ResourceConfigPlugin;
+impl Plugin for ResourceConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(
+            ConfigServerPlugin::<ResourceSpawnRegistryConfig>::default()
+                .depends_on::<TileConfigList>(),
+        );
+    }
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub enum VeinShapeConfig {
+    /// Random walk with a number of steps between min and max, like ore veins following cracks.
+    RandomWalk { steps: (u32, u32) },
+    /// Roughly round patch with a radius between min and max, like clay pits or berry bushes.
+    Blob { radius: (u8, u8) },
+}
+
+impl Default for VeinShapeConfig {
+    fn default() -> Self {
+        Self::Blob { radius: (1, 1) }
+    }
+}
+
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct ResourceSpawnConfig {
+    /// Resource type, stored on map metadata, like "iron" or "clay".
+    pub name: String,
+    /// Wall tile placed on each vein tile. When `None`, the resource is only stored on map
+    /// metadata, like clay under the ground.
+    #[reflect(default)]
+    pub tile: Option<String>,
+    /// Tiles the resource can be placed on. The wall tile is checked, or the floor tile when
+    /// there is no wall. Any tile is allowed when empty.
+    #[reflect(default)]
+    pub hosts: Vec<String>,
+    pub shape: VeinShapeConfig,
+    /// Minimum and maximum number of veins on each map.
+    pub count: (u32, u32),
+    /// Veins only start where the biome resource noise is above this value.
+    #[reflect(default = "default_threshold")]
+    pub threshold: f32,
+    #[reflect(default)]
+    pub elevation_range: Option<(f32, f32)>,
+    /// Minimum and maximum distance, in tiles, to the nearest tile without a wall. Ores deep
+    /// inside mountains use a higher minimum, while floor resources use zero.
+    #[reflect(default = "default_depth")]
+    pub depth: (u8, u8),
+    /// Extra placement rule which must pass where the vein starts.
+    #[reflect(default)]
+    pub rule: Option<PlacementRuleConfig>,
+}
+
+fn default_threshold() -> f32 {
+    f32::NEG_INFINITY
+}
+
+fn default_depth() -> (u8, u8) {
+    (0, u8::MAX)
+}
+
+#[derive(Reflect, Default, Debug, Clone, Deref)]
+pub struct ResourceSpawnRegistryConfig(pub Vec<ResourceSpawnConfig>);
+
+impl FromConfig for ResourceSpawnRegistryConfig {
+    type InnerType = Vec<ResourceSpawnConfig>;
+
+    fn from_inner(inner: Self::InnerType) -> Self {
+        Self(inner)
+    }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::deserialize_config;
+
+    #[test]
+    fn deserialize_resources() {
+        // Arrange
+        const RESOURCES: &str = r#"
+[
+    (
+        name: "iron",
+        tile: Some("IRON_ORE"),
+        hosts: ["STONE_WALL"],
+        shape: RandomWalk(steps: (8, 16)),
+        count: (2, 4),
+        threshold: 0.3,
+        depth: (2, 10),
+    ),
+    (
+        name: "clay",
+        shape: Blob(radius: (2, 3)),
+        count: (1, 1),
+        elevation_range: Some((-0.2, 0.0)),
+    ),
+]
+        "#;
+
+        // Act
+        let registry = deserialize_config::<ResourceSpawnRegistryConfig>(RESOURCES.as_bytes());
+
+        // Assert
+        let iron = &registry[0];
+        assert_eq!(iron.tile.as_deref(), Some("IRON_ORE"));
+        assert_eq!(iron.hosts, vec!["STONE_WALL"]);
+        assert!(matches!(
+            iron.shape,
+            VeinShapeConfig::RandomWalk { steps: (8, 16) }
+        ));
+        assert_eq!(iron.count, (2, 4));
+        assert_eq!(iron.threshold, 0.3);
+        assert_eq!(iron.depth, (2, 10));
+
+        let clay = &registry[1];
+        assert_eq!(clay.tile, None);
+        assert!(clay.hosts.is_empty());
+        assert!(matches!(
+            clay.shape,
+            VeinShapeConfig::Blob { radius: (2, 3) }
+        ));
+        assert_eq!(clay.threshold, f32::NEG_INFINITY);
+        assert_eq!(clay.elevation_range, Some((-0.2, 0.0)));
+        assert_eq!(clay.depth, (0, u8::MAX));
+        assert!(clay.rule.is_none());
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::{
+    server::{ConfigServerPlugin, FromConfig},
+    tile::TileConfigList,
+};
+
+pub(crate) struct RoadConfigPlugin;
+impl Plugin for RoadConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ConfigServerPlugin::<RoadConfig>::default().depends_on::<TileConfigList>());
+    }
+}
+
+/// Roads connecting structures, the player spawn and map exits. Roads are routed using the
+/// cheapest path between points of interest, then painted on the floor, clearing flora along the
+/// way. Terrain walls, unwalkable floors, like rivers, and structures are never crossed.
+#[derive(Reflect, Debug, Clone)]
+pub struct RoadConfig {
+    /// Floor tile painted on roads.
+    #[reflect(default = "default_road_tile")]
+    pub tile: String,
+    /// Road width, in tiles.
+    #[reflect(default = "default_road_width")]
+    pub width: u8,
+    /// Extra cost of each elevation unit climbed between two tiles, so roads avoid steep hills.
+    #[reflect(default)]
+    pub slope_cost: f32,
+    /// Cost of walking on each floor tile. Tiles not listed cost 1.0.
+    #[reflect(default)]
+    pub terrain_costs: Vec<(String, f32)>,
+    /// Extra cost of clearing flora on the way.
+    #[reflect(default)]
+    pub flora_cost: f32,
+    /// Chance, from 0.0 to 1.0, of each map edge to have an exit. Neighbor maps agree on their
+    /// shared edge exit, as long as both biomes use the same chance.
+    #[reflect(default = "default_exit_chance")]
+    pub exit_chance: f32,
+}
+
+impl Default for RoadConfig {
+    fn default() -> Self {
+        Self {
+            tile: default_road_tile(),
+            width: default_road_width(),
+            slope_cost: 0.0,
+            terrain_costs: Vec::new(),
+            flora_cost: 0.0,
+            exit_chance: default_exit_chance(),
+        }
+    }
+}
+
+fn default_road_tile() -> String {
+    "DIRT".to_string()
+}
+
+fn default_road_width() -> u8 {
+    1
+}
+
+fn default_exit_chance() -> f32 {
+    0.5
+}
+
+impl FromConfig for RoadConfig {
+    type InnerType = Self;
+
+    fn from_inner(asset: Self::InnerType) -> Self {
+        asset
+    }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::deserialize_config;
+
+    #[test]
+    fn deserialize_roads() {
+        // Arrange
+        const ROADS: &str = r#"(width: 2, terrain_costs: [("SAND", 3.0)])"#;
+
+        // Act
+        let roads = deserialize_config::<RoadConfig>(ROADS.as_bytes());
+
+        // Assert
+        assert_eq!(&roads.tile, "DIRT");
+        assert_eq!(roads.width, 2);
+        assert_eq!(roads.terrain_costs, vec![("SAND".to_string(), 3.0)]);
+        assert_eq!(roads.exit_chance, 0.5);
+    }
+}
@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+
+/// Declarative placement condition, evaluated for each tile position.
+///
+/// Rules can be combined using [`All`](PlacementRuleConfig::All),
+/// [`Any`](PlacementRuleConfig::Any) and [`Not`](PlacementRuleConfig::Not), so complex
+/// conditions like "reeds within 2 tiles of water" can be expressed in config files.
+#[derive(Reflect, Debug, Clone)]
+#[reflect(no_field_bounds)]
+pub enum PlacementRuleConfig {
+    /// Passes when all inner rules passes. An empty list always passes.
+    All(Vec<PlacementRuleConfig>),
+    /// Passes when any inner rule passes. An empty list never passes.
+    Any(Vec<PlacementRuleConfig>),
+    /// Passes when none of the inner rules passes. This is a list since boxed values can't be
+    /// reflected, but usually it holds a single rule.
+    Not(Vec<PlacementRuleConfig>),
+    /// Noise value, from the biome flora noise, inside the given range.
+    Noise { min: f32, max: f32 },
+    /// Terrain elevation inside the given range.
+    Elevation { min: f32, max: f32 },
+    /// Elevation difference to neighbor tiles inside the given range.
+    Slope { min: f32, max: f32 },
+    /// Distance, in tiles, to the nearest tile of the given type inside the given range.
+    DistanceTo { tile: String, min: u8, max: u8 },
+    /// Number of tiles of the given type inside a square of the given radius, excluding the
+    /// center tile.
+    Neighbors {
+        tile: String,
+        radius: u8,
+        min: u8,
+        max: u8,
+    },
+    /// Distance, in tiles, to the nearest map edge inside the given range.
+    EdgeDistance { min: u16, max: u16 },
+}
@@ -1,27 +1,203 @@
-use std::marker::PhantomData;
+use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+    path::Path,
+};
 
 use bevy::{
-    asset::{AssetLoader, AssetPath, UntypedAssetId},
+    asset::{
+        AssetLoadError, AssetLoadFailedEvent, AssetLoader, AssetPath, AssetTrackingSystems,
+        LoadContext, UntypedAssetId, io::AssetSourceId,
+    },
     ecs::system::SystemParam,
     prelude::*,
-    reflect::Reflectable,
+    reflect::{FromType, ReflectMut, Reflectable, TypeRegistry},
+    tasks::IoTaskPool,
 };
+use ron::error::{Position, Span, SpannedError};
 
-use crate::ConfigAssetLoaderError;
+use crate::{
+    ConfigAssetLoaderError, ConfigSaveError,
+    errors::{ConfigError, ConfigErrors},
+    include::{self, IncludeError, Resolved, SourceMap},
+    mods::LoadOrder,
+    spans::{self, Spans},
+};
 
-pub trait FromConfig: Reflectable + Send + Sync + 'static {
+pub trait FromConfig: Reflectable + FromReflect + Send + Sync + 'static {
     type InnerType: Reflectable + FromReflect;
 
     fn from_inner(inner: Self::InnerType) -> Self;
+
+    /// Converts back to the type stored on config files, so configs can be saved.
+    fn to_inner(&self) -> Self::InnerType;
 }
 
 #[derive(Asset, Reflect)]
-struct ConfigAsset<C: FromConfig>(C);
+pub(crate) struct ConfigAsset<C: FromConfig> {
+    pub(crate) config: C,
+    /// Asset path, kept so errors can point to the file and line.
+    pub(crate) path: String,
+    /// RON sources of the config file and the files it includes, by path without asset source.
+    pub(crate) sources: HashMap<String, String>,
+    /// Files each part of the include-resolved source was copied from.
+    #[reflect(ignore)]
+    pub(crate) map: SourceMap,
+    /// Positions of the values of the include-resolved source.
+    #[reflect(ignore)]
+    pub(crate) spans: Spans,
+    /// Configs referenced by [`ConfigRef`] fields.
+    pub(crate) refs: Vec<UntypedAssetId>,
+}
+
+impl<C: FromConfig> ConfigAsset<C> {
+    /// Asset path, line and column of the file the given offset of the include-resolved source
+    /// was copied from.
+    pub(crate) fn locate(&self, offset: usize) -> Option<(String, usize, usize)> {
+        let (file, offset) = self.map.locate(offset)?;
+        let (line, column) = include::position(self.sources.get(file)?, offset);
+        let asset_path = AssetPath::parse(&self.path);
+        let path = if asset_path.path() == Path::new(file) {
+            self.path.clone()
+        } else {
+            AssetPath::from(file.to_string())
+                .with_source(asset_path.source().clone_owned())
+                .to_string()
+        };
+
+        Some((path, line, column))
+    }
+}
+
+/// Reference to another config, written on config files as its path relative to the file
+/// referencing it, like `"forest/terrain_noise.ron"`, or as a full asset path with a source, like
+/// `"more_trees://config/flora.ron"`. Referenced configs are loaded together with the config
+/// referencing them, so they are available on [`Configs::get_ref`] once it is processed.
+#[derive(Reflect)]
+#[reflect(ConfigRef, where T: FromConfig)]
+pub struct ConfigRef<T: FromConfig>(String, #[reflect(ignore)] Option<Handle<ConfigAsset<T>>>);
+
+impl<T: FromConfig> ConfigRef<T> {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self(path.into(), None)
+    }
+
+    /// Path, as written on the config file.
+    pub fn path(&self) -> &str {
+        &self.0
+    }
+
+    /// Joins the path to the folder of the given config file, unless it is a full asset path.
+    pub fn resolve(&self, parent: &str) -> Result<String, IncludeError> {
+        if self.0.contains("://") {
+            Ok(self.0.clone())
+        } else {
+            include::join(parent, &self.0)
+        }
+    }
+}
+
+impl<T: FromConfig> Default for ConfigRef<T> {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
+}
+
+impl<T: FromConfig> Clone for ConfigRef<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone())
+    }
+}
+
+impl<T: FromConfig> std::fmt::Debug for ConfigRef<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ConfigRef").field(&self.0).finish()
+    }
+}
+
+/// Loads a [`ConfigRef`] found while walking a loaded config through reflection.
+#[derive(Clone)]
+pub struct ReflectConfigRef {
+    load: fn(
+        &mut dyn PartialReflect,
+        &mut LoadContext,
+    ) -> Result<Option<UntypedAssetId>, IncludeError>,
+}
+
+impl<T: FromConfig> FromType<ConfigRef<T>> for ReflectConfigRef {
+    fn from_type() -> Self {
+        Self {
+            load: |value, load_context| {
+                let Some(config_ref) = value.try_downcast_mut::<ConfigRef<T>>() else {
+                    return Ok(None);
+                };
+                let parent = load_context.path().to_string_lossy().replace('\\', "/");
+                let mut path = AssetPath::from(config_ref.resolve(&parent)?);
+                if path.source() == &AssetSourceId::Default {
+                    path = path.with_source(load_context.asset_path().source().clone_owned());
+                }
+
+                let handle = load_context.load::<ConfigAsset<T>>(path);
+                let id = handle.id().untyped();
+                config_ref.1 = Some(handle);
+                Ok(Some(id))
+            },
+        }
+    }
+}
 
-pub(crate) struct ConfigServerPlugin<T>(PhantomData<T>);
+/// Loads every [`ConfigRef`] inside the given value. Maps and sets aren't searched.
+fn load_refs(
+    value: &mut dyn PartialReflect,
+    registry: &TypeRegistry,
+    load_context: &mut LoadContext,
+    refs: &mut Vec<UntypedAssetId>,
+) -> Result<(), IncludeError> {
+    if let Some(config_ref) = value
+        .get_represented_type_info()
+        .and_then(|info| registry.get_type_data::<ReflectConfigRef>(info.type_id()))
+    {
+        refs.extend((config_ref.load)(value, load_context)?);
+        return Ok(());
+    }
+
+    let mut visit = |field: Option<&mut dyn PartialReflect>| match field {
+        Some(field) => load_refs(field, registry, load_context, refs),
+        None => Ok(()),
+    };
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(s) => (0..s.field_len()).try_for_each(|i| visit(s.field_at_mut(i))),
+        ReflectMut::TupleStruct(s) => (0..s.field_len()).try_for_each(|i| visit(s.field_mut(i))),
+        ReflectMut::Tuple(t) => (0..t.field_len()).try_for_each(|i| visit(t.field_mut(i))),
+        ReflectMut::List(l) => (0..l.len()).try_for_each(|i| visit(l.get_mut(i))),
+        ReflectMut::Array(a) => (0..a.len()).try_for_each(|i| visit(a.get_mut(i))),
+        ReflectMut::Enum(e) => (0..e.field_len()).try_for_each(|i| visit(e.field_at_mut(i))),
+        ReflectMut::Map(_) | ReflectMut::Set(_) | ReflectMut::Opaque(_) => Ok(()),
+    }
+}
+
+pub(crate) struct ConfigServerPlugin<T> {
+    dependencies: Vec<TypeId>,
+    _marker: PhantomData<T>,
+}
 impl<T> Default for ConfigServerPlugin<T> {
     fn default() -> Self {
-        Self(Default::default())
+        Self {
+            dependencies: Vec::new(),
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<T> ConfigServerPlugin<T> {
+    /// Configs of this type are only processed once every config of type `D` is ready, and are
+    /// processed again each time a config of type `D` changes. Useful for configs referencing
+    /// data of other configs, like tile names.
+    pub(crate) fn depends_on<D: FromConfig>(mut self) -> Self {
+        self.dependencies.push(TypeId::of::<D>());
+        self
     }
 }
 
@@ -38,13 +214,123 @@ where
     T: FromConfig,
 {
     fn build(&self, app: &mut App) {
+        app.world_mut()
+            .get_resource_or_init::<ConfigDependencies>()
+            .0
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .extend(&self.dependencies);
+
         app.init_asset::<ConfigAsset<T>>()
+            .register_asset_reflect::<ConfigAsset<T>>()
             .init_asset_loader::<ConfigAssetLoader<T>>()
             .init_resource::<AssetAdded<T>>()
-            .add_systems(Update, trigger_config_asset_updated::<T>);
+            .init_resource::<ConfigErrors>()
+            .init_resource::<ConfigDependencies>()
+            .init_resource::<EditedConfigs>()
+            .init_resource::<ConfigHandlers>()
+            .add_observer(save_edited_configs::<T>)
+            .add_systems(
+                Update,
+                mark_config_pending::<T>.before(process_pending_configs),
+            )
+            .add_systems(
+                PreUpdate,
+                record_load_errors::<T>.after(AssetTrackingSystems),
+            );
+    }
+}
+
+/// Processes configs once they are loaded, respecting their dependencies, and keeps track of the
+/// loading progress.
+pub(crate) struct ConfigStatusPlugin;
+impl Plugin for ConfigStatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConfigDependencies>()
+            .init_resource::<ConfigProgress>()
+            .add_systems(Update, process_pending_configs);
     }
 }
 
+/// Config types each config type depends on.
+#[derive(Resource, Default)]
+struct ConfigDependencies(HashMap<TypeId, Vec<TypeId>>);
+
+impl ConfigDependencies {
+    fn of(&self, type_id: TypeId) -> &[TypeId] {
+        self.0.get(&type_id).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigStatus {
+    /// The config file is still being loaded.
+    Loading,
+    /// Loaded, but [`ConfigAssetUpdated`] wasn't triggered yet, since it waits for the configs
+    /// it depends on.
+    Pending,
+    /// Loaded and processed.
+    Ready,
+    /// The config failed to load. See [`ConfigErrors`](crate::errors::ConfigErrors).
+    Failed,
+}
+
+/// Type and asset of a loaded config, without its generic type.
+#[derive(Component)]
+struct ConfigKind {
+    type_id: TypeId,
+    id: UntypedAssetId,
+    /// Configs referenced by this one, which is processed again when any of them changes.
+    refs: Vec<UntypedAssetId>,
+}
+
+/// How many of the requested configs are ready.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct ConfigProgress {
+    pub ready: usize,
+    pub total: usize,
+    settled: bool,
+}
+
+impl ConfigProgress {
+    /// Whether every config is ready, including configs requested while processing others.
+    pub fn is_ready(&self) -> bool {
+        self.settled && self.total > 0 && self.ready == self.total
+    }
+
+    /// Loading progress, from 0.0 to 1.0.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.ready as f32 / self.total as f32
+        }
+    }
+}
+
+/// Run condition which passes once every config is ready.
+pub fn configs_ready(progress: Res<ConfigProgress>) -> bool {
+    progress.is_ready()
+}
+
+/// Configs changed in memory since they were loaded, like configs tuned on the inspector.
+#[derive(Resource, Default, Debug)]
+pub struct EditedConfigs(HashSet<UntypedAssetId>);
+
+impl EditedConfigs {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, id: UntypedAssetId) -> bool {
+        self.0.contains(&id)
+    }
+}
+
+/// Saves every config in [`EditedConfigs`] back to its file, see [`ConfigServer::save`].
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SaveEditedConfigs;
+
 #[derive(EntityEvent)]
 pub struct ConfigAssetUpdated {
     entity: Entity,
@@ -59,20 +345,189 @@ impl ConfigAssetUpdated {
 #[derive(Component, Debug)]
 struct ConfigHandler<C: FromConfig>(Handle<ConfigAsset<C>>);
 
+/// Handler entity of each loaded config, shared by every caller which loaded it.
+#[derive(Resource, Default)]
+struct ConfigHandlers(HashMap<UntypedAssetId, SharedHandler>);
+
+struct SharedHandler {
+    entity: Entity,
+    /// Loads not unloaded yet.
+    users: usize,
+}
+
 #[derive(SystemParam)]
 pub struct ConfigServer<'w, 's> {
     asset_server: Res<'w, AssetServer>,
+    load_order: Option<Res<'w, LoadOrder>>,
+    handlers: ResMut<'w, ConfigHandlers>,
     commands: Commands<'w, 's>,
 }
 
 impl<'w, 's> ConfigServer<'w, 's> {
+    /// Spawns a handler entity which triggers [`ConfigAssetUpdated`] each time the config at the
+    /// given path is loaded or changed. Loading a path which already has a handler returns that
+    /// handler instead, and processes the config again so observers added by the new caller are
+    /// triggered too. Each load must be paired with an [`unload`](Self::unload).
     pub fn load<'a, 'p, C>(&'a mut self, path: impl Into<AssetPath<'p>>) -> EntityCommands<'a>
     where
         C: FromConfig,
     {
-        self.commands
-            .spawn(ConfigHandler::<C>(self.asset_server.load(path)))
+        let handle = self.asset_server.load::<ConfigAsset<C>>(path.into());
+        let id = handle.id().untyped();
+
+        // The handler may have been despawned without being unloaded.
+        if let Some(shared) = self.handlers.0.get_mut(&id)
+            && self.commands.get_entity(shared.entity).is_ok()
+        {
+            shared.users += 1;
+            let mut entity = self.commands.entity(shared.entity);
+            entity.queue(process_again);
+            return entity;
+        }
+
+        let entity = self.spawn_handler(handle);
+        self.handlers
+            .0
+            .insert(id, SharedHandler { entity, users: 1 });
+        self.commands.entity(entity)
+    }
+
+    /// Releases a handler returned by [`ConfigServer::load`]. Once every caller which loaded it
+    /// released it, the handler is despawned along with its observers, and the config asset is
+    /// dropped.
+    pub fn unload(&mut self, handler: Entity) {
+        let shared = self
+            .handlers
+            .0
+            .iter_mut()
+            .find(|(_, shared)| shared.entity == handler);
+        if let Some((&id, shared)) = shared {
+            shared.users -= 1;
+            if shared.users > 0 {
+                return;
+            }
+            self.handlers.0.remove(&id);
+        }
+
+        self.commands.entity(handler).try_despawn();
+    }
+
+    fn spawn_handler<C>(&mut self, handle: Handle<ConfigAsset<C>>) -> Entity
+    where
+        C: FromConfig,
+    {
+        let id = handle.id().untyped();
+
+        // The asset may be already loaded by another handler, so it won't be added again.
+        let status = if self.asset_server.is_loaded_with_dependencies(&handle) {
+            ConfigStatus::Pending
+        } else {
+            ConfigStatus::Loading
+        };
+
+        let mut entity = self.commands.spawn((
+            ConfigKind {
+                type_id: TypeId::of::<C>(),
+                id,
+                refs: Vec::new(),
+            },
+            status,
+            ConfigHandler::<C>(handle),
+        ));
+
+        if status == ConfigStatus::Pending {
+            entity.queue(copy_refs::<C>);
+        }
+
+        entity.id()
+    }
+
+    /// Triggers [`ConfigAssetUpdated`] again for every loaded config of the given type, once the
+    /// configs it depends on are ready. Useful when configs are resolved against data which
+    /// changed after they were loaded.
+    pub fn refresh<C>(&mut self)
+    where
+        C: FromConfig,
+    {
+        self.commands.queue(|world: &mut World| {
+            let mut handlers = world.query::<(&ConfigHandler<C>, &mut ConfigStatus)>();
+            for (_, mut status) in handlers.iter_mut(world) {
+                if *status == ConfigStatus::Ready {
+                    *status = ConfigStatus::Pending;
+                }
+            }
+        });
+    }
+
+    /// Writes the given config back to the file of the config asset with the given id. Once
+    /// written, the config is reloaded, triggering [`ConfigAssetUpdated`] as usual.
+    ///
+    /// The file is written in the background and write errors are only logged. Configs using
+    /// `include` or `extends` directives aren't overwritten, since their templates would be lost.
+    pub fn save<C>(&self, id: UntypedAssetId, config: &C) -> Result<(), ConfigSaveError>
+    where
+        C: FromConfig,
+    {
+        let path = self
+            .asset_server
+            .get_path(id)
+            .ok_or(ConfigSaveError::NotLoaded(id))?
+            .into_owned();
+
+        let asset_path = path.path().to_string_lossy().replace('\\', "/");
+        if self
+            .load_order
+            .as_ref()
+            .is_some_and(|order| order.is_modded(&asset_path))
+        {
+            return Err(ConfigSaveError::Modded(asset_path));
+        }
+
+        let ron = serialize_config(config)?;
+        let asset_server = self.asset_server.clone();
+
+        IoTaskPool::get()
+            .spawn(async move {
+                match write_config(&asset_server, &path, &ron).await {
+                    Ok(()) => {
+                        debug!("Config {path} saved");
+                        asset_server.reload(path);
+                    }
+                    Err(e) => error!("Failed to save config {path}. {e}"),
+                }
+            })
+            .detach();
+
+        Ok(())
+    }
+}
+
+async fn write_config(
+    asset_server: &AssetServer,
+    path: &AssetPath<'_>,
+    ron: &str,
+) -> Result<(), ConfigSaveError> {
+    let source = asset_server
+        .get_source(path.source())
+        .map_err(|e| ConfigSaveError::Write(e.to_string()))?;
+
+    if let Ok(mut reader) = source.reader().read(path.path()).await {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(|e| ConfigSaveError::Write(e.to_string()))?;
+        if !include::references(&String::from_utf8_lossy(&bytes))?.is_empty() {
+            return Err(ConfigSaveError::HasIncludes);
+        }
     }
+
+    source
+        .writer()
+        .map_err(|e| ConfigSaveError::Write(e.to_string()))?
+        .write_bytes(path.path(), ron.as_bytes())
+        .await
+        .map_err(|e| ConfigSaveError::Write(e.to_string()))
 }
 
 #[derive(SystemParam)]
@@ -88,32 +543,225 @@ where
     C: FromConfig,
 {
     pub fn get(&self, id: UntypedAssetId) -> Option<&C> {
-        self.assets.get(id.typed()).map(|asset| &asset.0)
+        self.assets.get(id.typed()).map(|asset| &asset.config)
+    }
+
+    /// Config referenced by the given [`ConfigRef`], once it is loaded.
+    pub fn get_ref(&self, config_ref: &ConfigRef<C>) -> Option<&C> {
+        self.assets
+            .get(config_ref.1.as_ref()?)
+            .map(|asset| &asset.config)
+    }
+}
+
+/// Copies the refs of an already loaded config to its handler, since they are only copied when
+/// the config is loaded.
+fn copy_refs<C: FromConfig>(mut entity: EntityWorldMut) {
+    let Some(id) = entity
+        .get::<ConfigKind>()
+        .map(|kind| kind.id.typed::<ConfigAsset<C>>())
+    else {
+        return;
+    };
+
+    let refs = entity
+        .world()
+        .get_resource::<Assets<ConfigAsset<C>>>()
+        .and_then(|assets| assets.get(id))
+        .map(|asset| asset.refs.clone());
+
+    if let (Some(refs), Some(mut kind)) = (refs, entity.get_mut::<ConfigKind>()) {
+        kind.refs = refs;
+    }
+}
+
+/// Marks a processed config as pending, so [`ConfigAssetUpdated`] is triggered again on its
+/// handler.
+fn process_again(mut entity: EntityWorldMut) {
+    if let Some(mut status) = entity.get_mut::<ConfigStatus>()
+        && *status == ConfigStatus::Ready
+    {
+        *status = ConfigStatus::Pending;
     }
 }
 
-fn trigger_config_asset_updated<C: FromConfig>(
+fn mark_config_pending<C: FromConfig>(
     mut reader: MessageReader<AssetEvent<ConfigAsset<C>>>,
-    mut commands: Commands,
-    handlers: Query<(Entity, &ConfigHandler<C>)>,
+    assets: Res<Assets<ConfigAsset<C>>>,
+    mut handlers: Query<(&mut ConfigKind, &mut ConfigStatus)>,
+    mut edited: ResMut<EditedConfigs>,
 ) {
     for &msg in reader.read() {
         match msg {
-            AssetEvent::Added { id } | AssetEvent::Modified { id } => {
-                let Some((entity, _)) = handlers.iter().find(|(_, handler)| handler.0.id() == id)
-                else {
-                    error!("Config handler not found for asset {id}");
-                    continue;
-                };
+            // Sent on every load and reload, once referenced configs are loaded too.
+            AssetEvent::LoadedWithDependencies { id } => {
+                let refs = assets
+                    .get(id)
+                    .map(|asset| asset.refs.clone())
+                    .unwrap_or_default();
+                let id = id.untyped();
+                edited.0.remove(&id);
+
+                for (mut kind, mut status) in &mut handlers {
+                    if kind.id == id {
+                        kind.refs.clone_from(&refs);
+                        *status = ConfigStatus::Pending;
+                    } else if kind.refs.contains(&id) && *status == ConfigStatus::Ready {
+                        *status = ConfigStatus::Pending;
+                    }
+                }
+            }
+            AssetEvent::Removed { id } => {
+                // Handlers still holding the asset must wait until it is loaded again.
+                let id = id.untyped();
+                edited.0.remove(&id);
+                for (_, mut status) in handlers.iter_mut().filter(|(kind, _)| kind.id == id) {
+                    *status = ConfigStatus::Loading;
+                }
+            }
+            // Also sent on reloads, but those are followed by `LoadedWithDependencies`.
+            AssetEvent::Modified { id } => {
+                edited.0.insert(id.untyped());
+            }
+            // Unused configs have no handlers left, and their errors are dropped on
+            // `record_load_errors`.
+            AssetEvent::Added { .. } | AssetEvent::Unused { .. } => continue,
+        }
+    }
+}
+
+fn save_edited_configs<C: FromConfig>(
+    _: On<SaveEditedConfigs>,
+    assets: Res<Assets<ConfigAsset<C>>>,
+    edited: Res<EditedConfigs>,
+    config_server: ConfigServer,
+) {
+    for (id, asset) in assets.iter() {
+        if !edited.contains(id.untyped()) {
+            continue;
+        }
+
+        match config_server.save(id.untyped(), &asset.config) {
+            Ok(()) => info!("Saving config {}", asset.path),
+            Err(e) => error!("Failed to save config {}. {e}", asset.path),
+        }
+    }
+}
 
-                commands.entity(entity).trigger(|e| ConfigAssetUpdated {
-                    entity: e,
-                    id: id.untyped(),
-                });
+/// Triggers [`ConfigAssetUpdated`] for pending configs whose dependencies are ready. Configs
+/// depending on the ones processed are processed again on the next frame, after the observers
+/// of their dependencies have run.
+fn process_pending_configs(
+    mut q_configs: Query<(Entity, &ConfigKind, &mut ConfigStatus)>,
+    dependencies: Res<ConfigDependencies>,
+    mut progress: ResMut<ConfigProgress>,
+    mut commands: Commands,
+) {
+    // Config types which can't be used yet, since some of their configs aren't ready.
+    let mut requested = HashSet::new();
+    let mut blocked = HashSet::new();
+    for (_, kind, status) in &q_configs {
+        requested.insert(kind.type_id);
+        if *status != ConfigStatus::Ready {
+            blocked.insert(kind.type_id);
+        }
+    }
+    let is_blocked = |type_id: &TypeId| blocked.contains(type_id) || !requested.contains(type_id);
+
+    let mut processed = HashSet::new();
+    for (entity, kind, mut status) in &mut q_configs {
+        if *status != ConfigStatus::Pending || dependencies.of(kind.type_id).iter().any(is_blocked)
+        {
+            continue;
+        }
+
+        let id = kind.id;
+        commands
+            .entity(entity)
+            .trigger(|entity| ConfigAssetUpdated { entity, id });
+        *status = ConfigStatus::Ready;
+        processed.insert(kind.type_id);
+    }
+
+    for (_, kind, mut status) in &mut q_configs {
+        if *status == ConfigStatus::Ready
+            && dependencies
+                .of(kind.type_id)
+                .iter()
+                .any(|d| processed.contains(d))
+        {
+            *status = ConfigStatus::Pending;
+        }
+    }
+
+    let statuses = q_configs.iter().map(|(_, _, status)| *status);
+    *progress = ConfigProgress {
+        ready: statuses
+            .clone()
+            .filter(|s| *s == ConfigStatus::Ready)
+            .count(),
+        total: statuses.count(),
+        // Observers triggered now may request more configs.
+        settled: processed.is_empty(),
+    };
+}
+
+/// Keeps [`ConfigErrors`] in sync with config load failures, like missing files or invalid RON.
+/// Errors are cleared once the same path loads successfully again.
+fn record_load_errors<C: FromConfig>(
+    mut loaded: MessageReader<AssetEvent<ConfigAsset<C>>>,
+    mut failed: MessageReader<AssetLoadFailedEvent<ConfigAsset<C>>>,
+    assets: Res<Assets<ConfigAsset<C>>>,
+    mut handlers: Query<(&ConfigHandler<C>, &mut ConfigStatus)>,
+    load_order: Option<Res<LoadOrder>>,
+    mut errors: ResMut<ConfigErrors>,
+) {
+    for msg in loaded.read() {
+        match msg {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                if let Some(asset) = assets.get(*id) {
+                    errors.clear_load_error(&asset.path);
+                }
             }
-            _ => continue,
+            // Failed configs which are no longer loaded won't be fixed by a reload.
+            AssetEvent::Unused { id } => errors.clear_unused(id.untyped()),
+            _ => {}
         }
     }
+
+    for msg in failed.read() {
+        for (_, mut status) in handlers.iter_mut().filter(|(h, _)| h.0.id() == msg.id) {
+            *status = ConfigStatus::Failed;
+        }
+
+        let (path, position, message) = match &msg.error {
+            AssetLoadError::AssetLoaderError(e) => match e.error().downcast_ref() {
+                Some(ConfigAssetLoaderError::RonSpannedError(e)) => {
+                    (None, Some(e.span.start), e.code.to_string())
+                }
+                Some(ConfigAssetLoaderError::IncludedRonError { path, error }) => (
+                    Some(path.clone()),
+                    Some(error.span.start),
+                    error.code.to_string(),
+                ),
+                Some(e) => (None, None, e.to_string()),
+                None => (None, None, e.error().to_string()),
+            },
+            e => (None, None, e.to_string()),
+        };
+
+        let error = ConfigError {
+            path: path.unwrap_or_else(|| msg.path.to_string()),
+            line: position.map(|p| p.line),
+            column: position.map(|p| p.col),
+            message,
+        };
+        let error = match &load_order {
+            Some(load_order) => error.attribute(load_order),
+            None => error,
+        };
+        errors.set_load_error(msg.id.untyped(), msg.path.to_string(), error);
+    }
 }
 
 struct ConfigAssetLoader<T>(PhantomData<T>);
@@ -138,33 +786,128 @@ where
         &self,
         reader: &mut dyn bevy::asset::io::Reader,
         _settings: &Self::Settings,
-        _load_context: &mut bevy::asset::LoadContext<'_>,
+        load_context: &mut bevy::asset::LoadContext<'_>,
     ) -> std::result::Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
 
-        let config: T::InnerType = deserialize_inner_type(&bytes)?;
+        let source = String::from_utf8_lossy(&bytes).into_owned();
+        let path = load_context.path().to_string_lossy().replace('\\', "/");
+
+        // Included files are read as loader dependencies, so changing any of them reloads this
+        // config too.
+        let mut sources = HashMap::new();
+        let mut pending = include::references(&source)?
+            .into_iter()
+            .map(|relative| include::join(&path, &relative))
+            .collect::<Result<Vec<_>, _>>()?;
+        while let Some(included) = pending.pop() {
+            if sources.contains_key(&included) {
+                continue;
+            }
+
+            let included_path = AssetPath::from(included.clone())
+                .with_source(load_context.asset_path().source().clone_owned());
+            let bytes = load_context.read_asset_bytes(included_path).await?;
+            let included_source = String::from_utf8_lossy(&bytes).into_owned();
+            for relative in include::references(&included_source)? {
+                pending.push(include::join(&included, &relative)?);
+            }
+            sources.insert(included, included_source);
+        }
+
+        let resolved = include::resolve(&path, &source, &sources)?;
+        sources.insert(path, source);
+        let (mut config, spans): (T::InnerType, _) =
+            deserialize_inner_type(resolved.text.as_bytes()).map_err(|error| match error {
+                ConfigAssetLoaderError::RonSpannedError(error) => {
+                    relocate(error, &resolved, &sources, load_context)
+                }
+                error => error,
+            })?;
+
+        let mut refs = Vec::new();
+        load_refs(
+            config.as_partial_reflect_mut(),
+            &config_registry::<T::InnerType>(),
+            load_context,
+            &mut refs,
+        )?;
+
+        Ok(ConfigAsset {
+            config: T::from_inner(config),
+            path: load_context.asset_path().to_string(),
+            sources,
+            map: resolved.map,
+            spans,
+            refs,
+        })
+    }
+}
+
+/// Moves the position of a parse error on an include-resolved source to the file it comes from.
+fn relocate(
+    mut error: SpannedError,
+    resolved: &Resolved,
+    sources: &HashMap<String, String>,
+    load_context: &LoadContext,
+) -> ConfigAssetLoaderError {
+    let locate = |position: Position| {
+        let (file, offset) = resolved.map.locate(offset(&resolved.text, position))?;
+        let (line, col) = include::position(sources.get(file)?, offset);
+        Some((file, Position { line, col }))
+    };
+    let (Some((file, start)), Some((_, end))) = (locate(error.span.start), locate(error.span.end))
+    else {
+        return error.into();
+    };
 
-        let asset = T::from_inner(config);
+    error.span = Span { start, end };
+    if Path::new(file) == load_context.path() {
+        return error.into();
+    }
 
-        Ok(ConfigAsset(asset))
+    let path = AssetPath::from(file.to_string())
+        .with_source(load_context.asset_path().source().clone_owned());
+    ConfigAssetLoaderError::IncludedRonError {
+        path: path.to_string(),
+        error: Box::new(error),
     }
 }
 
-pub(crate) fn deserialize_inner_type<C>(bytes: &[u8]) -> Result<C, ConfigAssetLoaderError>
+/// Byte offset of the given line and column, both starting at 1.
+fn offset(source: &str, position: Position) -> usize {
+    let line_start = source
+        .split_inclusive('\n')
+        .take(position.line.saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+    let line = &source[line_start..];
+    line.char_indices()
+        .map(|(idx, _)| idx)
+        .nth(position.col.saturating_sub(1))
+        .map_or(source.len(), |idx| line_start + idx)
+}
+
+/// Registry with the given config type and every type used by it.
+fn config_registry<C: Reflectable>() -> TypeRegistry {
+    let mut registry = TypeRegistry::new();
+    registry.register_derived_types();
+    registry.register::<C>();
+    registry
+}
+
+/// Deserializes a config, together with the positions of its values.
+pub(crate) fn deserialize_inner_type<C>(bytes: &[u8]) -> Result<(C, Spans), ConfigAssetLoaderError>
 where
     C: Reflectable + FromReflect,
 {
     use ron::extensions::Extensions;
-    use serde::de::DeserializeSeed;
 
     let opts = ron::Options::default()
         .with_default_extension(Extensions::IMPLICIT_SOME | Extensions::UNWRAP_NEWTYPES);
 
-    let mut registry = bevy::reflect::TypeRegistry::new();
-    registry.register_derived_types();
-    registry.register::<C>();
-
+    let registry = config_registry::<C>();
     let registration = registry
         .get(std::any::TypeId::of::<C>())
         .expect("Just registered it!");
@@ -173,7 +916,10 @@ where
 
     let reflect_deserializer =
         bevy::reflect::serde::TypedReflectDeserializer::new(registration, &registry);
-    let deserialized = reflect_deserializer.deserialize(&mut deserializer)?;
+    // Errors raised while deserializing have no position, so attach the parser one.
+    let source = std::str::from_utf8(bytes).unwrap_or_default();
+    let (deserialized, spans) = spans::record(source, &mut deserializer, reflect_deserializer)
+        .map_err(|e| deserializer.span_error(e))?;
 
     assert!(deserialized.as_partial_reflect().represents::<C>());
 
@@ -184,7 +930,37 @@ where
         )));
     };
 
-    Ok(config)
+    Ok((config, spans))
+}
+
+/// Parses a config the same way config assets are loaded, so configs can be used without an
+/// `App`.
+pub fn parse_config<C>(bytes: &[u8]) -> Result<C, ConfigAssetLoaderError>
+where
+    C: FromConfig,
+{
+    deserialize_inner_type(bytes).map(|(config, _)| C::from_inner(config))
+}
+
+/// Serializes a config the same way configs are saved, as pretty RON using the config files
+/// conventions: four spaces indentation, explicit `Some` and unwrapped newtypes.
+pub fn serialize_config<C>(config: &C) -> Result<String, ConfigSaveError>
+where
+    C: FromConfig,
+{
+    use ron::extensions::Extensions;
+
+    let inner = config.to_inner();
+
+    let registry = config_registry::<C::InnerType>();
+    let serializer =
+        bevy::reflect::serde::TypedReflectSerializer::new(inner.as_partial_reflect(), &registry);
+    let pretty = ron::ser::PrettyConfig::default().indentor("    ");
+    let ron = ron::Options::default()
+        .with_default_extension(Extensions::UNWRAP_NEWTYPES)
+        .to_string_pretty(&serializer, pretty)?;
+
+    Ok(ron + "\n")
 }
 
 #[cfg(test)]
@@ -192,7 +968,323 @@ pub(crate) fn deserialize_config<C>(bytes: &[u8]) -> C
 where
     C: FromConfig,
 {
-    let inner = deserialize_inner_type(bytes).unwrap();
+    parse_config(bytes).unwrap()
+}
+
+/// Asset folder on the temp dir, removed when dropped, even if the test fails.
+#[cfg(test)]
+pub(crate) struct AssetFolder(std::path::PathBuf);
+
+#[cfg(test)]
+impl std::ops::Deref for AssetFolder {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for AssetFolder {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Asset folder on the temp dir, with the given files.
+#[cfg(test)]
+pub(crate) fn asset_folder(name: &str, files: &[(&str, &str)]) -> AssetFolder {
+    let folder = std::env::temp_dir().join(format!("eternal_config_{name}_{}", std::process::id()));
+    for (file, content) in files {
+        let file = folder.join(file);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, content).unwrap();
+    }
+    AssetFolder(folder)
+}
+
+/// Updates the app until the condition passes, panicking after a few seconds.
+#[cfg(test)]
+pub(crate) fn update_until(app: &mut App, condition: impl Fn(&World) -> bool) {
+    let start = std::time::Instant::now();
+    while !condition(app.world()) {
+        assert!(
+            start.elapsed() < std::time::Duration::from_secs(10),
+            "Timed out"
+        );
+        app.update();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bevy::{ecs::system::RunSystemOnce, tasks::block_on};
+
+    use super::*;
+    use crate::{
+        biome::BiomePalletConfig,
+        noise::{NoiseFnConfig, NoiseStackConfig},
+        tile::TileConfigList,
+    };
 
-    C::from_inner(inner)
+    #[test]
+    fn parse_error_position() {
+        // Arrange
+        const TILES: &str = r#"
+[
+    (
+        name: "GRASS",
+        kind: Terain,
+    ),
+]
+        "#;
+
+        // Act
+        let result = parse_config::<TileConfigList>(TILES.as_bytes());
+
+        // Assert
+        let Err(ConfigAssetLoaderError::RonSpannedError(e)) = result else {
+            panic!("Expected a spanned error, got {result:?}");
+        };
+        assert_eq!(e.span.start.line, 5);
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        // Arrange
+        const NOISE: &str = r#"[
+    ("base", Fbm(
+        seed: 42,
+        frequency: 0.025,
+        octaves: 4,
+        lacunarity: 2.0,
+        persistence: 0.5,
+    )),
+    ("main", Alias("base")),
+]
+"#;
+        let config = deserialize_config::<NoiseStackConfig>(NOISE.as_bytes());
+
+        // Act
+        let ron = serialize_config(&config).unwrap();
+
+        // Assert
+        assert_eq!(ron, NOISE);
+    }
+
+    fn spawn_config<C: FromConfig>(world: &mut World, status: ConfigStatus) -> Entity {
+        world
+            .spawn((
+                ConfigKind {
+                    type_id: TypeId::of::<C>(),
+                    id: AssetId::<ConfigAsset<C>>::default().untyped(),
+                    refs: Vec::new(),
+                },
+                status,
+            ))
+            .id()
+    }
+
+    #[test]
+    fn dependencies_processed_first() {
+        // Arrange
+        let mut world = World::new();
+        world.init_resource::<ConfigProgress>();
+        world.insert_resource(ConfigDependencies(HashMap::from([(
+            TypeId::of::<BiomePalletConfig>(),
+            vec![TypeId::of::<TileConfigList>()],
+        )])));
+        let tiles = spawn_config::<TileConfigList>(&mut world, ConfigStatus::Loading);
+        let pallet = spawn_config::<BiomePalletConfig>(&mut world, ConfigStatus::Pending);
+        let status = |world: &World, entity| *world.get::<ConfigStatus>(entity).unwrap();
+
+        // Act
+        world.run_system_cached(process_pending_configs).unwrap();
+        let waiting = status(&world, pallet);
+
+        *world.get_mut::<ConfigStatus>(tiles).unwrap() = ConfigStatus::Pending;
+        world.run_system_cached(process_pending_configs).unwrap();
+        let processed = (status(&world, tiles), status(&world, pallet));
+
+        world.run_system_cached(process_pending_configs).unwrap();
+        let settling = world.resource::<ConfigProgress>().is_ready();
+        world.run_system_cached(process_pending_configs).unwrap();
+
+        // Assert
+        assert_eq!(waiting, ConfigStatus::Pending);
+        assert_eq!(processed, (ConfigStatus::Ready, ConfigStatus::Pending));
+        assert_eq!(status(&world, pallet), ConfigStatus::Ready);
+        assert!(!settling);
+        assert!(world.resource::<ConfigProgress>().is_ready());
+    }
+
+    #[test]
+    fn loaded_config_handler_has_refs() {
+        // Arrange
+        let mut world = World::new();
+        let mut assets = Assets::<ConfigAsset<TileConfigList>>::default();
+        let noise = AssetId::<ConfigAsset<NoiseStackConfig>>::default().untyped();
+        let handle = assets.add(ConfigAsset {
+            config: TileConfigList::default(),
+            path: "config/tiles.ron".to_string(),
+            sources: HashMap::new(),
+            map: SourceMap::default(),
+            spans: Spans::default(),
+            refs: vec![noise],
+        });
+        world.insert_resource(assets);
+        let handler = world
+            .spawn(ConfigKind {
+                type_id: TypeId::of::<TileConfigList>(),
+                id: handle.id().untyped(),
+                refs: Vec::new(),
+            })
+            .id();
+
+        // Act
+        copy_refs::<TileConfigList>(world.entity_mut(handler));
+
+        // Assert
+        assert_eq!(world.get::<ConfigKind>(handler).unwrap().refs, vec![noise]);
+    }
+
+    #[derive(Resource, Default)]
+    struct Updates(Vec<UntypedAssetId>);
+
+    fn noise_app(assets: &Path) -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                file_path: assets.to_string_lossy().into_owned(),
+                ..default()
+            },
+            ConfigServerPlugin::<NoiseStackConfig>::default(),
+            ConfigStatusPlugin,
+        ))
+        .init_resource::<Updates>();
+        app
+    }
+
+    #[test]
+    fn handlers_shared_by_path() {
+        // Arrange
+        let mut app = noise_app(Path::new("shared"));
+        let load = |world: &mut World| {
+            world
+                .run_system_once(|mut config_server: ConfigServer| {
+                    config_server.load::<NoiseStackConfig>("noise.ron").id()
+                })
+                .unwrap()
+        };
+        let unload = |world: &mut World, handler: Entity| {
+            world
+                .run_system_once(move |mut config_server: ConfigServer| {
+                    config_server.unload(handler);
+                })
+                .unwrap();
+        };
+        let first = load(app.world_mut());
+        let second = load(app.world_mut());
+
+        // Act
+        unload(app.world_mut(), first);
+        let kept = app.world().get_entity(first).is_ok();
+        unload(app.world_mut(), second);
+
+        // Assert
+        assert_eq!(first, second);
+        assert!(kept);
+        assert!(app.world().get_entity(first).is_err());
+    }
+
+    #[test]
+    fn save_writes_and_reloads() {
+        // Arrange
+        let assets = asset_folder(
+            "save",
+            &[
+                (
+                    "noise.ron",
+                    r#"[("main", Fbm(seed: 1, frequency: 0.05, octaves: 4, lacunarity: 2.0, persistence: 0.5))]"#,
+                ),
+                ("modded.ron", "[]"),
+                ("templated.ron", r#"[("main", include("noise.ron"))]"#),
+            ],
+        );
+        let mut app = noise_app(&assets);
+        app.world_mut()
+            .run_system_once(|mut config_server: ConfigServer| {
+                config_server.load::<NoiseStackConfig>("noise.ron").observe(
+                    |updated: On<ConfigAssetUpdated>, mut updates: ResMut<Updates>| {
+                        updates.0.push(updated.id());
+                    },
+                );
+            })
+            .unwrap();
+        update_until(&mut app, |world| world.resource::<Updates>().0.len() == 1);
+
+        // Tuned in memory, like on the inspector.
+        let id = app.world().resource::<Updates>().0[0];
+        let mut configs = app
+            .world_mut()
+            .resource_mut::<Assets<ConfigAsset<NoiseStackConfig>>>();
+        let config = &mut configs.get_mut(id.typed()).unwrap().config;
+        let NoiseFnConfig::Fbm { seed, .. } = &mut config.0[0].1 else {
+            panic!("Expected a Fbm noise");
+        };
+        *seed = 7;
+        let expected = serialize_config(config).unwrap();
+        update_until(&mut app, |world| {
+            world.resource::<EditedConfigs>().contains(id)
+        });
+
+        let load_order = LoadOrder::default();
+        load_order.record("modded.ron".to_string(), Vec::new());
+        app.insert_resource(load_order);
+        let modded = app
+            .world_mut()
+            .run_system_once(|asset_server: Res<AssetServer>| {
+                asset_server.load::<ConfigAsset<NoiseStackConfig>>("modded.ron")
+            })
+            .unwrap();
+
+        // Act
+        app.world_mut().trigger(SaveEditedConfigs);
+        update_until(&mut app, |world| {
+            world.resource::<Updates>().0.len() == 3 && world.resource::<EditedConfigs>().is_empty()
+        });
+
+        let modded = app
+            .world_mut()
+            .run_system_once(move |config_server: ConfigServer| {
+                config_server.save(modded.id().untyped(), &NoiseStackConfig::default())
+            })
+            .unwrap();
+
+        let asset_server = app.world().resource::<AssetServer>().clone();
+        let templated = block_on(write_config(
+            &asset_server,
+            &AssetPath::from("templated.ron"),
+            "[]",
+        ));
+
+        // Assert
+        assert!(app.world().resource::<EditedConfigs>().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(assets.join("noise.ron")).unwrap(),
+            expected
+        );
+        // Updated once loaded, once edited and once reloaded.
+        assert_eq!(app.world().resource::<Updates>().0, vec![id, id, id]);
+        assert!(matches!(modded, Err(ConfigSaveError::Modded(path)) if path == "modded.ron"));
+        assert!(matches!(templated, Err(ConfigSaveError::HasIncludes)));
+        assert_eq!(
+            std::fs::read_to_string(assets.join("templated.ron")).unwrap(),
+            r#"[("main", include("noise.ron"))]"#
+        );
+    }
 }
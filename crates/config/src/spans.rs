@@ -0,0 +1,466 @@
+//! Positions of config values, recorded while deserializing, so problems found after loading,
+//! like unknown tile names, can point to the value which caused them.
+//!
+//! Values are identified by their path on the deserialized config, made of field names, list
+//! indices and enum variants, like `[1].neighbors[0]`, `floor.default` or
+//! `[0].rule.Not[0].DistanceTo.tile`.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+};
+
+use bevy::platform::collections::HashMap;
+use serde::de::{
+    DeserializeSeed, Deserializer, EnumAccess, Error, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+
+/// Byte offsets of the string values of a config source, by their path.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Spans(HashMap<String, usize>);
+
+impl Spans {
+    /// Offset of the string at the given path. Strings with escapes aren't recorded.
+    pub(crate) fn get(&self, path: &str) -> Option<usize> {
+        self.0.get(path).copied()
+    }
+}
+
+/// Joins a field or enum variant name to a path.
+pub(crate) fn field(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+/// Joins a list or tuple index to a path.
+pub(crate) fn index(path: &str, index: usize) -> String {
+    format!("{path}[{index}]")
+}
+
+/// Deserializes the given value using the given deserializer, recording the position of every
+/// string borrowed from `source`.
+pub(crate) fn record<'de, D, S>(
+    source: &str,
+    deserializer: D,
+    seed: S,
+) -> Result<(S::Value, Spans), D::Error>
+where
+    D: Deserializer<'de>,
+    S: DeserializeSeed<'de>,
+{
+    let recorder = Recorder {
+        source,
+        spans: RefCell::default(),
+    };
+    let value = seed.deserialize(Recording {
+        inner: deserializer,
+        path: String::new(),
+        key: None,
+        recorder: &recorder,
+    })?;
+
+    Ok((value, Spans(recorder.spans.into_inner())))
+}
+
+struct Recorder<'a> {
+    source: &'a str,
+    spans: RefCell<HashMap<String, usize>>,
+}
+
+impl Recorder<'_> {
+    fn record(&self, path: &str, value: &str) {
+        let start = self.source.as_ptr() as usize;
+        let Some(offset) = (value.as_ptr() as usize)
+            .checked_sub(start)
+            .filter(|offset| offset + value.len() <= self.source.len())
+        else {
+            return;
+        };
+
+        // Points to the opening quote, or to the `r` of raw strings.
+        let before = &self.source[..offset];
+        let before = before
+            .strip_suffix('"')
+            .unwrap_or(before)
+            .trim_end_matches('#');
+        let before = before.strip_suffix('r').unwrap_or(before);
+        self.spans
+            .borrow_mut()
+            .insert(path.to_string(), before.len());
+    }
+}
+
+/// Wraps every deserializer, visitor and access of a config, keeping the path of the value being
+/// deserialized. Struct keys and enum variants are deserialized with `key` set, so their names
+/// are captured instead of recorded.
+struct Recording<'r, 'a, T> {
+    inner: T,
+    path: String,
+    key: Option<&'r Cell<Option<String>>>,
+    recorder: &'r Recorder<'a>,
+}
+
+impl<'r, 'a, T> Recording<'r, 'a, T> {
+    fn wrap<U>(&self, inner: U, path: String) -> Recording<'r, 'a, U> {
+        Recording {
+            inner,
+            path,
+            key: None,
+            recorder: self.recorder,
+        }
+    }
+
+    fn visitor<V>(self, visitor: V) -> (T, Recording<'r, 'a, V>) {
+        let Self {
+            inner,
+            path,
+            key,
+            recorder,
+        } = self;
+
+        (
+            inner,
+            Recording {
+                inner: visitor,
+                path,
+                key,
+                recorder,
+            },
+        )
+    }
+
+    /// Deserializes a struct key or enum variant, returning the path of its value.
+    fn name<S, F, R, E>(&self, seed: S, next: F) -> Result<(R, Option<String>), E>
+    where
+        F: FnOnce(Recording<'_, 'a, S>) -> Result<R, E>,
+    {
+        let key = Cell::new(None);
+        let value = next(Recording {
+            inner: seed,
+            path: self.path.clone(),
+            key: Some(&key),
+            recorder: self.recorder,
+        })?;
+        Ok((value, key.take().map(|name| field(&self.path, &name))))
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, D::Error> {
+                let (inner, visitor) = self.visitor(visitor);
+                inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Recording<'_, '_, D> {
+    type Error = D::Error;
+
+    forward_deserialize!(
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_i128(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_u128(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    );
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for Recording<'_, '_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<S::Value, D::Error> {
+        let (seed, recording) = self.visitor(deserializer);
+        seed.deserialize(recording)
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: Error>(self, v: $ty) -> Result<V::Value, E> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Recording<'_, '_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    forward_visit!(
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    );
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<V::Value, E> {
+        if let Some(key) = self.key {
+            key.set(Some(v.to_string()));
+        }
+        self.inner.visit_str(v)
+    }
+
+    fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<V::Value, E> {
+        match self.key {
+            Some(key) => key.set(Some(v.to_string())),
+            None => self.recorder.record(&self.path, v),
+        }
+        self.inner.visit_borrowed_str(v)
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<V::Value, E> {
+        if let Some(key) = self.key {
+            key.set(Some(v.clone()));
+        }
+        self.inner.visit_string(v)
+    }
+
+    fn visit_none<E: Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        let deserializer = self.wrap(deserializer, self.path.clone());
+        self.inner.visit_some(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<V::Value, D::Error> {
+        let deserializer = self.wrap(deserializer, self.path.clone());
+        self.inner.visit_newtype_struct(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        let seq = self.wrap(
+            Elements {
+                inner: seq,
+                next: 0,
+            },
+            self.path.clone(),
+        );
+        self.inner.visit_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        let map = self.wrap(
+            Entries {
+                inner: map,
+                next: 0,
+                value: String::new(),
+            },
+            self.path.clone(),
+        );
+        self.inner.visit_map(map)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        let data = self.wrap(data, self.path.clone());
+        self.inner.visit_enum(data)
+    }
+}
+
+struct Elements<A> {
+    inner: A,
+    next: usize,
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for Recording<'_, '_, Elements<A>> {
+    type Error = A::Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, A::Error> {
+        let seed = self.wrap(seed, index(&self.path, self.inner.next));
+        self.inner.next += 1;
+        self.inner.inner.next_element_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.inner.size_hint()
+    }
+}
+
+struct Entries<A> {
+    inner: A,
+    next: usize,
+    /// Path of the value of the last key.
+    value: String,
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Recording<'_, '_, Entries<A>> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        let entries = &mut self.inner;
+        let (key, path) = Recording {
+            inner: (),
+            path: self.path.clone(),
+            key: None,
+            recorder: self.recorder,
+        }
+        .name(seed, |seed| entries.inner.next_key_seed(seed))?;
+
+        // Keys which aren't strings are identified by their order.
+        self.inner.value = path.unwrap_or_else(|| index(&self.path, self.inner.next));
+        self.inner.next += 1;
+        Ok(key)
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, A::Error> {
+        let seed = self.wrap(seed, self.inner.value.clone());
+        self.inner.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.inner.size_hint()
+    }
+}
+
+impl<'r, 'a, 'de, A: EnumAccess<'de>> EnumAccess<'de> for Recording<'r, 'a, A> {
+    type Error = A::Error;
+    type Variant = Recording<'r, 'a, A::Variant>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), A::Error> {
+        let unwrapped = self.wrap((), self.path.clone());
+        let ((value, variant), path) =
+            unwrapped.name(seed, |seed| self.inner.variant_seed(seed))?;
+        let path = path.unwrap_or_else(|| unwrapped.path.clone());
+        Ok((value, unwrapped.wrap(variant, path)))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Recording<'_, '_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, A::Error> {
+        let seed = self.wrap(seed, self.path.clone());
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        let (inner, visitor) = self.visitor(visitor);
+        inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        let (inner, visitor) = self.visitor(visitor);
+        inner.struct_variant(fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{prefab::PrefabConfig, server::deserialize_inner_type};
+
+    #[test]
+    fn record_value_paths() {
+        // Arrange
+        let source = r##"[
+    (
+        name: "CAMP",
+        floor: ["ds"],
+        legend: [
+            ('d', Random([("DIRT", 3.0), (r#"STONE"#, 1.0)])),
+            ('s', Tile("SAND")),
+            ('t', Tile("T\"REE")),
+        ],
+    ),
+]"##;
+
+        // Act
+        let (_, spans) = deserialize_inner_type::<Vec<PrefabConfig>>(source.as_bytes()).unwrap();
+
+        // Assert
+        let value =
+            |path: &str, len: usize| spans.get(path).map(|start| &source[start..start + len]);
+        assert_eq!(value("[0].name", 6), Some("\"CAMP\""));
+        assert_eq!(value("[0].floor[0]", 4), Some("\"ds\""));
+        assert_eq!(value("[0].legend[0][1].Random[0][0]", 6), Some("\"DIRT\""));
+        assert_eq!(
+            value("[0].legend[0][1].Random[1][0]", 10),
+            Some("r#\"STONE\"#")
+        );
+        assert_eq!(value("[0].legend[1][1].Tile", 6), Some("\"SAND\""));
+        // Strings with escapes aren't borrowed from the source.
+        assert_eq!(value("[0].legend[2][1].Tile", 1), None);
+    }
+}
@@ -35,6 +35,23 @@ pub struct TileConfig {
     pub map_color: HexColor,
     pub outline: bool,
     pub blend_tech: Option<BlendTech>,
+    /// Tiles allowed next to this one, besides itself. Any tile is allowed when empty.
+    #[reflect(default)]
+    pub neighbors: Vec<String>,
+    /// Alternative sprites picked per position. When not empty, replaces `atlas_index`.
+    #[reflect(default)]
+    pub variants: Vec<TileVariantConfig>,
+}
+
+#[derive(Debug, Reflect, Clone)]
+pub struct TileVariantConfig {
+    pub atlas_index: u16,
+    #[reflect(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
 }
 
 #[derive(Default, Debug, Reflect, Clone)]
@@ -46,4 +63,44 @@ impl FromConfig for TileConfigList {
     fn from_inner<'a>(asset: Self::InnerType) -> Self {
         Self(asset)
     }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::deserialize_config;
+
+    #[test]
+    fn deserialize_tile_variants() {
+        // Arrange
+        let ron = r##"
+[
+    (
+        kind: Terrain,
+        name: "GRASS",
+        atlas: "sheets/terrain.png",
+        atlas_index: 5,
+        map_color: "#33cc33",
+        outline: false,
+        blend_tech: None,
+        variants: [(atlas_index: 5, weight: 4.0), (atlas_index: 6)],
+    ),
+]
+        "##;
+
+        // Act
+        let config = deserialize_config::<TileConfigList>(ron.as_bytes());
+
+        // Assert
+        let variants = &config.0[0].variants;
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants[0].atlas_index, 5);
+        assert_eq!(variants[0].weight, 4.0);
+        assert_eq!(variants[1].atlas_index, 6);
+        assert_eq!(variants[1].weight, 1.0);
+    }
 }
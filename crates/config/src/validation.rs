@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+use crate::server::{ConfigServerPlugin, FromConfig};
+
+pub(crate) struct MapValidationConfigPlugin;
+impl Plugin for MapValidationConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((ConfigServerPlugin::<MapValidationRegistryConfig>::default(),));
+    }
+}
+
+/// Invariants checked on generated maps. Maps breaking any of them are generated again, using a
+/// seed derived from the original one.
+#[derive(Reflect, Debug, Clone)]
+pub struct MapValidationConfig {
+    /// Minimum fraction of the map, from 0.0 to 1.0, covered by the largest walkable region.
+    pub min_walkable_area: f32,
+    /// Free tiles needed around the spawn, on each direction.
+    pub spawn_clearance: u8,
+    /// How many maps are generated before giving up and keeping the last one.
+    pub max_attempts: u32,
+}
+
+impl Default for MapValidationConfig {
+    fn default() -> Self {
+        Self {
+            min_walkable_area: 0.0,
+            spawn_clearance: 1,
+            max_attempts: 1,
+        }
+    }
+}
+
+/// Validation used by default and the overrides for map generators which need different
+/// invariants, like dungeons which are mostly walls.
+#[derive(Reflect, Default, Debug, Clone)]
+pub struct MapValidationRegistryConfig {
+    pub default: MapValidationConfig,
+    /// Validation of each map generator, by name.
+    #[reflect(default)]
+    pub generators: Vec<(String, MapValidationConfig)>,
+}
+
+impl FromConfig for MapValidationRegistryConfig {
+    type InnerType = Self;
+
+    fn from_inner(inner: Self::InnerType) -> Self {
+        inner
+    }
+
+    fn to_inner(&self) -> Self::InnerType {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::deserialize_config;
+
+    pub use super::*;
+
+    #[test]
+    fn deserialize_validation() {
+        // Arrange
+        const VALIDATION: &str = r#"
+(
+    default: (
+        min_walkable_area: 0.4,
+        spawn_clearance: 2,
+        max_attempts: 5,
+    ),
+    generators: [
+        ("dungeon", (min_walkable_area: 0.05, spawn_clearance: 1, max_attempts: 3)),
+    ],
+)
+    "#;
+
+        // Act
+        let config = deserialize_config::<MapValidationRegistryConfig>(VALIDATION.as_bytes());
+
+        // Assert
+        assert_eq!(config.default.min_walkable_area, 0.4);
+        assert_eq!(config.default.spawn_clearance, 2);
+        assert_eq!(config.default.max_attempts, 5);
+        assert!(matches!(
+            &config.generators[0],
+            (name, MapValidationConfig { max_attempts: 3, .. }) if name == "dungeon"
+        ));
+    }
+}
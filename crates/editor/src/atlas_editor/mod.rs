@@ -79,6 +79,8 @@ fn update_atlas_image(
     let mut min = f32::MAX;
     let mut max = f32::MIN;
 
+    let sea_level = atlas.river.sea_level;
+
     for i in 0..atlas::ATLAS_SIZE {
         let index = i * 4;
         let elevation = atlas.elevation[i];
@@ -90,11 +92,11 @@ fn update_atlas_image(
             max = elevation;
         }
 
-        let color = if elevation > 0.25 {
+        let color = if elevation > sea_level + 0.25 {
             Srgba::WHITE.to_u8_array()
-        } else if elevation > 0.0 {
+        } else if elevation > sea_level {
             GREEN.to_u8_array()
-        } else if elevation > -0.25 {
+        } else if elevation > sea_level - 0.25 {
             LIGHT_BLUE.to_u8_array()
         } else {
             DARK_BLUE.to_u8_array()
@@ -105,6 +107,15 @@ fn update_atlas_image(
         data[index + 2] = color[2];
     }
 
+    for cell in atlas.rivers.iter().flat_map(|r| r.path.iter()) {
+        let index = atlas::to_index(cell.x, cell.y) * 4;
+        let color = BLUE.to_u8_array();
+
+        data[index] = color[0];
+        data[index + 1] = color[1];
+        data[index + 2] = color[2];
+    }
+
     debug!("min: {min}, max: {max}");
 }
 
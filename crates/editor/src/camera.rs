@@ -2,6 +2,7 @@ use bevy::{
     input::mouse::{MouseMotion, MouseWheel},
     prelude::*,
 };
+use eternal_config::input::InputActions;
 
 pub struct CameraPlugin;
 
@@ -51,10 +52,10 @@ fn zoom(
 
 fn pan(
     mut motion_msgs: MessageReader<MouseMotion>,
-    input: Res<ButtonInput<MouseButton>>,
+    actions: Res<InputActions>,
     singleton: Single<(&mut Transform, &Projection, &DebugCamera)>,
 ) {
-    if !input.pressed(MouseButton::Middle) {
+    if !actions.pressed("camera_pan") {
         return;
     }
 
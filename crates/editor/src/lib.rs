@@ -1,7 +1,10 @@
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use eternal_config::ConfigPlugin;
+use eternal_config::{
+    ConfigPlugin,
+    input::{InputActions, InputActionsPlugin},
+};
 use eternal_grid::ecs::GridPlugin;
 use eternal_procgen::ProcGenPlugin;
 use eternal_ui::UiPlugin;
@@ -22,6 +25,7 @@ impl Plugin for EditorPlugin {
                 ProcGenPlugin,
                 camera::CameraPlugin,
                 ConfigPlugin,
+                InputActionsPlugin,
                 GridPlugin,
             ))
             .add_plugins((MapEditorPlugin, AtlasEditorPlugin))
@@ -38,16 +42,16 @@ enum EditorState {
 }
 
 fn switch_editor_state(
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Res<InputActions>,
     current_state: Res<State<EditorState>>,
     mut next_state: ResMut<NextState<EditorState>>,
 ) {
     let current_state = *current_state.get();
 
-    if input.just_released(KeyCode::Digit1) && current_state != EditorState::Atlas {
+    if actions.just_released("atlas_editor") && current_state != EditorState::Atlas {
         debug!("Switching to Atlas Editor");
         next_state.set(EditorState::Atlas);
-    } else if input.just_released(KeyCode::Digit2) && current_state != EditorState::Map {
+    } else if actions.just_released("map_editor") && current_state != EditorState::Map {
         debug!("Switching to Map Editor");
         next_state.set(EditorState::Map);
     }
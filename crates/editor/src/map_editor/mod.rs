@@ -3,12 +3,21 @@ use bevy::{
     prelude::*,
     render::render_resource::{TextureDescriptor, TextureDimension, TextureFormat, TextureUsages},
 };
+use eternal_config::input::InputActions;
 use eternal_grid::{
     ecs::TileRegistry,
     grid::{self, LayerIndex},
     tile::NONE_INFO,
 };
-use eternal_procgen::{biome::BiomeRegistry, map::Map};
+use eternal_procgen::{
+    WorldSeed,
+    atlas::{ActiveMap, Atlas, AtlasRegion},
+    biome::BiomeRegistry,
+    generator::{GeneratorContext, MapGeneratorRegistry},
+    map::Map,
+    prefab::PrefabRegistry,
+    validation::{MapReport, MapValidation},
+};
 
 use crate::{
     EditorState,
@@ -22,6 +31,7 @@ pub struct MapEditorPlugin;
 impl Plugin for MapEditorPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(MapUiPlugin)
+            .init_resource::<SelectedGenerator>()
             .add_systems(OnEnter(EditorState::Map), setup)
             .add_systems(OnExit(EditorState::Map), cleanup)
             .add_systems(
@@ -31,7 +41,16 @@ impl Plugin for MapEditorPlugin {
                         resource_exists::<Map>
                             .and(resource_changed::<Map>.or(resource_changed::<MapOptions>)),
                     ),
-                    update_map.run_if(resource_changed::<BiomeRegistry>),
+                    cycle_generator,
+                    update_map.run_if(
+                        resource_changed::<BiomeRegistry>
+                            .or(resource_changed::<PrefabRegistry>)
+                            .or(resource_changed::<MapGeneratorRegistry>)
+                            .or(resource_changed::<SelectedGenerator>)
+                            .or(resource_changed::<MapValidation>)
+                            .or(resource_exists_and_changed::<Atlas>)
+                            .or(resource_changed::<ActiveMap>),
+                    ),
                     draw_gizmos,
                 )
                     .run_if(in_state(EditorState::Map)),
@@ -42,10 +61,27 @@ impl Plugin for MapEditorPlugin {
 #[derive(Component)]
 struct MapImage;
 
+/// Name of the map generator used to generate the map being edited.
+#[derive(Resource, Deref)]
+struct SelectedGenerator(String);
+
+impl Default for SelectedGenerator {
+    fn default() -> Self {
+        Self("overworld".to_string())
+    }
+}
+
 fn setup(
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
     biome_registry: Res<BiomeRegistry>,
+    prefab_registry: Res<PrefabRegistry>,
+    generator_registry: Res<MapGeneratorRegistry>,
+    generator: Res<SelectedGenerator>,
+    validation: Res<MapValidation>,
+    atlas: Option<Res<Atlas>>,
+    active_map: Res<ActiveMap>,
+    seed: Res<WorldSeed>,
 ) {
     let image = Image {
         data: Some(vec![0; grid::LAYER_SIZE * 4]), // 4 colors (rgba)
@@ -71,20 +107,27 @@ fn setup(
         },
     ));
 
-    let map = if !biome_registry.is_empty() {
-        let biome = biome_registry
-            .get_biome("Forest")
-            .expect("Biome forest exists");
-        eternal_procgen::generate_map(biome)
-    } else {
-        Map::default()
+    let ctx = GeneratorContext {
+        biomes: &biome_registry,
+        prefabs: &prefab_registry,
+        region: atlas.as_deref().map(|atlas| AtlasRegion {
+            atlas,
+            map: **active_map,
+        }),
+        seed: **seed,
+        spawn_clearance: validation.get(&generator).spawn_clearance,
     };
+    let (map, report) = generator_registry
+        .generate_validated(&generator, &ctx, &validation)
+        .unwrap_or_default();
 
     commands.insert_resource(map);
+    commands.insert_resource(report);
 }
 
 fn cleanup(mut commands: Commands, single: Option<Single<Entity, With<MapImage>>>) {
     commands.remove_resource::<Map>();
+    commands.remove_resource::<MapReport>();
     if let Some(single) = single {
         commands.entity(single.into_inner()).despawn();
     }
@@ -139,11 +182,58 @@ fn update_map_image(
     debug!("{min}, {max}");
 }
 
-fn update_map(biome_registry: Res<BiomeRegistry>, mut commands: Commands) {
-    let biome = biome_registry
-        .get_biome("Forest")
-        .expect("Biome forest exists");
-    commands.insert_resource(eternal_procgen::generate_map(biome));
+fn update_map(
+    biome_registry: Res<BiomeRegistry>,
+    prefab_registry: Res<PrefabRegistry>,
+    generator_registry: Res<MapGeneratorRegistry>,
+    generator: Res<SelectedGenerator>,
+    validation: Res<MapValidation>,
+    atlas: Option<Res<Atlas>>,
+    active_map: Res<ActiveMap>,
+    seed: Res<WorldSeed>,
+    mut commands: Commands,
+) {
+    let ctx = GeneratorContext {
+        biomes: &biome_registry,
+        prefabs: &prefab_registry,
+        region: atlas.as_deref().map(|atlas| AtlasRegion {
+            atlas,
+            map: **active_map,
+        }),
+        seed: **seed,
+        spawn_clearance: validation.get(&generator).spawn_clearance,
+    };
+
+    match generator_registry.generate_validated(&generator, &ctx, &validation) {
+        Ok((map, report)) => {
+            commands.insert_resource(map);
+            commands.insert_resource(report);
+        }
+        Err(err) => error!("Failed to generate map: {err}"),
+    }
+}
+
+fn cycle_generator(
+    actions: Res<InputActions>,
+    generator_registry: Res<MapGeneratorRegistry>,
+    mut generator: ResMut<SelectedGenerator>,
+) {
+    if !actions.just_pressed("cycle_generator") {
+        return;
+    }
+
+    let names = generator_registry.names().collect::<Vec<_>>();
+    if names.is_empty() {
+        return;
+    }
+
+    let next = names
+        .iter()
+        .position(|&name| name == generator.0)
+        .map_or(0, |idx| (idx + 1) % names.len());
+
+    info!("Using map generator {}", names[next]);
+    generator.0 = names[next].to_string();
 }
 
 fn draw_gizmos(mut gizmos: Gizmos, projetion: Single<&Projection, With<Camera2d>>) {
@@ -1,9 +1,12 @@
 use bevy::{
-    feathers::controls::checkbox,
+    feathers::controls::{ButtonProps, button, checkbox},
     prelude::*,
     ui::Checked,
-    ui_widgets::{ValueChange, observe},
+    ui_widgets::{Activate, ValueChange, observe},
 };
+use eternal_config::server::SaveEditedConfigs;
+use eternal_grid::{ecs::TileRegistry, grid, tile::NONE_INFO};
+use eternal_procgen::validation::MapReport;
 use eternal_ui::window::{WindowConfig, window};
 
 use crate::EditorState;
@@ -13,7 +16,13 @@ pub struct MapUiPlugin;
 impl Plugin for MapUiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(EditorState::Map), setup)
-            .add_systems(OnExit(EditorState::Map), cleanup);
+            .add_systems(OnExit(EditorState::Map), cleanup)
+            .add_systems(
+                Update,
+                update_report.run_if(
+                    in_state(EditorState::Map).and(resource_exists_and_changed::<MapReport>),
+                ),
+            );
     }
 }
 
@@ -26,6 +35,9 @@ pub struct MapOptions {
 #[derive(Component)]
 struct MapUi;
 
+#[derive(Component)]
+struct ReportText;
+
 fn setup(mut commands: Commands) {
     commands.insert_resource(MapOptions {
         terrain: true,
@@ -76,13 +88,93 @@ fn setup(mut commands: Commands) {
                             }
                         )
                     ),
+                    // Writes configs tuned on the inspector, which reloads them and the map.
+                    (
+                        button(ButtonProps::default(), (), Spawn(Text::new("Save configs"))),
+                        observe(|_: On<Activate>, mut commands: Commands| {
+                            commands.trigger(SaveEditedConfigs);
+                        })
+                    ),
                 ],
             ),
         ),
         MapUi,
     ));
+
+    commands.spawn((
+        window(
+            WindowConfig {
+                title: "Report".to_string(),
+                bottom: px(1.0),
+                right: px(1.0),
+                ..default()
+            },
+            (
+                Text::default(),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                ReportText,
+            ),
+        ),
+        MapUi,
+    ));
 }
 
-fn cleanup(ui_root: Single<Entity, With<MapUi>>, mut commands: Commands) {
-    commands.entity(ui_root.into_inner()).despawn();
+fn cleanup(q_ui_roots: Query<Entity, With<MapUi>>, mut commands: Commands) {
+    for entity in q_ui_roots {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn update_report(
+    report: Res<MapReport>,
+    tile_registry: Res<TileRegistry>,
+    mut text: Single<&mut Text, With<ReportText>>,
+) {
+    use std::fmt::Write;
+
+    let tile_name = |tile_id| &tile_registry.get(&tile_id).unwrap_or(&NONE_INFO).name;
+    let percent = |count: usize| count as f32 / grid::LAYER_SIZE as f32 * 100.0;
+
+    let mut s = String::new();
+    let attempts = report.attempts;
+    let _ = writeln!(s, "Seed: {} ({attempts} attempts)", report.seed);
+    let _ = writeln!(s, "Walkable: {:.1}%", report.walkable_area * 100.0);
+    match report.spawn {
+        Some(spawn) => _ = writeln!(s, "Spawn: {}, {}", spawn.x, spawn.y),
+        None => _ = writeln!(s, "Spawn: none"),
+    }
+
+    let _ = writeln!(s, "\nFloor:");
+    for &(tile_id, count) in &report.floor_tiles {
+        let _ = writeln!(s, "  {}: {:.1}%", tile_name(tile_id), percent(count));
+    }
+
+    let _ = writeln!(s, "\nWall:");
+    for &(tile_id, count) in &report.wall_tiles {
+        let _ = writeln!(s, "  {}: {:.1}%", tile_name(tile_id), percent(count));
+    }
+
+    let _ = writeln!(s, "\nFlora:");
+    for (name, count) in &report.flora {
+        let _ = writeln!(s, "  {name}: {count}");
+    }
+
+    let (min, max) = report.elevation_range;
+    let _ = writeln!(s, "\nElevation ({min:.2} to {max:.2}):");
+    for count in report.elevation_histogram {
+        let bar = "#".repeat((percent(count) / 2.0).ceil() as usize);
+        let _ = writeln!(s, "  {:>5.1}% {bar}", percent(count));
+    }
+
+    if !report.is_valid() {
+        let _ = writeln!(s, "\nIssues:");
+        for issue in &report.issues {
+            let _ = writeln!(s, "  {issue}");
+        }
+    }
+
+    text.0 = s;
 }
@@ -4,7 +4,7 @@ use eternal_config::{
     tile::{TileConfig, TileConfigList},
 };
 
-use crate::tile::{self, TileId, TileInfo};
+use crate::tile::{self, TileId, TileInfo, TileVariant};
 
 pub struct GridPlugin;
 
@@ -41,6 +41,85 @@ impl TileRegistry {
         Self(map)
     }
 
+    /// Builds the registry from tile configs. Atlas textures are loaded using `load_atlas`, which
+    /// may return a default handle when textures aren't needed.
+    pub fn from_config(
+        config: &TileConfigList,
+        mut load_atlas: impl FnMut(&str) -> Handle<Image>,
+    ) -> Self {
+        let ids = config
+            .0
+            .iter()
+            .enumerate()
+            .map(|(idx, config)| (config.name.as_str(), TileId::new(idx as u16)))
+            .collect::<HashMap<_, _>>();
+
+        let map = config
+            .0
+            .iter()
+            .enumerate()
+            .map(|(idx, config)| {
+                let TileConfig {
+                    name,
+                    kind,
+                    atlas,
+                    atlas_index,
+                    map_color,
+                    outline,
+                    blend_tech,
+                    neighbors,
+                    variants,
+                } = config;
+
+                let neighbors = neighbors
+                    .iter()
+                    .filter_map(|neighbor| {
+                        let id = ids.get(neighbor.as_str()).copied();
+                        if id.is_none() {
+                            warn!("Neighbor {neighbor} of tile {name} not found");
+                        }
+                        id
+                    })
+                    .collect::<Vec<_>>();
+
+                let variants = variants
+                    .iter()
+                    .filter_map(|variant| {
+                        if variant.weight < 0.0 {
+                            warn!(
+                                "Variant {} of tile {name} has negative weight {}",
+                                variant.atlas_index, variant.weight
+                            );
+                            return None;
+                        }
+                        Some(TileVariant {
+                            atlas_index: variant.atlas_index,
+                            weight: variant.weight,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                let info = TileInfo {
+                    name: name.clone().into(),
+                    kind: (*kind).into(),
+                    atlas: load_atlas(atlas),
+                    atlas_index: *atlas_index,
+                    map_color: map_color.into(),
+                    outline: *outline,
+                    blend_tech: blend_tech.unwrap_or_default().into(),
+                    neighbors: neighbors.into(),
+                    variants: variants.into(),
+                };
+
+                let id = TileId::new(idx as u16);
+                (id, info)
+            })
+            .chain(std::iter::once((TileId::new(u16::MAX), tile::NONE_INFO)))
+            .collect::<HashMap<_, _>>();
+
+        Self(map)
+    }
+
     pub fn get_by_name(&self, name: &str) -> &TileInfo {
         self.0
             .values()
@@ -49,9 +128,18 @@ impl TileRegistry {
     }
 
     pub fn get_id_by_name(&self, name: &str) -> TileId {
+        self.find_id_by_name(name).unwrap_or(TileId::none())
+    }
+
+    /// Same as [`Self::get_id_by_name`], but returns `None` when there is no tile with the given
+    /// name. `NONE` is always found.
+    pub fn find_id_by_name(&self, name: &str) -> Option<TileId> {
+        if name == tile::NONE_INFO.name {
+            return Some(TileId::none());
+        }
+
         self.iter()
             .find_map(|(id, info)| if info.name == name { Some(*id) } else { None })
-            .unwrap_or(TileId::none())
     }
 }
 
@@ -72,38 +160,11 @@ fn on_tile_config_list_updated(
         return;
     };
 
-    let map = tile_config_list
-        .0
-        .iter()
-        .enumerate()
-        .map(|(idx, config)| {
-            let TileConfig {
-                name,
-                kind,
-                atlas,
-                atlas_index,
-                map_color,
-                outline,
-                blend_tech,
-            } = config;
-
-            let info = TileInfo {
-                name: name.clone().into(),
-                kind: (*kind).into(),
-                atlas: asset_server.load(atlas),
-                atlas_index: *atlas_index,
-                map_color: map_color.into(),
-                outline: *outline,
-                blend_tech: blend_tech.unwrap_or_default().into(),
-            };
-
-            let id = TileId::new(idx as u16);
-            (id, info)
-        })
-        .chain(std::iter::once((TileId::new(u16::MAX), tile::NONE_INFO)))
-        .collect::<HashMap<_, _>>();
-
-    debug!("Loaded tile info list: {map:?}");
-
-    commands.insert_resource(TileRegistry::new(map));
+    let registry = TileRegistry::from_config(tile_config_list, |atlas| {
+        asset_server.load(atlas.to_string())
+    });
+
+    debug!("Loaded tile info list: {registry:?}");
+
+    commands.insert_resource(registry);
 }
@@ -48,6 +48,13 @@ pub fn to_index(x: u16, y: u16) -> usize {
     y as usize * DIMS.x as usize + x as usize
 }
 
+pub fn from_index(idx: usize) -> (u16, u16) {
+    (
+        (idx % DIMS.x as usize) as u16,
+        (idx / DIMS.x as usize) as u16,
+    )
+}
+
 pub fn grid_to_world(x: u16, y: u16) -> Vec2 {
     Vec2::new(x as f32, y as f32) * tile::SIZE.as_vec2()
 }
@@ -0,0 +1,19 @@
+/// Hashes the given bytes together with a seed using FNV-1a and a final avalanche mix.
+pub fn hash(seed: u64, bytes: &[u8]) -> u64 {
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    let mut hash = 0xCBF2_9CE4_8422_2325 ^ seed;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    mix(hash)
+}
+
+/// `SplitMix64` finalizer.
+pub fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -1,3 +1,4 @@
 pub mod ecs;
 pub mod grid;
+pub mod hash;
 pub mod tile;
@@ -3,6 +3,8 @@ use std::{borrow::Cow, marker::PhantomData};
 use bevy::{math::U16Vec2, prelude::*};
 use serde::Deserialize;
 
+use crate::hash;
+
 pub const NONE_INFO: TileInfo = TileInfo {
     name: Cow::Borrowed("NONE"),
     kind: TileKind::Terrain,
@@ -11,6 +13,8 @@ pub const NONE_INFO: TileInfo = TileInfo {
     map_color: Srgba::NONE,
     outline: false,
     blend_tech: BlendTech::None,
+    neighbors: Cow::Borrowed(&[]),
+    variants: Cow::Borrowed(&[]),
 };
 
 /// The size of each rendered individual tile.
@@ -39,6 +43,41 @@ pub struct TileInfo {
     pub map_color: Srgba,
     pub outline: bool,
     pub blend_tech: BlendTech,
+    /// Tiles allowed next to this one, besides itself. Any tile is allowed when empty.
+    pub neighbors: Cow<'static, [TileId]>,
+    /// Alternative sprites picked per position. When not empty, replaces `atlas_index`.
+    pub variants: Cow<'static, [TileVariant]>,
+}
+
+impl TileInfo {
+    /// Atlas index rendered at the given position. Variants are picked using a stable hash of
+    /// the position and seed, so the same tile always looks the same on a given world.
+    pub fn atlas_index_at(&self, x: u16, y: u16, seed: u64) -> u16 {
+        let total = self.variants.iter().map(|v| v.weight).sum::<f32>();
+        if total <= 0.0 {
+            return self.atlas_index;
+        }
+
+        let hash = hash::hash(seed, [x.to_le_bytes(), y.to_le_bytes()].as_flattened());
+        let mut roll = (hash >> 40) as f32 / (1u64 << 24) as f32 * total;
+
+        for variant in self.variants.iter() {
+            if roll < variant.weight {
+                return variant.atlas_index;
+            }
+            roll -= variant.weight;
+        }
+
+        self.variants
+            .last()
+            .map_or(self.atlas_index, |v| v.atlas_index)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Reflect)]
+pub struct TileVariant {
+    pub atlas_index: u16,
+    pub weight: f32,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Hash, Deref, Reflect)]
@@ -90,3 +129,48 @@ impl TileVisible {
         self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_variants() {
+        // Arrange
+        let info = TileInfo {
+            atlas_index: 5,
+            variants: vec![
+                TileVariant {
+                    atlas_index: 5,
+                    weight: 3.0,
+                },
+                TileVariant {
+                    atlas_index: 6,
+                    weight: 1.0,
+                },
+            ]
+            .into(),
+            ..default()
+        };
+
+        // Act
+        let indices = (0..64)
+            .flat_map(|y| (0..64).map(move |x| (x, y)))
+            .map(|(x, y)| info.atlas_index_at(x, y, 42))
+            .collect::<Vec<_>>();
+
+        // Assert
+        let variants = indices.iter().filter(|&&index| index == 6).count();
+        assert!((768..1280).contains(&variants), "{variants} variants");
+        let layout = (0..8)
+            .map(|x| info.atlas_index_at(x, 0, 42))
+            .collect::<Vec<_>>();
+        assert_eq!(layout, [6, 5, 6, 6, 6, 5, 5, 5]);
+        let other_seed = (0..64)
+            .flat_map(|y| (0..64).map(move |x| (x, y)))
+            .map(|(x, y)| info.atlas_index_at(x, y, 7))
+            .collect::<Vec<_>>();
+        assert_ne!(indices, other_seed);
+        assert_eq!(NONE_INFO.atlas_index_at(10, 20, 42), u16::MAX);
+    }
+}
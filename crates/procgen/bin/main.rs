@@ -0,0 +1,322 @@
+//! Headless procgen preview. Loads the configs from an asset folder, generates the atlas and a
+//! map, and writes them as PNG images. Run with `--help` to see the available options.
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use bevy::{log::LogPlugin, math::U16Vec2, prelude::*};
+use eternal_grid::ecs::TileRegistry;
+use eternal_procgen::{
+    WorldSeed,
+    atlas::{self, Atlas, AtlasRegion},
+    biome::BiomeRegistry,
+    headless,
+    prefab::PrefabRegistry,
+    preview,
+    validation::MapValidation,
+};
+use image::RgbaImage;
+
+const USAGE: &str = "\
+Usage: eternal_procgen [OPTIONS]
+
+Options:
+  --assets <DIR>    Asset folder containing the configs [default: assets]
+  --out <DIR>       Folder where images are written [default: target/procgen]
+  --seed <SEED>     World seed [default: 42]
+  --biome <NAME>    Biome used to generate the map and color the atlas [default: Forest]
+  --cell <X,Y>      Atlas cell of the map [default: center of the atlas]
+  --elevation       Also write elevation heatmaps
+  --noise           Also write a heatmap of each biome noise layer
+  --batch <COUNT>   Generate COUNT seeds, starting at --seed, side by side on a single image
+  --columns <N>     Columns used by --batch [default: square root of COUNT]
+  -h, --help        Print this help";
+
+/// How long to wait for configs to load and the atlas to be generated.
+const LOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, thiserror::Error)]
+enum CliError {
+    #[error("{0}\n\n{USAGE}")]
+    Usage(String),
+    #[error("Asset folder {0} not found")]
+    AssetsNotFound(PathBuf),
+    #[error("Timed out waiting for configs to load")]
+    Timeout,
+    #[error("Biome {0} not found")]
+    BiomeNotFound(String),
+    #[error("Failed to write image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug)]
+struct Args {
+    assets: PathBuf,
+    out: PathBuf,
+    seed: u64,
+    biome: String,
+    cell: U16Vec2,
+    elevation: bool,
+    noise: bool,
+    batch: Option<u32>,
+    columns: Option<u32>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            assets: "assets".into(),
+            out: "target/procgen".into(),
+            seed: WorldSeed::default().0,
+            biome: "Forest".to_string(),
+            cell: U16Vec2::splat(atlas::MAP_COUNT / 2),
+            elevation: false,
+            noise: false,
+            batch: None,
+            columns: None,
+        }
+    }
+}
+
+impl Args {
+    /// Parses the arguments. Returns `None` when help was requested.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, CliError> {
+        fn value<T: std::str::FromStr>(
+            flag: &str,
+            args: &mut impl Iterator<Item = String>,
+        ) -> Result<T, CliError> {
+            let value = args
+                .next()
+                .ok_or_else(|| CliError::Usage(format!("Missing value for {flag}")))?;
+            value
+                .parse()
+                .map_err(|_| CliError::Usage(format!("Invalid value for {flag}: {value}")))
+        }
+
+        let mut parsed = Args::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--assets" => parsed.assets = value::<PathBuf>(&arg, &mut args)?,
+                "--out" => parsed.out = value::<PathBuf>(&arg, &mut args)?,
+                "--seed" => parsed.seed = value(&arg, &mut args)?,
+                "--biome" => parsed.biome = value(&arg, &mut args)?,
+                "--cell" => {
+                    let cell = value::<String>(&arg, &mut args)?;
+                    let invalid = || CliError::Usage(format!("Invalid cell: {cell}"));
+                    let (x, y) = cell.split_once(',').ok_or_else(invalid)?;
+                    let x = x.trim().parse::<u16>().map_err(|_| invalid())?;
+                    let y = y.trim().parse::<u16>().map_err(|_| invalid())?;
+                    if x >= atlas::MAP_COUNT || y >= atlas::MAP_COUNT {
+                        return Err(CliError::Usage(format!(
+                            "Cell must be less than {}",
+                            atlas::MAP_COUNT
+                        )));
+                    }
+                    parsed.cell = U16Vec2::new(x, y);
+                }
+                "--elevation" => parsed.elevation = true,
+                "--noise" => parsed.noise = true,
+                "--batch" => parsed.batch = Some(value::<u32>(&arg, &mut args)?.max(1)),
+                "--columns" => parsed.columns = Some(value::<u32>(&arg, &mut args)?.max(1)),
+                "-h" | "--help" => return Ok(None),
+                _ => return Err(CliError::Usage(format!("Unknown argument: {arg}"))),
+            }
+        }
+
+        Ok(Some(parsed))
+    }
+}
+
+/// Images generated for a single seed.
+struct Output {
+    seed: u64,
+    map: RgbaImage,
+    atlas: RgbaImage,
+    map_elevation: Option<RgbaImage>,
+    atlas_elevation: Option<RgbaImage>,
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), CliError> {
+    // Bevy resolves relative asset paths from the executable folder, not the working one.
+    let assets = args
+        .assets
+        .canonicalize()
+        .map_err(|_| CliError::AssetsNotFound(args.assets.clone()))?;
+
+    let mut app = eternal_procgen::headless::app(&assets);
+    app.add_plugins(LogPlugin::default());
+
+    std::fs::create_dir_all(&args.out)?;
+
+    let count = args.batch.unwrap_or(1);
+    let mut outputs = Vec::with_capacity(count as usize);
+
+    for seed in (0..count as u64).map(|i| args.seed.wrapping_add(i)) {
+        if !headless::generate_atlas(&mut app, seed, LOAD_TIMEOUT) {
+            return Err(CliError::Timeout);
+        }
+
+        let output = generate(app.world(), args, seed)?;
+        if args.batch.is_none() {
+            write_output(&output, &args.out)?;
+        }
+        outputs.push(output);
+    }
+
+    if let Some(count) = args.batch {
+        write_batch(&outputs, args, count)?;
+    }
+
+    if args.noise {
+        write_noise(app.world(), args)?;
+    }
+
+    Ok(())
+}
+
+fn generate(world: &World, args: &Args, seed: u64) -> Result<Output, CliError> {
+    let tiles = world.resource::<TileRegistry>();
+    let prefabs = world.resource::<PrefabRegistry>();
+    let atlas = world.resource::<Atlas>();
+    let biome = world
+        .resource::<BiomeRegistry>()
+        .get_biome(&args.biome)
+        .ok_or_else(|| CliError::BiomeNotFound(args.biome.clone()))?;
+
+    info!("Generating seed {seed}");
+
+    let region = AtlasRegion {
+        atlas,
+        map: args.cell,
+    };
+    let spawn_clearance = world.resource::<MapValidation>().0.default.spawn_clearance;
+    let map = eternal_procgen::generate_map(biome, prefabs, Some(region), seed, spawn_clearance);
+
+    Ok(Output {
+        seed,
+        map: preview::map_image(&map, tiles),
+        atlas: preview::atlas_image(atlas, &biome.terrain_pallet, tiles),
+        map_elevation: args.elevation.then(|| preview::elevation_image(&map)),
+        atlas_elevation: args
+            .elevation
+            .then(|| preview::atlas_elevation_image(atlas)),
+    })
+}
+
+fn save(image: &RgbaImage, path: PathBuf) -> Result<(), CliError> {
+    image.save(&path)?;
+    info!("Written {}", path.display());
+    Ok(())
+}
+
+fn write_output(output: &Output, out: &Path) -> Result<(), CliError> {
+    let seed = output.seed;
+    save(&output.map, out.join(format!("map_{seed}.png")))?;
+    save(&output.atlas, out.join(format!("atlas_{seed}.png")))?;
+
+    if let Some(image) = &output.map_elevation {
+        save(image, out.join(format!("map_elevation_{seed}.png")))?;
+    }
+    if let Some(image) = &output.atlas_elevation {
+        save(image, out.join(format!("atlas_elevation_{seed}.png")))?;
+    }
+
+    Ok(())
+}
+
+fn write_batch(outputs: &[Output], args: &Args, count: u32) -> Result<(), CliError> {
+    let columns = args
+        .columns
+        .unwrap_or_else(|| (count as f32).sqrt().ceil() as u32);
+    let last = args.seed.wrapping_add(count as u64 - 1);
+    let name = |kind: &str| args.out.join(format!("{kind}_{}-{last}.png", args.seed));
+
+    let sheet = |images: Vec<RgbaImage>| preview::contact_sheet(&images, columns);
+
+    save(
+        &sheet(outputs.iter().map(|o| o.map.clone()).collect()),
+        name("maps"),
+    )?;
+    save(
+        &sheet(outputs.iter().map(|o| o.atlas.clone()).collect()),
+        name("atlases"),
+    )?;
+
+    if args.elevation {
+        save(
+            &sheet(
+                outputs
+                    .iter()
+                    .filter_map(|o| o.map_elevation.clone())
+                    .collect(),
+            ),
+            name("map_elevations"),
+        )?;
+        save(
+            &sheet(
+                outputs
+                    .iter()
+                    .filter_map(|o| o.atlas_elevation.clone())
+                    .collect(),
+            ),
+            name("atlas_elevations"),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Noise layers doesn't depend on the world seed, so they are written only once.
+fn write_noise(world: &World, args: &Args) -> Result<(), CliError> {
+    let biome = world
+        .resource::<BiomeRegistry>()
+        .get_biome(&args.biome)
+        .ok_or_else(|| CliError::BiomeNotFound(args.biome.clone()))?;
+
+    for (kind, stack) in [
+        ("terrain", &biome.terrain_noise),
+        ("flora", &biome.flora_noise),
+    ] {
+        for layer in stack.layers() {
+            let Some(layer_stack) = stack.layer(layer) else {
+                warn!("Failed to build {kind} noise layer {layer}");
+                continue;
+            };
+
+            save(
+                &preview::noise_image(&layer_stack),
+                args.out.join(format!("noise_{kind}_{layer}.png")),
+            )?;
+        }
+    }
+
+    Ok(())
+}
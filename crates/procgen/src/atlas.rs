@@ -1,10 +1,11 @@
 use bevy::{math::U16Vec2, prelude::*};
 use eternal_config::{
+    erosion::{ErosionConfig, RiverConfig},
     noise::NoiseStackConfig,
     server::{ConfigAssetUpdated, ConfigServer, Configs},
 };
 
-use crate::noise::NoiseStack;
+use crate::{WorldSeed, noise::NoiseStack};
 
 pub const MAP_RESOLUTION: u16 = 3;
 pub const MAP_COUNT: u16 = 128;
@@ -15,20 +16,51 @@ pub(crate) struct AtlasPlugin;
 
 impl Plugin for AtlasPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup);
+        app.init_resource::<AtlasSettings>()
+            .init_resource::<ActiveMap>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                update_atlas
+                    .run_if(resource_changed::<AtlasSettings>.or(resource_changed::<WorldSeed>)),
+            );
     }
 }
 
+/// Configs needed to generate the atlas. The atlas is only generated once all of them are loaded.
+#[derive(Default, Resource)]
+struct AtlasSettings {
+    noise: Option<NoiseStack>,
+    erosion: Option<ErosionConfig>,
+}
+
 fn setup(mut config_server: ConfigServer) {
     config_server
         .load::<NoiseStackConfig>("config/procgen/atlas.ron")
         .observe(on_noise_stack_config_updated);
+
+    config_server
+        .load::<ErosionConfig>("config/procgen/erosion.ron")
+        .observe(on_erosion_config_updated);
+}
+
+fn on_erosion_config_updated(
+    updated: On<ConfigAssetUpdated>,
+    configs: Configs<ErosionConfig>,
+    mut settings: ResMut<AtlasSettings>,
+) {
+    let Some(config) = configs.get(updated.id()) else {
+        error!("Failed to get atlas erosion config.");
+        return;
+    };
+
+    settings.erosion = Some(config.clone());
 }
 
 fn on_noise_stack_config_updated(
     updated: On<ConfigAssetUpdated>,
     configs: Configs<NoiseStackConfig>,
-    mut commands: Commands,
+    mut settings: ResMut<AtlasSettings>,
 ) {
     let Some(config) = configs.get(updated.id()) else {
         error!("Failed to get atlas noise config.");
@@ -43,9 +75,15 @@ fn on_noise_stack_config_updated(
         }
     };
 
-    let atlas = crate::generate_atlas(&stack);
+    settings.noise = Some(stack);
+}
+
+fn update_atlas(settings: Res<AtlasSettings>, seed: Res<WorldSeed>, mut commands: Commands) {
+    let (Some(noise), Some(erosion)) = (&settings.noise, &settings.erosion) else {
+        return;
+    };
 
-    commands.insert_resource(atlas);
+    commands.insert_resource(crate::generate_atlas(noise, erosion, **seed));
 }
 
 pub fn to_index(x: u16, y: u16) -> usize {
@@ -59,15 +97,52 @@ pub fn from_index(index: usize) -> U16Vec2 {
     }
 }
 
+/// A river traced on the atlas, from its source to the sea or to another river.
+#[derive(Default, Debug, Clone)]
+pub struct River {
+    pub path: Vec<U16Vec2>,
+}
+
 #[derive(Default, Debug, Clone, Resource)]
 pub struct Atlas {
     pub elevation: Vec<f32>,
+    /// Number of cells which drains through each cell, including itself.
+    pub flow: Vec<f32>,
+    pub rivers: Vec<River>,
+    /// Settings used to trace the rivers.
+    pub river: RiverConfig,
+    /// World seed the atlas was generated with. Features shared by neighbor maps, like river
+    /// meanders, are seeded with it, so they keep matching when a map is generated again using
+    /// another seed.
+    pub seed: u64,
 }
 
 impl Atlas {
     pub fn new() -> Self {
         Self {
             elevation: vec![0.0; ATLAS_SIZE],
+            flow: vec![0.0; ATLAS_SIZE],
+            rivers: vec![],
+            river: default(),
+            seed: 0,
         }
     }
 }
+
+/// The area covered by a single map on the atlas.
+#[derive(Debug, Clone, Copy)]
+pub struct AtlasRegion<'a> {
+    pub atlas: &'a Atlas,
+    /// Map coordinates, in the range `[0, MAP_COUNT)`.
+    pub map: U16Vec2,
+}
+
+/// The map which is currently being played or edited.
+#[derive(Debug, Clone, Copy, Resource, Reflect, Deref)]
+pub struct ActiveMap(pub U16Vec2);
+
+impl Default for ActiveMap {
+    fn default() -> Self {
+        Self(U16Vec2::splat(MAP_COUNT / 2))
+    }
+}
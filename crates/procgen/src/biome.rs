@@ -1,13 +1,22 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use eternal_config::{
-    biome::{BiomePalletConfig, BiomeRegistryConfig},
-    flora::FloraSpawnRegistryConfig,
+    biome::{BiomeConfig, BiomePalletConfig, BiomeRegistryConfig, PalletLayerConfig},
+    flora::{FloraSpawnConfig, FloraSpawnRegistryConfig},
     noise::NoiseStackConfig,
+    prefab::{StructureSpawnConfig, StructureSpawnRegistryConfig},
+    resource::{ResourceSpawnConfig, ResourceSpawnRegistryConfig, VeinShapeConfig},
+    road::RoadConfig,
     server::{ConfigAssetUpdated, ConfigServer, Configs},
 };
 use eternal_grid::{ecs::TileRegistry, grid::LayerIndex, tile::TileId};
 
-use crate::noise::NoiseStack;
+use crate::{
+    noise::{NoiseStack, NoiseStackParserError},
+    rng::Rng,
+    road::{RoadError, Roads},
+    rule::{PlacementRule, PlacementRuleError},
+    wfc::AdjacencyRepair,
+};
 
 pub(crate) struct BiomePlugin;
 
@@ -22,41 +31,356 @@ impl Plugin for BiomePlugin {
 pub struct Flora {
     pub name: String,
     pub tile: TileId,
-    pub threshold: f32,
+    pub rule: PlacementRule,
     pub wall_spacing: u8,
     pub floor_spacing: u8,
-    pub elevation_range: Option<(f32, f32)>,
     pub allowed_terrains: Vec<TileId>,
+    pub weight: f32,
+    pub min_distance: f32,
+    pub species_distance: f32,
+    pub clustering: f32,
 }
 
 #[derive(Default, Debug, Clone, Reflect, Deref)]
 pub struct FloraRegistry(Vec<Flora>);
 
+impl FloraRegistry {
+    pub fn new(floras: Vec<Flora>) -> Self {
+        Self(floras)
+    }
+
+    pub fn from_config(
+        config: &FloraSpawnRegistryConfig,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, BiomeError> {
+        Ok(Self(
+            config
+                .iter()
+                .map(|flora_config| {
+                    Ok(Flora {
+                        name: flora_config.name.clone(),
+                        tile: tile_registry.get_id_by_name(&flora_config.flora),
+                        rule: flora_rule(flora_config, tile_registry)
+                            .map_err(|err| BiomeError::Rule(flora_config.name.clone(), err))?,
+                        wall_spacing: flora_config.wall_spacing,
+                        floor_spacing: flora_config.floor_spacing,
+                        allowed_terrains: flora_config
+                            .allowed_terrains
+                            .iter()
+                            .map(|name| tile_registry.get_id_by_name(name))
+                            .collect(),
+                        weight: flora_config.weight,
+                        min_distance: flora_config.min_distance,
+                        species_distance: flora_config.species_distance,
+                        clustering: flora_config.clustering.clamp(0.0, 1.0),
+                    })
+                })
+                .collect::<Result<_, BiomeError>>()?,
+        ))
+    }
+}
+
+#[derive(Default, Debug, Clone, Reflect)]
+pub struct StructureSpawn {
+    pub name: String,
+    pub prefab: String,
+    pub count: (u32, u32),
+    pub flatness: f32,
+    pub clearance: u8,
+    pub rule: PlacementRule,
+}
+
+impl StructureSpawn {
+    pub fn from_config(
+        config: &StructureSpawnConfig,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, BiomeError> {
+        Ok(Self {
+            name: config.name.clone(),
+            prefab: config.prefab.clone(),
+            count: config.count,
+            flatness: config.flatness,
+            clearance: config.clearance,
+            rule: config
+                .rule
+                .as_ref()
+                .map(|rule| PlacementRule::from_config(rule, tile_registry))
+                .transpose()
+                .map_err(|err| BiomeError::Rule(config.name.clone(), err))?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Reflect)]
+pub enum VeinShape {
+    RandomWalk { steps: (u32, u32) },
+    Blob { radius: (u8, u8) },
+}
+
+impl Default for VeinShape {
+    fn default() -> Self {
+        Self::Blob { radius: (1, 1) }
+    }
+}
+
+#[derive(Default, Debug, Clone, Reflect)]
+pub struct ResourceSpawn {
+    pub name: String,
+    /// Wall tile placed on vein tiles. `None` for resources which are only map metadata.
+    pub tile: Option<TileId>,
+    pub hosts: Vec<TileId>,
+    pub shape: VeinShape,
+    pub count: (u32, u32),
+    pub depth: (u8, u8),
+    pub rule: PlacementRule,
+}
+
+impl ResourceSpawn {
+    pub fn from_config(
+        config: &ResourceSpawnConfig,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, BiomeError> {
+        let mut rules = vec![];
+
+        // Without a threshold, biomes don't need a resource noise.
+        if config.threshold.is_finite() {
+            rules.push(PlacementRule::Noise {
+                min: config.threshold,
+                max: f32::INFINITY,
+            });
+        }
+
+        if let Some((min, max)) = config.elevation_range {
+            rules.push(PlacementRule::Elevation { min, max });
+        }
+
+        if let Some(rule) = &config.rule {
+            rules.push(
+                PlacementRule::from_config(rule, tile_registry)
+                    .map_err(|err| BiomeError::Rule(config.name.clone(), err))?,
+            );
+        }
+
+        let tile = |name: &str| {
+            tile_registry
+                .find_id_by_name(name)
+                .ok_or_else(|| BiomeError::ResourceTile(config.name.clone(), name.to_string()))
+        };
+
+        Ok(Self {
+            name: config.name.clone(),
+            tile: config.tile.as_deref().map(tile).transpose()?,
+            hosts: config
+                .hosts
+                .iter()
+                .map(|name| tile(name))
+                .collect::<Result<_, _>>()?,
+            shape: match config.shape {
+                VeinShapeConfig::RandomWalk { steps } => VeinShape::RandomWalk { steps },
+                VeinShapeConfig::Blob { radius } => VeinShape::Blob { radius },
+            },
+            count: config.count,
+            depth: config.depth,
+            rule: PlacementRule::All(rules),
+        })
+    }
+}
+
+/// Value pallet entries are matched against.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub enum PalletChannel {
+    Elevation,
+    Slope,
+    /// A layer of the biome terrain noise.
+    Noise(String),
+}
+
+impl PalletChannel {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "elevation" => Self::Elevation,
+            "slope" => Self::Slope,
+            _ => Self::Noise(name.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum BiomePalletError {
+    #[error("Tile {0} not found")]
+    UnknownTile(String),
+}
+
+#[derive(Default, Debug, Clone, Reflect)]
+struct PalletEntry {
+    tile: TileId,
+    /// Channel index on [`BiomePallet::channels`], min and max values.
+    ranges: Vec<(usize, f32, f32)>,
+    weight: f32,
+}
+
+#[derive(Default, Debug, Clone, Reflect)]
+struct PalletLayer {
+    default: TileId,
+    entries: Vec<PalletEntry>,
+}
+
+impl PalletLayer {
+    fn collapse(&self, values: &[f32], rng: &mut Rng) -> TileId {
+        let matches = |entry: &&PalletEntry| {
+            entry
+                .ranges
+                .iter()
+                .all(|&(channel, min, max)| (min..max).contains(&values[channel]))
+        };
+
+        let Some(most_ranges) = self
+            .entries
+            .iter()
+            .filter(matches)
+            .map(|entry| entry.ranges.len())
+            .max()
+        else {
+            return self.default;
+        };
+
+        let candidates = self
+            .entries
+            .iter()
+            .filter(matches)
+            .filter(|entry| entry.ranges.len() == most_ranges)
+            .collect::<Vec<_>>();
+
+        // Only roll when there is a choice, so pallets without overlapping entries don't
+        // consume random numbers.
+        if let [entry] = candidates.as_slice() {
+            return entry.tile;
+        }
+
+        rng.pick_weighted(&candidates, |entry| entry.weight)
+            .map_or(self.default, |entry| entry.tile)
+    }
+}
+
+/// Compiled version of [`BiomePalletConfig`], with channels and tile names resolved.
 #[derive(Default, Debug, Clone, Reflect)]
 pub struct BiomePallet {
-    floor: Vec<(f32, TileId)>,
-    wall: Vec<(f32, TileId)>,
+    channels: Vec<PalletChannel>,
+    floor: PalletLayer,
+    wall: PalletLayer,
+    pub river: TileId,
+    pub river_bank: TileId,
+    /// Floor tiles preferred for the player spawn.
+    pub spawn: Vec<TileId>,
+    /// Floor tiles which can't be walked on, including the river tile.
+    pub unwalkable: Vec<TileId>,
+    pub repair: Option<AdjacencyRepair>,
 }
 
 impl BiomePallet {
-    pub fn collapse(&self, layer: LayerIndex, value: f32) -> TileId {
-        let layer = match layer {
-            LayerIndex::Floor => &self.floor,
-            LayerIndex::Wall => &self.wall,
-            LayerIndex::Roof => todo!(),
+    pub fn from_config(
+        config: &BiomePalletConfig,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, BiomePalletError> {
+        let tile = |name: &str| {
+            tile_registry
+                .find_id_by_name(name)
+                .ok_or_else(|| BiomePalletError::UnknownTile(name.to_string()))
+        };
+
+        let mut channels = vec![];
+        let mut layer = |config: &PalletLayerConfig| -> Result<PalletLayer, BiomePalletError> {
+            let entries = config
+                .entries
+                .iter()
+                .map(|entry| {
+                    let ranges = entry
+                        .ranges
+                        .iter()
+                        .map(|range| {
+                            let channel = PalletChannel::from_name(&range.channel);
+                            let idx =
+                                channels
+                                    .iter()
+                                    .position(|c| *c == channel)
+                                    .unwrap_or_else(|| {
+                                        channels.push(channel);
+                                        channels.len() - 1
+                                    });
+                            (idx, range.min, range.max)
+                        })
+                        .collect();
+
+                    Ok(PalletEntry {
+                        tile: tile(&entry.tile)?,
+                        ranges,
+                        weight: entry.weight,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+
+            Ok(PalletLayer {
+                default: tile(&config.default)?,
+                entries,
+            })
         };
 
-        for &(threshould, tile_id) in layer {
-            if value < threshould {
-                return tile_id;
-            }
+        let floor = layer(&config.floor)?;
+        let wall = layer(&config.wall)?;
+
+        let floor_tiles = std::iter::once(floor.default)
+            .chain(floor.entries.iter().map(|entry| entry.tile))
+            .collect::<Vec<_>>();
+        let repair = config
+            .repair
+            .as_ref()
+            .map(|repair| AdjacencyRepair::from_config(repair, &floor_tiles, tile_registry));
+
+        let river = tile(&config.river)?;
+        let mut unwalkable = config
+            .unwalkable
+            .iter()
+            .map(|name| tile(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !unwalkable.contains(&river) {
+            unwalkable.push(river);
         }
 
-        Default::default()
+        Ok(Self {
+            channels,
+            floor,
+            wall,
+            river,
+            river_bank: tile(&config.river_bank)?,
+            spawn: config
+                .spawn
+                .iter()
+                .map(|name| tile(name))
+                .collect::<Result<_, _>>()?,
+            unwalkable,
+            repair,
+        })
+    }
+
+    /// Channels used by pallet entries. Values given to [`Self::collapse`] must be in the same
+    /// order.
+    pub fn channels(&self) -> &[PalletChannel] {
+        &self.channels
+    }
+
+    /// Picks the tile of the given layer whose entry matches the channel values. Channels which
+    /// aren't available should be `NaN`, so entries using them never match.
+    pub fn collapse(&self, layer: LayerIndex, values: &[f32], rng: &mut Rng) -> TileId {
+        match layer {
+            LayerIndex::Floor => self.floor.collapse(values, rng),
+            LayerIndex::Wall => self.wall.collapse(values, rng),
+            LayerIndex::Roof => todo!(),
+        }
     }
 
     fn is_ready(&self) -> bool {
-        !self.floor.is_empty()
+        !self.floor.default.is_none() || !self.floor.entries.is_empty()
     }
 }
 
@@ -67,24 +391,93 @@ pub struct Biome {
     pub flora_noise: NoiseStack,
     pub terrain_noise: NoiseStack,
     pub terrain_pallet: BiomePallet,
+    pub structures: Vec<StructureSpawn>,
+    pub resources: Vec<ResourceSpawn>,
+    pub resource_noise: NoiseStack,
+    /// Roads of the biome. `None` until the biome is built from its configs.
+    pub roads: Option<Roads>,
+}
+
+/// Every config of a single biome.
+#[derive(Default, Clone)]
+pub struct BiomeConfigs {
+    pub terrain_noise: NoiseStackConfig,
+    pub terrain_pallet: BiomePalletConfig,
+    pub flora: FloraSpawnRegistryConfig,
+    pub flora_noise: NoiseStackConfig,
+    pub structures: StructureSpawnRegistryConfig,
+    pub resources: ResourceSpawnRegistryConfig,
+    pub resource_noise: NoiseStackConfig,
+    pub roads: RoadConfig,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BiomeError {
+    #[error("Failed to build terrain noise. {0}")]
+    TerrainNoise(NoiseStackParserError),
+    #[error("Failed to build flora noise. {0}")]
+    FloraNoise(NoiseStackParserError),
+    #[error("Failed to build resource noise. {0}")]
+    ResourceNoise(NoiseStackParserError),
+    #[error("Failed to build pallet. {0}")]
+    Pallet(#[from] BiomePalletError),
+    #[error("Failed to build placement rule of {0}. {1}")]
+    Rule(String, PlacementRuleError),
+    #[error("Failed to build resource {0}. Tile {1} not found")]
+    ResourceTile(String, String),
+    #[error("Failed to build roads. {0}")]
+    Roads(#[from] RoadError),
 }
 
 impl Biome {
+    /// Builds a biome from its configs, the same way [`BiomeRegistry`] is built from config
+    /// assets.
+    pub fn from_configs(
+        name: impl Into<String>,
+        configs: &BiomeConfigs,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, BiomeError> {
+        Ok(Self {
+            name: name.into(),
+            flora_registry: FloraRegistry::from_config(&configs.flora, tile_registry)?,
+            flora_noise: NoiseStack::from_config(&configs.flora_noise)
+                .map_err(BiomeError::FloraNoise)?,
+            terrain_noise: NoiseStack::from_config(&configs.terrain_noise)
+                .map_err(BiomeError::TerrainNoise)?,
+            terrain_pallet: BiomePallet::from_config(&configs.terrain_pallet, tile_registry)?,
+            structures: configs
+                .structures
+                .iter()
+                .map(|config| StructureSpawn::from_config(config, tile_registry))
+                .collect::<Result<_, _>>()?,
+            resources: configs
+                .resources
+                .iter()
+                .map(|config| ResourceSpawn::from_config(config, tile_registry))
+                .collect::<Result<_, _>>()?,
+            resource_noise: NoiseStack::from_config(&configs.resource_noise)
+                .map_err(BiomeError::ResourceNoise)?,
+            roads: Some(Roads::from_config(&configs.roads, tile_registry)?),
+        })
+    }
+
     fn is_ready(&self) -> bool {
         self.flora_noise.is_ready()
             && self.terrain_noise.is_ready()
             && self.terrain_pallet.is_ready()
             && !self.flora_registry.is_empty()
+            && (self.resources.is_empty() || self.resource_noise.is_ready())
     }
 }
 
-#[derive(Component, Deref)]
-struct BiomeName(String);
-
 #[derive(Default, Debug, Clone, Resource, Reflect, Deref)]
 pub struct BiomeRegistry(Vec<Biome>);
 
 impl BiomeRegistry {
+    pub fn new(biomes: Vec<Biome>) -> Self {
+        Self(biomes)
+    }
+
     pub fn get_biome(&self, name: &str) -> Option<&Biome> {
         self.0.iter().find(|b| b.name == name)
     }
@@ -108,179 +501,272 @@ fn setup(mut config_server: ConfigServer) {
         .observe(on_biome_config_updated);
 }
 
-#[derive(Component, Debug, Clone, Copy)]
-enum NoiseType {
-    Terrain,
-    Flora,
+/// Configs referenced by biome configs.
+#[derive(SystemParam)]
+struct BiomeRefs<'w> {
+    noise: Configs<'w, NoiseStackConfig>,
+    pallet: Configs<'w, BiomePalletConfig>,
+    flora: Configs<'w, FloraSpawnRegistryConfig>,
+    structures: Configs<'w, StructureSpawnRegistryConfig>,
+    resources: Configs<'w, ResourceSpawnRegistryConfig>,
+    roads: Configs<'w, RoadConfig>,
 }
 
+impl BiomeRefs<'_> {
+    fn configs(&self, config: &BiomeConfig) -> Option<BiomeConfigs> {
+        Some(BiomeConfigs {
+            terrain_noise: self.noise.get_ref(&config.terrain_noise)?.clone(),
+            terrain_pallet: self.pallet.get_ref(&config.terrain_pallet)?.clone(),
+            flora: self.flora.get_ref(&config.flora)?.clone(),
+            flora_noise: self.noise.get_ref(&config.flora_noise)?.clone(),
+            structures: self.structures.get_ref(&config.structures)?.clone(),
+            resources: self.resources.get_ref(&config.resources)?.clone(),
+            resource_noise: self.noise.get_ref(&config.resource_noise)?.clone(),
+            roads: self.roads.get_ref(&config.roads)?.clone(),
+        })
+    }
+}
+
+/// Rebuilds every biome. Referenced configs are loaded with the biome registry config, which is
+/// updated again when any of them changes.
 fn on_biome_config_updated(
     updated: On<ConfigAssetUpdated>,
     biome_configs: Configs<BiomeRegistryConfig>,
-    mut config_server: ConfigServer,
+    refs: BiomeRefs,
+    tile_registry: Res<TileRegistry>,
     mut commands: Commands,
 ) {
     let id = updated.id();
-    let Some(BiomeRegistryConfig(biome_configs)) = biome_configs.get(id).cloned() else {
+    let Some(biome_configs) = biome_configs.get(id) else {
         error!("Biome registry config not found for id {}", id);
         return;
     };
 
-    let mut biomes = Vec::with_capacity(biome_configs.len());
-
-    for config in biome_configs {
-        config_server
-            .load::<BiomePalletConfig>(&config.terrain_pallet)
-            .insert(BiomeName(config.name.clone()))
-            .observe(on_biome_terrain_pallet_config_updated);
-
-        config_server
-            .load::<NoiseStackConfig>(&config.terrain_noise)
-            .insert((BiomeName(config.name.clone()), NoiseType::Terrain))
-            .observe(on_biome_noise_config_updated);
-
-        config_server
-            .load::<NoiseStackConfig>(&config.flora_noise)
-            .insert((BiomeName(config.name.clone()), NoiseType::Flora))
-            .observe(on_biome_noise_config_updated);
+    let biomes = biome_configs
+        .iter()
+        .map(|config| {
+            debug!("Updating biome {}!", config.name);
+
+            let Some(configs) = refs.configs(config) else {
+                error!("Configs of biome {} aren't loaded", config.name);
+                return Biome {
+                    name: config.name.clone(),
+                    ..default()
+                };
+            };
+
+            Biome::from_configs(&config.name, &configs, &tile_registry).unwrap_or_else(|err| {
+                error!("Failed to build biome {}. {err}", config.name);
+                Biome {
+                    name: config.name.clone(),
+                    ..default()
+                }
+            })
+        })
+        .collect();
 
-        config_server
-            .load::<FloraSpawnRegistryConfig>(&config.flora)
-            .insert(BiomeName(config.name.clone()))
-            .observe(on_biome_flora_config_updated);
+    commands.insert_resource(BiomeRegistry(biomes));
+}
 
-        let biome = Biome {
-            name: config.name,
-            ..default()
-        };
+/// Combines the flora noise threshold and elevation range with its custom placement rule.
+fn flora_rule(
+    config: &FloraSpawnConfig,
+    tile_registry: &TileRegistry,
+) -> Result<PlacementRule, PlacementRuleError> {
+    // Flora noise must be strictly above the threshold, so negated since noise ranges are
+    // inclusive.
+    let mut rules = vec![PlacementRule::Not(vec![PlacementRule::Noise {
+        min: f32::NEG_INFINITY,
+        max: config.threshold,
+    }])];
+
+    if let Some((min, max)) = config.elevation_range {
+        rules.push(PlacementRule::Elevation { min, max });
+    }
 
-        biomes.push(biome);
+    if let Some(rule) = &config.rule {
+        rules.push(PlacementRule::from_config(rule, tile_registry)?);
     }
 
-    commands.insert_resource(BiomeRegistry(biomes));
+    Ok(PlacementRule::All(rules))
 }
 
-fn on_biome_terrain_pallet_config_updated(
-    updated: On<ConfigAssetUpdated>,
-    q_names: Query<&BiomeName>,
-    pallet_configs: Configs<BiomePalletConfig>,
-    mut registry: ResMut<BiomeRegistry>,
-    tile_registry: Res<TileRegistry>,
-) {
-    let biome_name = q_names
-        .get(updated.event_target())
-        .expect("Observer to have BiomeName component")
-        .as_str();
-
-    debug!("Updating pallet of biome {biome_name}!");
-
-    let Some(pallet_config) = pallet_configs.get(updated.id()) else {
-        error!("Pallet config not found for biome {biome_name}");
-        return;
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashMap;
+    use eternal_config::{
+        biome::{PalletEntryConfig, PalletRangeConfig},
+        noise::NoiseFnConfig,
     };
+    use eternal_grid::tile::TileInfo;
 
-    let Some(biome) = registry.get_biome_mut(biome_name) else {
-        error!("Biome {biome_name} not found on registry!");
-        return;
+    use super::*;
+    use crate::{
+        map::Map,
+        rule::{DistanceFields, RuleContext},
     };
 
-    let floor = pallet_config
-        .floor
-        .iter()
-        .map(|(threshould, tile_name)| {
-            let tile_id = tile_registry.get_id_by_name(tile_name);
-            (*threshould, tile_id)
-        })
-        .collect();
-
-    let wall = pallet_config
-        .wall
-        .iter()
-        .map(|(threshould, tile_name)| {
-            let tile_id = tile_registry.get_id_by_name(tile_name);
-            (*threshould, tile_id)
-        })
-        .collect();
+    fn tile_registry() -> TileRegistry {
+        let tiles = ["STONE", "WATER", "GRASS", "MUD"]
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| {
+                (
+                    TileId::new(id as u16 + 1),
+                    TileInfo {
+                        name: name.into(),
+                        ..default()
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        TileRegistry::new(tiles)
+    }
 
-    biome.terrain_pallet = BiomePallet { floor, wall }
-}
+    fn entry(tile: &str, ranges: &[(&str, f32, f32)], weight: f32) -> PalletEntryConfig {
+        PalletEntryConfig {
+            tile: tile.to_string(),
+            ranges: ranges
+                .iter()
+                .map(|&(channel, min, max)| PalletRangeConfig {
+                    channel: channel.to_string(),
+                    min,
+                    max,
+                })
+                .collect(),
+            weight,
+        }
+    }
 
-fn on_biome_noise_config_updated(
-    updated: On<ConfigAssetUpdated>,
-    q_params: Query<(&BiomeName, &NoiseType)>,
-    configs: Configs<NoiseStackConfig>,
-    mut registry: ResMut<BiomeRegistry>,
-) {
-    let (BiomeName(biome_name), &noise_type) = q_params
-        .get(updated.event_target())
-        .expect("Observer to have BiomeName and NoiseType components");
+    fn pallet_config() -> BiomePalletConfig {
+        BiomePalletConfig {
+            floor: PalletLayerConfig {
+                default: "STONE".to_string(),
+                entries: vec![
+                    entry("WATER", &[("elevation", f32::NEG_INFINITY, 0.0)], 1.0),
+                    entry("GRASS", &[("elevation", 0.0, 0.5)], 1.0),
+                    entry(
+                        "MUD",
+                        &[("elevation", 0.0, 0.5), ("moisture", 0.5, f32::INFINITY)],
+                        1.0,
+                    ),
+                    entry(
+                        "WATER",
+                        &[("elevation", 0.0, 0.5), ("moisture", 0.5, f32::INFINITY)],
+                        1.0,
+                    ),
+                ],
+            },
+            wall: PalletLayerConfig {
+                default: "NONE".to_string(),
+                entries: vec![],
+            },
+            river: "WATER".to_string(),
+            river_bank: "MUD".to_string(),
+            spawn: vec!["GRASS".to_string()],
+            unwalkable: vec![],
+            repair: None,
+        }
+    }
 
-    debug!("Updating noise of biome {biome_name} ({noise_type:?})");
+    #[test]
+    fn collapse_pallet() {
+        // Arrange
+        let pallet = BiomePallet::from_config(&pallet_config(), &tile_registry()).unwrap();
+        let tiles = tile_registry();
+        let mut rng = Rng::new(0);
 
-    let Some(noise_config) = configs.get(updated.id()) else {
-        error!("Noise config not found for biome {biome_name}.");
-        return;
-    };
+        // Act
+        let mut collapse = |elevation: f32, moisture: f32| {
+            pallet.collapse(LayerIndex::Floor, &[elevation, moisture], &mut rng)
+        };
 
-    let stack = match NoiseStack::from_config(noise_config) {
-        Ok(s) => s,
-        Err(err) => {
-            error!("Failed to update terrain noise for biome {biome_name}. {err}");
-            return;
-        }
-    };
+        // Assert
+        assert_eq!(
+            pallet.channels(),
+            &[
+                PalletChannel::Elevation,
+                PalletChannel::Noise("moisture".to_string())
+            ]
+        );
+        assert_eq!(collapse(-0.5, 0.0), tiles.get_id_by_name("WATER"));
+        assert_eq!(collapse(0.2, 0.0), tiles.get_id_by_name("GRASS"));
+        assert_eq!(collapse(0.7, 0.0), tiles.get_id_by_name("STONE"));
+        // Unavailable channels never match
+        assert_eq!(collapse(0.2, f32::NAN), tiles.get_id_by_name("GRASS"));
+        // Entries matching more channels win, picking between equal matches
+        let picked = (0..100).map(|_| collapse(0.2, 0.8)).collect::<Vec<_>>();
+        assert!(picked.contains(&tiles.get_id_by_name("MUD")));
+        assert!(picked.contains(&tiles.get_id_by_name("WATER")));
+        assert!(!picked.contains(&tiles.get_id_by_name("GRASS")));
+        assert!(
+            pallet
+                .collapse(LayerIndex::Wall, &[0.2, 0.8], &mut Rng::new(0))
+                .is_none()
+        );
+    }
 
-    let Some(biome) = registry.get_biome_mut(biome_name) else {
-        error!("Biome {biome_name} not found on registry!");
-        return;
-    };
+    #[test]
+    fn flora_strictly_above_threshold() {
+        // Arrange
+        let map = Map::new("test".to_string(), 0);
+        let noise = NoiseStack::from_config(&NoiseStackConfig(vec![(
+            "main".to_string(),
+            NoiseFnConfig::Constant(0.5),
+        )]))
+        .unwrap();
+        let distances = DistanceFields::default();
+        let ctx = RuleContext {
+            map: &map,
+            noise: &noise,
+            distances: &distances,
+        };
+        let rule = |threshold| {
+            let config = FloraSpawnConfig {
+                threshold,
+                ..default()
+            };
+            flora_rule(&config, &tile_registry()).unwrap()
+        };
 
-    match noise_type {
-        NoiseType::Terrain => biome.terrain_noise = stack,
-        NoiseType::Flora => biome.flora_noise = stack,
+        // Act & Assert
+        assert!(rule(0.4).check(0, 0, &ctx));
+        assert!(!rule(0.5).check(0, 0, &ctx));
     }
-}
 
-fn on_biome_flora_config_updated(
-    updated: On<ConfigAssetUpdated>,
-    q_names: Query<&BiomeName>,
-    flora_configs: Configs<FloraSpawnRegistryConfig>,
-    mut registry: ResMut<BiomeRegistry>,
-    tile_registry: Res<TileRegistry>,
-) {
-    let biome_name = q_names
-        .get(updated.event_target())
-        .expect("Observer to have BiomeName component")
-        .as_str();
+    #[test]
+    fn resource_unknown_tile() {
+        // Arrange
+        let config = ResourceSpawnConfig {
+            name: "iron".to_string(),
+            tile: Some("IRON_ORE".to_string()),
+            hosts: vec!["STONE".to_string()],
+            ..default()
+        };
 
-    debug!("Updating flora registry of biome {biome_name}!");
+        // Act
+        let result = ResourceSpawn::from_config(&config, &tile_registry());
 
-    let Some(flora_registry_config) = flora_configs.get(updated.id()) else {
-        error!("Flora registry config not found for biome {biome_name}");
-        return;
-    };
+        // Assert
+        assert!(matches!(
+            result,
+            Err(BiomeError::ResourceTile(resource, tile)) if resource == "iron" && tile == "IRON_ORE"
+        ));
+    }
 
-    let Some(biome) = registry.get_biome_mut(biome_name) else {
-        error!("Biome {biome_name} not found on registry!");
-        return;
-    };
+    #[test]
+    fn pallet_unknown_tile() {
+        // Arrange
+        let mut config = pallet_config();
+        config.floor.entries.push(entry("LAVA", &[], 1.0));
 
-    let registry = flora_registry_config
-        .iter()
-        .map(|flora_config| Flora {
-            name: flora_config.name.clone(),
-            tile: tile_registry.get_id_by_name(&flora_config.flora),
-            threshold: flora_config.threshold,
-            wall_spacing: flora_config.wall_spacing,
-            floor_spacing: flora_config.floor_spacing,
-            elevation_range: flora_config.elevation_range,
-            allowed_terrains: flora_config
-                .allowed_terrains
-                .iter()
-                .map(|name| tile_registry.get_id_by_name(name))
-                .collect(),
-        })
-        .collect();
+        // Act
+        let result = BiomePallet::from_config(&config, &tile_registry());
 
-    biome.flora_registry = FloraRegistry(registry);
+        // Assert
+        assert_eq!(
+            result.unwrap_err(),
+            BiomePalletError::UnknownTile("LAVA".to_string())
+        );
+    }
 }
@@ -0,0 +1,337 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use bevy::{math::U16Vec2, prelude::*};
+use eternal_config::erosion::{
+    ErosionConfig, HydraulicErosionConfig, RiverConfig, ThermalErosionConfig,
+};
+
+use crate::{
+    atlas::{self, ATLAS_AXIS_SIZE, ATLAS_SIZE, Atlas, River},
+    rng::Rng,
+};
+
+const NEIGHBORS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Runs hydraulic and thermal erosion over the atlas elevation and then traces rivers.
+pub fn erode(atlas: &mut Atlas, config: &ErosionConfig, rng: &mut Rng) {
+    hydraulic_erosion(&mut atlas.elevation, &config.hydraulic, rng);
+    thermal_erosion(&mut atlas.elevation, &config.thermal);
+    trace_rivers(atlas, &config.river);
+}
+
+fn neighbors(index: usize) -> impl Iterator<Item = usize> {
+    let pos = atlas::from_index(index).as_ivec2();
+    NEIGHBORS.into_iter().filter_map(move |(dx, dy)| {
+        let (x, y) = (pos.x + dx, pos.y + dy);
+        let size = ATLAS_AXIS_SIZE as i32;
+        (x >= 0 && y >= 0 && x < size && y < size).then(|| atlas::to_index(x as u16, y as u16))
+    })
+}
+
+/// Returns the bilinear interpolated height and gradient at the given position.
+fn height_and_gradient(elevation: &[f32], pos: Vec2) -> (f32, Vec2) {
+    let cell = pos.floor();
+    let (x, y) = (cell.x as u16, cell.y as u16);
+    let offset = pos - cell;
+
+    let nw = elevation[atlas::to_index(x, y)];
+    let ne = elevation[atlas::to_index(x + 1, y)];
+    let sw = elevation[atlas::to_index(x, y + 1)];
+    let se = elevation[atlas::to_index(x + 1, y + 1)];
+
+    let gradient = Vec2::new(
+        (ne - nw) * (1.0 - offset.y) + (se - sw) * offset.y,
+        (sw - nw) * (1.0 - offset.x) + (se - ne) * offset.x,
+    );
+
+    let height = nw * (1.0 - offset.x) * (1.0 - offset.y)
+        + ne * offset.x * (1.0 - offset.y)
+        + sw * (1.0 - offset.x) * offset.y
+        + se * offset.x * offset.y;
+
+    (height, gradient)
+}
+
+/// Adds the given amount to the four cells around the position, weighted by distance.
+fn spread(elevation: &mut [f32], pos: Vec2, amount: f32) {
+    let cell = pos.floor();
+    let (x, y) = (cell.x as u16, cell.y as u16);
+    let offset = pos - cell;
+
+    elevation[atlas::to_index(x, y)] += amount * (1.0 - offset.x) * (1.0 - offset.y);
+    elevation[atlas::to_index(x + 1, y)] += amount * offset.x * (1.0 - offset.y);
+    elevation[atlas::to_index(x, y + 1)] += amount * (1.0 - offset.x) * offset.y;
+    elevation[atlas::to_index(x + 1, y + 1)] += amount * offset.x * offset.y;
+}
+
+fn hydraulic_erosion(elevation: &mut [f32], config: &HydraulicErosionConfig, rng: &mut Rng) {
+    // Droplets must stay at least one cell away from the right and bottom borders, since
+    // bilinear sampling reads the next cell.
+    let max = (ATLAS_AXIS_SIZE - 1) as f32;
+
+    for _ in 0..config.droplets {
+        let mut pos = Vec2::new(rng.range_f32(0.0, max), rng.range_f32(0.0, max));
+        let mut dir = Vec2::ZERO;
+        let mut speed = 1.0;
+        let mut water = 1.0;
+        let mut sediment = 0.0;
+
+        for _ in 0..config.max_lifetime {
+            let (height, gradient) = height_and_gradient(elevation, pos);
+
+            dir = (dir * config.inertia - gradient * (1.0 - config.inertia)).normalize_or_zero();
+            if dir == Vec2::ZERO {
+                break;
+            }
+
+            let old_pos = pos;
+            pos += dir;
+
+            if pos.cmplt(Vec2::ZERO).any() || pos.cmpge(Vec2::splat(max)).any() {
+                break;
+            }
+
+            let (new_height, _) = height_and_gradient(elevation, pos);
+            let delta = new_height - height;
+
+            let capacity = (-delta * speed * water * config.capacity).max(config.min_capacity);
+
+            if sediment > capacity || delta > 0.0 {
+                // Going uphill fills the pit behind, otherwise drops the excess sediment.
+                let deposit = if delta > 0.0 {
+                    delta.min(sediment)
+                } else {
+                    (sediment - capacity) * config.deposit_speed
+                };
+                sediment -= deposit;
+                spread(elevation, old_pos, deposit);
+            } else {
+                // Never erode more than the height difference, to avoid digging holes.
+                let erode = ((capacity - sediment) * config.erode_speed).min(-delta);
+                sediment += erode;
+                spread(elevation, old_pos, -erode);
+            }
+
+            speed = (speed * speed + delta * config.gravity).max(0.0).sqrt();
+            water *= 1.0 - config.evaporate_speed;
+        }
+    }
+}
+
+fn thermal_erosion(elevation: &mut [f32], config: &ThermalErosionConfig) {
+    let mut delta = vec![0.0; ATLAS_SIZE];
+
+    for _ in 0..config.iterations {
+        delta.fill(0.0);
+
+        for index in 0..ATLAS_SIZE {
+            let height = elevation[index];
+            for neighbor in neighbors(index) {
+                let diff = height - elevation[neighbor];
+                if diff > config.talus {
+                    // Split between all 8 neighbors, so a cell never gives more than it has.
+                    let amount = (diff - config.talus) * config.rate / NEIGHBORS.len() as f32;
+                    delta[index] -= amount;
+                    delta[neighbor] += amount;
+                }
+            }
+        }
+
+        elevation
+            .iter_mut()
+            .zip(&delta)
+            .for_each(|(height, delta)| *height += delta);
+    }
+}
+
+/// Cell ordered by elevation, lowest first, to be used on a [`BinaryHeap`].
+#[derive(PartialEq)]
+struct Lowest(f32, usize);
+
+impl Eq for Lowest {}
+
+impl PartialOrd for Lowest {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Lowest {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0).then(other.1.cmp(&self.1))
+    }
+}
+
+/// Fills depressions using priority-flood, starting from the sea and atlas borders, so every land
+/// cell has a downhill path to the sea. Returns the filled elevation and the downstream cell of
+/// each cell, if any.
+fn flow_directions(elevation: &[f32], sea_level: f32) -> (Vec<f32>, Vec<Option<usize>>) {
+    const EPSILON: f32 = 1e-5;
+
+    let mut filled = elevation.to_vec();
+    let mut downstream = vec![None; ATLAS_SIZE];
+    let mut visited = vec![false; ATLAS_SIZE];
+    let mut queue = BinaryHeap::new();
+
+    for (index, &height) in elevation.iter().enumerate() {
+        let pos = atlas::from_index(index);
+        let border = pos.cmpeq(U16Vec2::ZERO).any()
+            || pos.cmpeq(U16Vec2::splat(ATLAS_AXIS_SIZE as u16 - 1)).any();
+
+        if height < sea_level || border {
+            visited[index] = true;
+            queue.push(Lowest(height, index));
+        }
+    }
+
+    while let Some(Lowest(height, index)) = queue.pop() {
+        for neighbor in neighbors(index) {
+            if visited[neighbor] {
+                continue;
+            }
+
+            visited[neighbor] = true;
+            filled[neighbor] = filled[neighbor].max(height + EPSILON);
+            downstream[neighbor] = Some(index);
+            queue.push(Lowest(filled[neighbor], neighbor));
+        }
+    }
+
+    (filled, downstream)
+}
+
+fn trace_rivers(atlas: &mut Atlas, config: &RiverConfig) {
+    let (filled, downstream) = flow_directions(&atlas.elevation, config.sea_level);
+
+    // Accumulate flow from the highest cells down to the sea.
+    let mut order = (0..ATLAS_SIZE).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| filled[b].total_cmp(&filled[a]).then(a.cmp(&b)));
+
+    let mut flow = vec![1.0; ATLAS_SIZE];
+    for &index in &order {
+        if let Some(down) = downstream[index] {
+            flow[down] += flow[index];
+        }
+    }
+
+    let is_river = |index: usize| {
+        flow[index] >= config.flow_threshold && atlas.elevation[index] >= config.sea_level
+    };
+
+    let mut has_upstream = vec![false; ATLAS_SIZE];
+    for index in (0..ATLAS_SIZE).filter(|&i| is_river(i)) {
+        if let Some(down) = downstream[index] {
+            has_upstream[down] = true;
+        }
+    }
+
+    // Trace each river from its source until it reaches the sea or joins another river.
+    let mut visited = vec![false; ATLAS_SIZE];
+    let mut rivers = vec![];
+    for &source in &order {
+        if !is_river(source) || has_upstream[source] {
+            continue;
+        }
+
+        let mut path = vec![atlas::from_index(source)];
+        visited[source] = true;
+
+        let mut current = source;
+        while let Some(down) = downstream[current] {
+            path.push(atlas::from_index(down));
+
+            if visited[down] || !is_river(down) {
+                break;
+            }
+
+            visited[down] = true;
+            current = down;
+        }
+
+        rivers.push(River { path });
+    }
+
+    atlas.flow = flow;
+    atlas.rivers = rivers;
+    atlas.river = config.clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rivers_reach_the_sea() {
+        // Arrange
+        let mut atlas = Atlas::new();
+        for (index, height) in atlas.elevation.iter_mut().enumerate() {
+            // A slope going down to the sea on the left side, with a valley in the middle.
+            let pos = atlas::from_index(index).as_vec2();
+            let valley = (pos.y - ATLAS_AXIS_SIZE as f32 / 2.0).abs() * 0.001;
+            *height = pos.x * 0.01 - 0.5 + valley;
+        }
+        let config = RiverConfig {
+            sea_level: 0.0,
+            flow_threshold: 200.0,
+            ..default()
+        };
+
+        // Act
+        trace_rivers(&mut atlas, &config);
+
+        // Assert
+        assert!(!atlas.rivers.is_empty());
+        for river in &atlas.rivers {
+            let mouth = *river.path.last().unwrap();
+            let index = atlas::to_index(mouth.x, mouth.y);
+            let joined = atlas
+                .rivers
+                .iter()
+                .filter(|other| !std::ptr::eq(*other, river))
+                .any(|other| other.path.contains(&mouth));
+            assert!(atlas.elevation[index] < config.sea_level || joined);
+        }
+    }
+
+    #[test]
+    fn erosion_is_deterministic() {
+        // Arrange
+        let config = HydraulicErosionConfig {
+            droplets: 500,
+            max_lifetime: 30,
+            inertia: 0.05,
+            capacity: 4.0,
+            min_capacity: 0.01,
+            erode_speed: 0.3,
+            deposit_speed: 0.3,
+            evaporate_speed: 0.01,
+            gravity: 4.0,
+        };
+        let hills = || {
+            (0..ATLAS_SIZE)
+                .map(|i| {
+                    let pos = atlas::from_index(i).as_vec2();
+                    (pos.x * 0.1).sin() * (pos.y * 0.1).cos()
+                })
+                .collect::<Vec<_>>()
+        };
+        let (mut a, mut b) = (hills(), hills());
+
+        // Act
+        hydraulic_erosion(&mut a, &config, &mut Rng::new(3));
+        hydraulic_erosion(&mut b, &config, &mut Rng::new(3));
+
+        // Assert
+        assert_eq!(a, b);
+        assert_ne!(a, hills());
+    }
+}
@@ -0,0 +1,299 @@
+use bevy::{math::U16Vec2, prelude::*};
+use eternal_grid::{
+    grid::{self, LayerIndex},
+    tile::TileId,
+};
+
+use crate::{
+    biome::{Biome, Flora},
+    map::Map,
+    rng::Rng,
+    rule::{self, RuleContext},
+};
+
+/// How many candidates are tried around each active sample before it is retired.
+const POISSON_ATTEMPTS: u32 = 30;
+
+/// The smallest allowed sample radius, so two samples never end up on the same tile.
+const MIN_SAMPLE_RADIUS: f32 = 1.5;
+
+/// A flora which was placed on the map.
+#[derive(Debug, Clone, Copy)]
+struct Placed {
+    pos: Vec2,
+    flora: usize,
+}
+
+/// Bucket grid used to query placed flora nearby a given position.
+struct SpatialIndex {
+    cell_size: f32,
+    dims: UVec2,
+    cells: Vec<Vec<Placed>>,
+}
+
+impl SpatialIndex {
+    fn new(cell_size: f32) -> Self {
+        let dims = (grid::DIMS.as_vec2() / cell_size).ceil().as_uvec2();
+        Self {
+            cell_size,
+            dims,
+            cells: vec![vec![]; dims.element_product() as usize],
+        }
+    }
+
+    fn cell(&self, pos: Vec2) -> UVec2 {
+        (pos / self.cell_size)
+            .as_uvec2()
+            .min(self.dims - UVec2::ONE)
+    }
+
+    fn insert(&mut self, placed: Placed) {
+        let cell = self.cell(placed.pos);
+        self.cells[(cell.y * self.dims.x + cell.x) as usize].push(placed);
+    }
+
+    fn nearby(&self, pos: Vec2, radius: f32) -> impl Iterator<Item = &Placed> {
+        let min = self.cell((pos - radius).max(Vec2::ZERO));
+        let max = self.cell(pos + radius);
+
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| (y * self.dims.x + x) as usize))
+            .flat_map(|idx| self.cells[idx].iter())
+            .filter(move |p| p.pos.distance(pos) < radius)
+    }
+}
+
+/// Generates evenly spread sample points covering the whole map using Bridson's Poisson-disk
+/// sampling. The returned order is the order in which samples were discovered, which is random
+/// but deterministic for a given [`Rng`].
+fn poisson_disk_samples(radius: f32, rng: &mut Rng) -> Vec<Vec2> {
+    let cell_size = radius / std::f32::consts::SQRT_2;
+    let bounds = grid::DIMS.as_vec2();
+    let dims = (bounds / cell_size).ceil().as_uvec2();
+
+    let mut cells: Vec<Option<usize>> = vec![None; dims.element_product() as usize];
+    let cell_index = |p: Vec2| {
+        let cell = (p / cell_size).as_uvec2().min(dims - UVec2::ONE);
+        (cell.y * dims.x + cell.x) as usize
+    };
+
+    let mut samples = vec![];
+    let mut active = vec![];
+
+    let first = Vec2::new(rng.range_f32(0.0, bounds.x), rng.range_f32(0.0, bounds.y));
+    cells[cell_index(first)] = Some(0);
+    samples.push(first);
+    active.push(0);
+
+    while !active.is_empty() {
+        let active_idx = rng.range_u32(0, active.len() as u32) as usize;
+        let center = samples[active[active_idx]];
+
+        let mut found = false;
+        for _ in 0..POISSON_ATTEMPTS {
+            let angle = rng.range_f32(0.0, std::f32::consts::TAU);
+            let distance = rng.range_f32(radius, radius * 2.0);
+            let candidate = center + Vec2::from_angle(angle) * distance;
+
+            if candidate.cmplt(Vec2::ZERO).any() || candidate.cmpge(bounds).any() {
+                continue;
+            }
+
+            let cell = (candidate / cell_size).as_ivec2();
+            let too_close = (-2..=2).any(|dy| {
+                (-2..=2).any(|dx| {
+                    let neighbor = cell + IVec2::new(dx, dy);
+                    if neighbor.cmplt(IVec2::ZERO).any() || neighbor.cmpge(dims.as_ivec2()).any() {
+                        return false;
+                    }
+
+                    cells[(neighbor.y as u32 * dims.x + neighbor.x as u32) as usize]
+                        .is_some_and(|idx| samples[idx].distance(candidate) < radius)
+                })
+            });
+
+            if !too_close {
+                cells[cell_index(candidate)] = Some(samples.len());
+                active.push(samples.len());
+                samples.push(candidate);
+                found = true;
+                break;
+            }
+        }
+
+        if !found {
+            active.swap_remove(active_idx);
+        }
+    }
+
+    samples
+}
+
+/// Checks if the given flora can grow on the given tile, based on its rule and terrain only.
+fn can_grow(flora: &Flora, x: u16, y: u16, ctx: &RuleContext, walls: &[TileId]) -> bool {
+    if !flora.rule.check(x, y, ctx) {
+        return false;
+    }
+
+    let allowed =
+        |tile: &TileId| flora.allowed_terrains.is_empty() || flora.allowed_terrains.contains(tile);
+
+    let floor_layer = &ctx.map.tile[LayerIndex::Floor];
+    if !allowed(floor_layer.get(x, y)) {
+        return false;
+    }
+
+    // Don't spawn if there isn't enough space on the floor
+    if !floor_layer
+        .sample(x, y, grid::SampleShape::Circle(flora.floor_spacing))
+        .into_iter()
+        .all(allowed)
+    {
+        return false;
+    }
+
+    // Don't spawn if there are terrain walls nearby. Spacing between floras is handled by
+    // `min_distance` and `species_distance` instead.
+    grid::SampleShape::Circle(flora.wall_spacing)
+        .range(U16Vec2::new(x, y))
+        .into_iter()
+        .all(|p| walls[grid::to_index(p.x, p.y)].is_none())
+}
+
+/// Minimum distance required between two floras.
+fn required_distance(a: &Flora, b: &Flora, same_kind: bool) -> f32 {
+    if same_kind {
+        a.min_distance.max(b.min_distance)
+    } else {
+        a.species_distance.max(b.species_distance)
+    }
+}
+
+/// Places flora on the wall layer using Poisson-disk samples. On each sample, the flora is picked
+/// by weight among the ones which can grow there and are far enough from already placed flora.
+/// Clustering makes a flora more likely to be picked nearby others of the same kind.
+pub(crate) fn place_flora(biome: &Biome, map: &mut Map, rng: &mut Rng) {
+    let floras = &biome.flora_registry;
+    if floras.is_empty() {
+        return;
+    }
+
+    let sample_radius = floras
+        .iter()
+        .map(|f| f.min_distance.min(f.species_distance))
+        .fold(f32::MAX, f32::min)
+        .max(MIN_SAMPLE_RADIUS);
+
+    let max_distance = floras
+        .iter()
+        .map(|f| f.min_distance.max(f.species_distance))
+        .fold(sample_radius, f32::max);
+
+    // Cluster reach is twice the distance needed between floras of same kind.
+    let cluster_reach = max_distance * 2.0;
+
+    // Snapshot terrain walls, so wall spacing doesn't depend on the placement order.
+    let walls = map.tile[LayerIndex::Wall].to_vec();
+
+    let distances = rule::distance_fields(floras.iter().map(|f| &f.rule), map);
+
+    let mut index = SpatialIndex::new(cluster_reach);
+
+    for sample in poisson_disk_samples(sample_radius, rng) {
+        let tile = sample.as_u16vec2();
+        let (x, y) = (tile.x, tile.y);
+
+        if !walls[grid::to_index(x, y)].is_none()
+            || map.structures.iter().any(|s| s.contains(tile.as_uvec2()))
+        {
+            continue;
+        }
+
+        let ctx = RuleContext {
+            map,
+            noise: &biome.flora_noise,
+            distances: &distances,
+        };
+
+        let candidates = floras
+            .iter()
+            .enumerate()
+            .filter(|(_, flora)| can_grow(flora, x, y, &ctx, &walls))
+            .filter(|&(idx, flora)| {
+                index.nearby(sample, max_distance).all(|placed| {
+                    let other = &floras[placed.flora];
+                    let same_kind = placed.flora == idx;
+                    placed.pos.distance(sample) >= required_distance(flora, other, same_kind)
+                })
+            })
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+
+        if candidates.is_empty() {
+            continue;
+        }
+
+        // Give nearby floras a chance to spread their own kind.
+        let clustered = index
+            .nearby(sample, cluster_reach)
+            .filter(|placed| candidates.contains(&placed.flora))
+            .map(|placed| placed.flora)
+            .find(|&idx| rng.chance(floras[idx].clustering));
+
+        let picked = clustered.or_else(|| {
+            rng.pick_weighted(&candidates, |&idx| floras[idx].weight)
+                .copied()
+        });
+
+        let Some(picked) = picked else {
+            continue;
+        };
+
+        index.insert(Placed {
+            pos: sample,
+            flora: picked,
+        });
+
+        map.tile[LayerIndex::Wall].set(x, y, floras[picked].tile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poisson_disk_respects_radius() {
+        // Arrange
+        let radius = 4.0;
+        let mut rng = Rng::new(42);
+
+        // Act
+        let samples = poisson_disk_samples(radius, &mut rng);
+
+        // Assert
+        assert!(!samples.is_empty());
+        let index = samples
+            .iter()
+            .fold(SpatialIndex::new(radius), |mut idx, &pos| {
+                idx.insert(Placed { pos, flora: 0 });
+                idx
+            });
+        for &sample in &samples {
+            // Only the sample itself should be inside its own radius
+            assert_eq!(index.nearby(sample, radius).count(), 1);
+        }
+    }
+
+    #[test]
+    fn poisson_disk_is_deterministic() {
+        // Act
+        let a = poisson_disk_samples(3.0, &mut Rng::new(1));
+        let b = poisson_disk_samples(3.0, &mut Rng::new(1));
+        let c = poisson_disk_samples(3.0, &mut Rng::new(2));
+
+        // Assert
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
@@ -0,0 +1,143 @@
+use eternal_grid::{grid, tile::TileId};
+
+use crate::{
+    generator::{GeneratorContext, MapGenerator, MapGeneratorError},
+    map::Map,
+    rng::Rng,
+    validation,
+};
+
+/// Generates caves using cellular automata. The map starts with random walls, which are then
+/// smoothed a few times. Only the largest open area is kept, so the whole cave is reachable.
+#[derive(Debug, Clone)]
+pub struct CaveGenerator {
+    pub name: String,
+    pub floor: TileId,
+    pub wall: TileId,
+    pub fill: f32,
+    pub iterations: u32,
+    pub birth: u8,
+    pub survival: u8,
+}
+
+fn is_border(x: i32, y: i32) -> bool {
+    x <= 0 || y <= 0 || x >= grid::DIMS.x as i32 - 1 || y >= grid::DIMS.y as i32 - 1
+}
+
+fn wall_neighbors(walls: &[bool], x: i32, y: i32) -> u8 {
+    let mut count = 0;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+
+            let (nx, ny) = (x + dx, y + dy);
+            // Outside the map counts as wall, so caves don't open to the edges.
+            if is_border(nx, ny) || walls[grid::to_index(nx as u16, ny as u16)] {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+impl CaveGenerator {
+    fn step(&self, walls: &[bool]) -> Vec<bool> {
+        (0..grid::LAYER_SIZE)
+            .map(|idx| {
+                let x = (idx % grid::DIMS.x as usize) as i32;
+                let y = (idx / grid::DIMS.x as usize) as i32;
+                if is_border(x, y) {
+                    return true;
+                }
+
+                let neighbors = wall_neighbors(walls, x, y);
+                if walls[idx] {
+                    neighbors >= self.survival
+                } else {
+                    neighbors >= self.birth
+                }
+            })
+            .collect()
+    }
+}
+
+/// Fills every open area except the largest one.
+fn keep_largest_area(walls: &mut [bool]) {
+    let open = walls.iter().map(|wall| !wall).collect::<Vec<_>>();
+
+    walls.fill(true);
+    for idx in validation::largest_region(&open) {
+        walls[idx] = false;
+    }
+}
+
+impl MapGenerator for CaveGenerator {
+    fn generate(&self, ctx: &GeneratorContext) -> Result<Map, MapGeneratorError> {
+        let mut rng = Rng::from_stream(ctx.seed, "cave");
+
+        let mut walls = (0..grid::LAYER_SIZE)
+            .map(|idx| {
+                let x = (idx % grid::DIMS.x as usize) as i32;
+                let y = (idx / grid::DIMS.x as usize) as i32;
+                is_border(x, y) || rng.chance(self.fill)
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..self.iterations {
+            walls = self.step(&walls);
+        }
+
+        keep_largest_area(&mut walls);
+
+        let mut map = Map::new(self.name.clone(), ctx.seed);
+        super::write_layout(&mut map, &walls, self.floor, self.wall);
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eternal_grid::grid::LayerIndex;
+
+    use super::*;
+    use crate::{biome::BiomeRegistry, prefab::PrefabRegistry};
+
+    #[test]
+    fn cave_is_connected() {
+        // Arrange
+        let generator = CaveGenerator {
+            name: "caves".to_string(),
+            floor: TileId::new(0),
+            wall: TileId::new(1),
+            fill: 0.45,
+            iterations: 5,
+            birth: 5,
+            survival: 4,
+        };
+        let ctx = GeneratorContext {
+            biomes: &BiomeRegistry::default(),
+            prefabs: &PrefabRegistry::default(),
+            region: None,
+            seed: 7,
+            spawn_clearance: 1,
+        };
+
+        // Act
+        let map = generator.generate(&ctx).unwrap();
+
+        // Assert
+        let mut walls = map.tile[LayerIndex::Wall]
+            .iter()
+            .map(|t| !t.is_none())
+            .collect::<Vec<_>>();
+        let open = walls.iter().filter(|w| !**w).count();
+        assert!(open > grid::LAYER_SIZE / 4);
+
+        // Keeping the largest area again must not change anything
+        keep_largest_area(&mut walls);
+        assert_eq!(walls.iter().filter(|w| !**w).count(), open);
+    }
+}
@@ -0,0 +1,202 @@
+use bevy::prelude::*;
+use eternal_grid::{grid, tile::TileId};
+
+use crate::{
+    generator::{GeneratorContext, MapGenerator, MapGeneratorError},
+    map::{Map, Structure},
+    rng::Rng,
+};
+
+/// Generates rooms connected by corridors. The map is recursively split in two parts, a room is
+/// placed on each leaf and sibling parts are connected by a corridor, so every room is
+/// reachable. Rooms are recorded as `room` structures on the map.
+#[derive(Debug, Clone)]
+pub struct DungeonGenerator {
+    pub name: String,
+    pub floor: TileId,
+    pub wall: TileId,
+    pub min_room: u16,
+    pub max_room: u16,
+    pub depth: u32,
+    pub corridor_width: u8,
+}
+
+#[derive(Default)]
+struct Layout {
+    rooms: Vec<URect>,
+    corridors: Vec<(UVec2, UVec2)>,
+}
+
+impl DungeonGenerator {
+    /// Smallest area which can hold a room, with a wall on each side.
+    fn min_area(&self) -> u32 {
+        self.min_room as u32 + 2
+    }
+
+    /// Splits the area, returning the center of one of the rooms inside it, if any.
+    fn split(&self, area: URect, depth: u32, rng: &mut Rng, layout: &mut Layout) -> Option<UVec2> {
+        let size = area.size();
+        let min_area = self.min_area();
+        let can_split = size.cmpge(UVec2::splat(min_area * 2));
+
+        if depth == 0 || !can_split.any() {
+            return self.place_room(area, rng, layout);
+        }
+
+        // Prefer to split along the longest axis, so areas don't get too thin.
+        let vertical = match (can_split.x, can_split.y) {
+            (true, false) => true,
+            (false, true) => false,
+            _ if size.x as f32 > size.y as f32 * 1.25 => true,
+            _ if size.y as f32 > size.x as f32 * 1.25 => false,
+            _ => rng.chance(0.5),
+        };
+
+        let (a, b) = if vertical {
+            let at = area.min.x + rng.range_u32(min_area, size.x - min_area + 1);
+            (
+                URect::new(area.min.x, area.min.y, at, area.max.y),
+                URect::new(at, area.min.y, area.max.x, area.max.y),
+            )
+        } else {
+            let at = area.min.y + rng.range_u32(min_area, size.y - min_area + 1);
+            (
+                URect::new(area.min.x, area.min.y, area.max.x, at),
+                URect::new(area.min.x, at, area.max.x, area.max.y),
+            )
+        };
+
+        let a = self.split(a, depth - 1, rng, layout);
+        let b = self.split(b, depth - 1, rng, layout);
+
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                layout.corridors.push((a, b));
+                Some(if rng.chance(0.5) { a } else { b })
+            }
+            (room, None) | (None, room) => room,
+        }
+    }
+
+    fn place_room(&self, area: URect, rng: &mut Rng, layout: &mut Layout) -> Option<UVec2> {
+        let available = area.size().saturating_sub(UVec2::splat(2));
+        let max = available.min(UVec2::splat(self.max_room as u32));
+        let min = self.min_room as u32;
+        if max.cmplt(UVec2::splat(min)).any() {
+            return None;
+        }
+
+        let size = UVec2::new(rng.range_u32(min, max.x + 1), rng.range_u32(min, max.y + 1));
+        let slack = available - size;
+        let origin = area.min
+            + UVec2::ONE
+            + UVec2::new(rng.range_u32(0, slack.x + 1), rng.range_u32(0, slack.y + 1));
+
+        let room = URect::from_corners(origin, origin + size);
+        layout.rooms.push(room);
+
+        Some(room.center())
+    }
+
+    fn carve_corridor(&self, from: UVec2, to: UVec2, walls: &mut [bool]) {
+        let width = self.corridor_width.max(1) as u32;
+        let mut carve = |x: u32, y: u32| {
+            for dy in 0..width {
+                for dx in 0..width {
+                    let (x, y) = (x + dx, y + dy);
+                    if x < grid::DIMS.x - 1 && y < grid::DIMS.y - 1 {
+                        walls[grid::to_index(x as u16, y as u16)] = false;
+                    }
+                }
+            }
+        };
+
+        // L-shaped corridor, horizontal first then vertical.
+        for x in from.x.min(to.x)..=from.x.max(to.x) {
+            carve(x, from.y);
+        }
+        for y in from.y.min(to.y)..=from.y.max(to.y) {
+            carve(to.x, y);
+        }
+    }
+}
+
+impl MapGenerator for DungeonGenerator {
+    fn generate(&self, ctx: &GeneratorContext) -> Result<Map, MapGeneratorError> {
+        let mut rng = Rng::from_stream(ctx.seed, "dungeon");
+
+        let mut layout = Layout::default();
+        let area = URect::from_corners(UVec2::ONE, grid::DIMS - UVec2::ONE);
+        self.split(area, self.depth, &mut rng, &mut layout);
+
+        let mut walls = vec![true; grid::LAYER_SIZE];
+        for room in &layout.rooms {
+            for y in room.min.y..room.max.y {
+                for x in room.min.x..room.max.x {
+                    walls[grid::to_index(x as u16, y as u16)] = false;
+                }
+            }
+        }
+
+        for &(from, to) in &layout.corridors {
+            self.carve_corridor(from, to, &mut walls);
+        }
+
+        let mut map = Map::new(self.name.clone(), ctx.seed);
+        super::write_layout(&mut map, &walls, self.floor, self.wall);
+
+        map.structures = layout
+            .rooms
+            .into_iter()
+            .map(|bounds| Structure {
+                name: "room".to_string(),
+                prefab: String::new(),
+                bounds,
+                spawn: None,
+                entrance: None,
+            })
+            .collect();
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{biome::BiomeRegistry, prefab::PrefabRegistry};
+
+    #[test]
+    fn dungeon_rooms_do_not_overlap() {
+        // Arrange
+        let generator = DungeonGenerator {
+            name: "dungeon".to_string(),
+            floor: TileId::new(0),
+            wall: TileId::new(1),
+            min_room: 6,
+            max_room: 20,
+            depth: 5,
+            corridor_width: 2,
+        };
+        let ctx = GeneratorContext {
+            biomes: &BiomeRegistry::default(),
+            prefabs: &PrefabRegistry::default(),
+            region: None,
+            seed: 7,
+            spawn_clearance: 1,
+        };
+
+        // Act
+        let map = generator.generate(&ctx).unwrap();
+
+        // Assert
+        assert!(map.structures.len() > 8);
+        for (i, a) in map.structures.iter().enumerate() {
+            let size = a.bounds.size();
+            assert!(size.cmpge(UVec2::splat(6)).all() && size.cmple(UVec2::splat(20)).all());
+            for b in map.structures.iter().skip(i + 1) {
+                assert!(a.bounds.intersect(b.bounds).is_empty());
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use bevy::prelude::*;
+use eternal_config::{
+    generator::{MapGeneratorConfig, MapGeneratorRegistryConfig},
+    server::{ConfigAssetUpdated, ConfigServer, Configs},
+};
+use eternal_grid::{
+    ecs::TileRegistry,
+    grid::{self, LayerIndex},
+    tile::{TileElevation, TileId},
+};
+
+use crate::{
+    atlas::AtlasRegion,
+    biome::BiomeRegistry,
+    map::Map,
+    prefab::PrefabRegistry,
+    rng,
+    validation::{self, MapReport, MapValidation},
+};
+
+mod cave;
+mod dungeon;
+
+pub use cave::CaveGenerator;
+pub use dungeon::DungeonGenerator;
+
+pub(crate) struct MapGeneratorPlugin;
+
+impl Plugin for MapGeneratorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapGeneratorRegistry>()
+            .add_systems(Startup, setup);
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MapGeneratorError {
+    #[error("Map generator {0} not found")]
+    NotFound(String),
+    #[error("Biome {0} not found")]
+    BiomeNotFound(String),
+    #[error("Tile {1} of map generator {0} not found")]
+    UnknownTile(String, String),
+}
+
+/// Everything a [`MapGenerator`] may need to generate a map.
+#[derive(Clone, Copy)]
+pub struct GeneratorContext<'a> {
+    pub biomes: &'a BiomeRegistry,
+    pub prefabs: &'a PrefabRegistry,
+    /// The atlas area covered by the map, if the map is part of the overworld.
+    pub region: Option<AtlasRegion<'a>>,
+    pub seed: u64,
+    /// Free tiles required around the player spawn, usually the one of the generator validation.
+    pub spawn_clearance: u8,
+}
+
+/// Generates a whole [`Map`], filling Floor and Wall layers and elevation.
+pub trait MapGenerator: std::fmt::Debug + Send + Sync {
+    fn generate(&self, ctx: &GeneratorContext) -> Result<Map, MapGeneratorError>;
+}
+
+/// Generates overworld maps, using the terrain noise, rivers, structures and flora of a biome.
+#[derive(Debug, Clone)]
+pub struct BiomeGenerator {
+    pub biome: String,
+}
+
+impl MapGenerator for BiomeGenerator {
+    fn generate(&self, ctx: &GeneratorContext) -> Result<Map, MapGeneratorError> {
+        let biome = ctx
+            .biomes
+            .get_biome(&self.biome)
+            .ok_or_else(|| MapGeneratorError::BiomeNotFound(self.biome.clone()))?;
+
+        Ok(crate::generate_map(
+            biome,
+            ctx.prefabs,
+            ctx.region,
+            ctx.seed,
+            ctx.spawn_clearance,
+        ))
+    }
+}
+
+fn from_config(
+    name: &str,
+    config: &MapGeneratorConfig,
+    tile_registry: &TileRegistry,
+) -> Result<Box<dyn MapGenerator>, MapGeneratorError> {
+    let tile = |tile: &str| {
+        tile_registry
+            .find_id_by_name(tile)
+            .ok_or_else(|| MapGeneratorError::UnknownTile(name.to_string(), tile.to_string()))
+    };
+
+    Ok(match config {
+        MapGeneratorConfig::Biome(biome) => Box::new(BiomeGenerator {
+            biome: biome.clone(),
+        }),
+        MapGeneratorConfig::Cave {
+            floor,
+            wall,
+            fill,
+            iterations,
+            birth,
+            survival,
+        } => Box::new(CaveGenerator {
+            name: name.to_string(),
+            floor: tile(floor)?,
+            wall: tile(wall)?,
+            fill: *fill,
+            iterations: *iterations,
+            birth: *birth,
+            survival: *survival,
+        }),
+        MapGeneratorConfig::Dungeon {
+            floor,
+            wall,
+            min_room,
+            max_room,
+            depth,
+            corridor_width,
+        } => Box::new(DungeonGenerator {
+            name: name.to_string(),
+            floor: tile(floor)?,
+            wall: tile(wall)?,
+            min_room: *min_room,
+            max_room: *max_room,
+            depth: *depth,
+            corridor_width: *corridor_width,
+        }),
+    })
+}
+
+/// Named map generators, loaded from config.
+#[derive(Default, Debug, Resource)]
+pub struct MapGeneratorRegistry(Vec<(String, Box<dyn MapGenerator>)>);
+
+impl MapGeneratorRegistry {
+    pub fn from_config(
+        config: &MapGeneratorRegistryConfig,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, MapGeneratorError> {
+        Ok(Self(
+            config
+                .iter()
+                .map(|(name, generator)| {
+                    Ok((name.clone(), from_config(name, generator, tile_registry)?))
+                })
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn MapGenerator> {
+        self.0
+            .iter()
+            .find_map(|(n, generator)| (n == name).then_some(generator.as_ref()))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Generates a map using the generator with the given name.
+    pub fn generate(&self, name: &str, ctx: &GeneratorContext) -> Result<Map, MapGeneratorError> {
+        self.get(name)
+            .ok_or_else(|| MapGeneratorError::NotFound(name.to_string()))?
+            .generate(ctx)
+    }
+
+    /// Generates a map and checks it against the validation invariants. Invalid maps are
+    /// generated again using a seed derived from the context one, until one is valid or the max
+    /// attempts is reached, in which case the last one is returned. The map spawn is selected
+    /// when missing, see [`crate::spawn::select_spawn`], using the clearance of the validation.
+    ///
+    /// Only the map seed changes between attempts. Features shared with neighbor maps, like
+    /// rivers and road exits, are seeded by the atlas, so retried maps still match their
+    /// neighbors.
+    pub fn generate_validated(
+        &self,
+        name: &str,
+        ctx: &GeneratorContext,
+        validation: &MapValidation,
+    ) -> Result<(Map, MapReport), MapGeneratorError> {
+        let generator = self
+            .get(name)
+            .ok_or_else(|| MapGeneratorError::NotFound(name.to_string()))?;
+        let validation = validation.get(name);
+
+        let max_attempts = validation.max_attempts.max(1);
+        let mut attempt: u32 = 1;
+        loop {
+            let seed = if attempt == 1 {
+                ctx.seed
+            } else {
+                rng::hash(ctx.seed, &attempt.to_le_bytes())
+            };
+
+            let mut map = generator.generate(&GeneratorContext {
+                seed,
+                spawn_clearance: validation.spawn_clearance,
+                ..*ctx
+            })?;
+            let biome = ctx.biomes.get_biome(&map.biome);
+            let mut report = validation::analyze(&map, biome, validation);
+            report.attempts = attempt;
+            map.spawn = report.spawn;
+
+            if report.is_valid() || attempt >= max_attempts {
+                return Ok((map, report));
+            }
+
+            debug!(
+                "Map {name} of seed {seed} is invalid, retrying: {:?}",
+                report.issues
+            );
+            attempt += 1;
+        }
+    }
+}
+
+/// Writes a wall layout into the map. Floor is placed everywhere, so removing walls later
+/// reveals floor. Walls are one unit higher than floors.
+fn write_layout(map: &mut Map, walls: &[bool], floor: TileId, wall: TileId) {
+    for (idx, &is_wall) in walls.iter().enumerate() {
+        let x = (idx % grid::DIMS.x as usize) as u16;
+        let y = (idx / grid::DIMS.x as usize) as u16;
+
+        map.tile[LayerIndex::Floor].set(x, y, floor);
+        if is_wall {
+            map.tile[LayerIndex::Wall].set(x, y, wall);
+            map.elevation.set(x, y, TileElevation::new(1.0));
+        } else {
+            map.elevation.set(x, y, TileElevation::new(0.0));
+        }
+    }
+}
+
+fn setup(mut config_server: ConfigServer) {
+    config_server
+        .load::<MapGeneratorRegistryConfig>("config/procgen/generators.ron")
+        .observe(on_generator_config_updated);
+}
+
+fn on_generator_config_updated(
+    updated: On<ConfigAssetUpdated>,
+    configs: Configs<MapGeneratorRegistryConfig>,
+    tile_registry: Res<TileRegistry>,
+    mut commands: Commands,
+) {
+    let Some(config) = configs.get(updated.id()) else {
+        error!("Map generator registry config not found.");
+        return;
+    };
+
+    debug!("Updating map generator registry!");
+
+    match MapGeneratorRegistry::from_config(config, &tile_registry) {
+        Ok(registry) => commands.insert_resource(registry),
+        Err(err) => error!("Failed to build map generator registry. {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generator_unknown_tile() {
+        // Arrange
+        let config = MapGeneratorRegistryConfig(vec![(
+            "caves".to_string(),
+            MapGeneratorConfig::Cave {
+                floor: "NONE".to_string(),
+                wall: "STONE_WAL".to_string(),
+                fill: 0.45,
+                iterations: 5,
+                birth: 5,
+                survival: 4,
+            },
+        )]);
+
+        // Act
+        let result = MapGeneratorRegistry::from_config(&config, &TileRegistry::default());
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(MapGeneratorError::UnknownTile(generator, tile))
+                if generator == "caves" && tile == "STONE_WAL"
+        ));
+    }
+}
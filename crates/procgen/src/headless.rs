@@ -0,0 +1,63 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use bevy::{app::PluginsState, prelude::*};
+use eternal_config::{ConfigPlugin, server::ConfigProgress};
+use eternal_grid::ecs::GridPlugin;
+
+use crate::{ProcGenPlugin, WorldSeed, atlas::Atlas, biome::BiomeRegistry};
+
+/// Builds an app which loads procgen configs from the given asset folder, without opening a
+/// window. More plugins can be added before the first [`generate_atlas`] call.
+pub fn app(assets: &Path) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: assets.to_string_lossy().into_owned(),
+            ..default()
+        },
+        // Tile configs references their textures.
+        ImagePlugin::default(),
+        ConfigPlugin,
+        GridPlugin,
+        ProcGenPlugin,
+    ));
+    app
+}
+
+/// Whether every config needed to generate maps is loaded and the atlas is generated.
+pub fn is_ready(world: &World) -> bool {
+    world.resource::<ConfigProgress>().is_ready()
+        && world.resource::<BiomeRegistry>().is_ready()
+        && world.contains_resource::<Atlas>()
+}
+
+/// Generates the atlas of the given seed, updating the app until it is ready. Returns `false` if
+/// it took longer than `timeout`.
+pub fn generate_atlas(app: &mut App, seed: u64, timeout: Duration) -> bool {
+    if app.plugins_state() == PluginsState::Ready {
+        app.finish();
+        app.cleanup();
+    }
+
+    app.world_mut().remove_resource::<Atlas>();
+    app.insert_resource(WorldSeed(seed));
+
+    let start = Instant::now();
+    loop {
+        app.update();
+
+        if is_ready(app.world()) {
+            return true;
+        }
+
+        if start.elapsed() > timeout {
+            return false;
+        }
+
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
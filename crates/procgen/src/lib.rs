@@ -1,33 +1,74 @@
 use bevy::prelude::*;
+use eternal_config::erosion::ErosionConfig;
 use eternal_grid::{
     grid::{self, LayerIndex},
     tile::TileElevation,
 };
 
 use crate::{
-    atlas::{Atlas, AtlasPlugin},
-    biome::{Biome, BiomePlugin},
+    atlas::{Atlas, AtlasPlugin, AtlasRegion},
+    biome::{Biome, BiomePlugin, PalletChannel},
+    generator::MapGeneratorPlugin,
     map::Map,
     noise::NoiseStack,
+    prefab::{PrefabPlugin, PrefabRegistry},
+    rng::Rng,
+    validation::ValidationPlugin,
+    wfc::AdjacencyRepair,
 };
 
 pub mod atlas;
 pub mod biome;
+pub mod erosion;
+mod flora;
+pub mod generator;
+pub mod headless;
+pub mod library;
 pub mod map;
 pub mod noise;
+pub mod prefab;
+pub mod preview;
+mod resource;
+mod river;
+pub mod rng;
+pub mod road;
+pub mod rule;
+pub mod spawn;
+mod structure;
+pub mod validation;
+pub mod wfc;
 
 pub struct ProcGenPlugin;
 
 impl Plugin for ProcGenPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((BiomePlugin, AtlasPlugin));
+        app.init_resource::<WorldSeed>().add_plugins((
+            BiomePlugin,
+            AtlasPlugin,
+            PrefabPlugin,
+            MapGeneratorPlugin,
+            ValidationPlugin,
+        ));
     }
 }
 
-pub fn generate_atlas(noise_stack: &NoiseStack) -> Atlas {
+/// The seed used to generate the world. Every random decision made by procgen derives from it.
+#[derive(Debug, Clone, Copy, Resource, Reflect, Deref)]
+pub struct WorldSeed(pub u64);
+
+impl Default for WorldSeed {
+    fn default() -> Self {
+        Self(42)
+    }
+}
+
+pub fn generate_atlas(noise_stack: &NoiseStack, erosion: &ErosionConfig, seed: u64) -> Atlas {
     debug!("Generating atlas!");
 
-    let mut atlas = Atlas::new();
+    let mut atlas = Atlas {
+        seed,
+        ..Atlas::new()
+    };
 
     for y in 0..atlas::ATLAS_AXIS_SIZE as u16 {
         for x in 0..atlas::ATLAS_AXIS_SIZE as u16 {
@@ -35,89 +76,255 @@ pub fn generate_atlas(noise_stack: &NoiseStack) -> Atlas {
         }
     }
 
+    erosion::erode(&mut atlas, erosion, &mut Rng::from_stream(seed, "erosion"));
+
     debug!("Atlas generated!");
 
     atlas
 }
 
-pub fn generate_map(biome: &Biome) -> Map {
+/// Generates a single map. When an atlas region is given, atlas rivers crossing the map are
+/// projected into it. The player spawn is selected before roads are placed, so roads lead to it.
+pub fn generate_map(
+    biome: &Biome,
+    prefabs: &PrefabRegistry,
+    region: Option<AtlasRegion>,
+    seed: u64,
+    spawn_clearance: u8,
+) -> Map {
     debug!("Generating map!");
 
-    let mut map = Map::new(biome.name.clone());
+    let mut map = Map::new(biome.name.clone(), seed);
+    generate_terrain(biome, &mut map, &mut Rng::from_stream(seed, "terrain"));
+
+    let repair = biome.terrain_pallet.repair.as_ref();
+    if let Some(repair) = repair {
+        let unlocked = [false; grid::LAYER_SIZE];
+        repair_adjacency(&mut map, repair, &unlocked, "wfc");
+    }
+    let terrain = map.tile[LayerIndex::Floor].to_vec();
+
+    if let Some(region) = region {
+        river::project_rivers(
+            &region,
+            biome.terrain_pallet.river,
+            biome.terrain_pallet.river_bank,
+            &mut map,
+        );
+    }
+
+    structure::place_structures(
+        biome,
+        prefabs,
+        &mut map,
+        &mut Rng::from_stream(seed, "structures"),
+    );
+
+    resource::place_resources(biome, &mut map, &mut Rng::from_stream(seed, "resources"));
+
+    flora::place_flora(biome, &mut map, &mut Rng::from_stream(seed, "flora"));
+
+    map.spawn = spawn::select_spawn(
+        &map,
+        &biome.terrain_pallet.spawn,
+        &biome.terrain_pallet.unwalkable,
+        spawn_clearance,
+    );
+
+    if let Some(roads) = &biome.roads {
+        road::place_roads(roads, biome, region.as_ref(), &mut map);
+    }
+
+    // Rivers, structures, resources and roads are placed without following adjacency rules, so
+    // the terrain around them is repaired again, keeping the placed tiles.
+    if let Some(repair) = repair {
+        let placed = map.tile[LayerIndex::Floor]
+            .iter()
+            .zip(&terrain)
+            .map(|(tile, terrain)| tile != terrain)
+            .collect::<Vec<_>>();
+        repair_adjacency(&mut map, repair, &placed, "wfc_placed");
+    }
+
+    debug!("Map generated!");
+
+    map
+}
+
+fn repair_adjacency(map: &mut Map, repair: &AdjacencyRepair, locked: &[bool], stream: &str) {
+    let mut rng = Rng::from_stream(map.seed, stream);
+    let unresolved = wfc::repair(map, repair, locked, &mut rng);
+    if unresolved > 0 {
+        debug!("{unresolved} tiles are still next to tiles they aren't allowed to");
+    }
+}
+
+fn generate_terrain(biome: &Biome, map: &mut Map, rng: &mut Rng) {
     for y in 0..grid::DIMS.y as u16 {
         for x in 0..grid::DIMS.x as u16 {
-            generate_terrain(x, y, biome, &mut map);
+            let elevation = biome.terrain_noise.get(x as f32, y as f32);
+            map.elevation.set(x, y, TileElevation::new(elevation));
         }
     }
 
+    let pallet = &biome.terrain_pallet;
+    let noises = pallet
+        .channels()
+        .iter()
+        .map(|channel| {
+            let PalletChannel::Noise(name) = channel else {
+                return None;
+            };
+            let layer = biome.terrain_noise.layer(name);
+            if layer.is_none() {
+                warn!(
+                    "Pallet noise layer {name} not found on biome {}",
+                    biome.name
+                );
+            }
+            layer
+        })
+        .collect::<Vec<_>>();
+
+    let mut values = vec![0.0; pallet.channels().len()];
     for y in 0..grid::DIMS.y as u16 {
         for x in 0..grid::DIMS.x as u16 {
-            generate_flora(x, y, biome, &mut map);
+            for (value, (channel, noise)) in
+                values.iter_mut().zip(pallet.channels().iter().zip(&noises))
+            {
+                *value = match channel {
+                    PalletChannel::Elevation => **map.elevation.get(x, y),
+                    PalletChannel::Slope => rule::slope(x, y, map),
+                    PalletChannel::Noise(_) => noise
+                        .as_ref()
+                        .map_or(f32::NAN, |noise| noise.get(x as f32, y as f32)),
+                };
+            }
+
+            let floor = pallet.collapse(LayerIndex::Floor, &values, rng);
+            let wall = pallet.collapse(LayerIndex::Wall, &values, rng);
+            map.tile[LayerIndex::Floor].set(x, y, floor);
+            map.tile[LayerIndex::Wall].set(x, y, wall);
         }
     }
-
-    debug!("Map generated!");
-
-    map
 }
 
-fn generate_terrain(x: u16, y: u16, biome: &Biome, map: &mut Map) {
-    let elevation = biome.terrain_noise.get(x as f32, y as f32);
+#[cfg(test)]
+mod tests {
+    use bevy::math::U16Vec2;
+    use eternal_config::erosion::RiverConfig;
 
-    map.elevation.set(x, y, TileElevation::new(elevation));
+    use super::*;
+    use crate::{
+        atlas::River,
+        library::{BiomeSources, parse_biome, parse_tiles},
+    };
 
-    map.tile[LayerIndex::Floor].set(
-        x,
-        y,
-        biome.terrain_pallet.collapse(LayerIndex::Floor, elevation),
-    );
-    map.tile[LayerIndex::Wall].set(
-        x,
-        y,
-        biome.terrain_pallet.collapse(LayerIndex::Wall, elevation),
-    );
-}
+    const TILES: &str = r##"
+[
+    (
+        kind: Terrain,
+        name: "GRASS",
+        atlas: "",
+        atlas_index: 0,
+        map_color: "#33cc33",
+        outline: false,
+        blend_tech: None,
+    ),
+    (
+        kind: Terrain,
+        name: "WATER",
+        atlas: "",
+        atlas_index: 0,
+        map_color: "#3399ff",
+        outline: false,
+        blend_tech: None,
+        neighbors: ["SAND"],
+    ),
+    (
+        kind: Terrain,
+        name: "SAND",
+        atlas: "",
+        atlas_index: 0,
+        map_color: "#ffcc66",
+        outline: false,
+        blend_tech: None,
+    ),
+]
+"##;
 
-fn generate_flora(x: u16, y: u16, biome: &Biome, map: &mut Map) {
-    let probability = biome.flora_noise.get(x as f32, y as f32);
-    let elevation = **map.elevation.get(x, y);
+    const NOISE: &str = r#"[("main", Fbm(seed: 1, frequency: 0.05, octaves: 2, lacunarity: 2.0, persistence: 0.5))]"#;
 
-    let floor_layer = &map.tile[LayerIndex::Floor];
-    let wall_layer = &map.tile[LayerIndex::Wall];
+    #[test]
+    fn rivers_follow_adjacency_rules() {
+        // Arrange
+        let tiles = parse_tiles(TILES).unwrap();
+        // Rivers without banks, so river water is placed right next to grass.
+        let sources = BiomeSources {
+            terrain_noise: NOISE,
+            terrain_pallet: r#"(
+                floor: (
+                    default: "GRASS",
+                    entries: [
+                        (tile: "WATER", ranges: [(channel: "elevation", min: 10.0)]),
+                        (tile: "SAND", ranges: [(channel: "elevation", min: 10.0)]),
+                    ],
+                ),
+                wall: (default: "NONE"),
+                river: "WATER",
+                river_bank: "GRASS",
+                repair: Some((radius: 2, max_attempts: 3)),
+            )"#,
+            flora: "[]",
+            flora_noise: NOISE,
+            structures: "[]",
+            resources: "[]",
+            resource_noise: NOISE,
+            roads: r#"(tile: "SAND", exit_chance: 0.0)"#,
+        };
+        let biome = parse_biome("river", &sources, &tiles).unwrap();
 
-    let tile = floor_layer.get(x, y);
+        let map_coord = U16Vec2::splat(10);
+        let first_cell = map_coord * atlas::MAP_RESOLUTION;
+        let mut atlas = Atlas {
+            river: RiverConfig {
+                flow_threshold: 1.0,
+                min_width: 3.0,
+                max_width: 3.0,
+                bank_width: 0.0,
+                ..default()
+            },
+            ..Atlas::new()
+        };
+        atlas.flow.fill(1.0);
+        atlas.rivers.push(River {
+            path: (0..atlas::MAP_RESOLUTION + 2)
+                .map(|x| U16Vec2::new(first_cell.x - 1 + x, first_cell.y + 1))
+                .collect(),
+        });
+        let region = AtlasRegion {
+            atlas: &atlas,
+            map: map_coord,
+        };
 
-    // Check floras which can be spawned here.
-    let mut flora_candidates = biome
-        .flora_registry
-        .iter()
-        .filter(|flora| {
-            probability > flora.threshold
-                && (flora.allowed_terrains.is_empty() || flora.allowed_terrains.contains(tile))
-                && flora
-                    .elevation_range
-                    .is_none_or(|(min, max)| min > elevation && elevation < max)
-        })
-        .collect::<Vec<_>>();
+        // Act
+        let map = generate_map(&biome, &PrefabRegistry::default(), Some(region), 42, 1);
 
-    // Don't spawn if there is other flora blocking the wall space nearby
-    flora_candidates.retain(|f| {
-        wall_layer
-            .sample(x, y, grid::SampleShape::Circle(f.wall_spacing))
-            .into_iter()
-            .all(|t| t.is_none())
-    });
-
-    // Don't spawn if there isn't enough space on the floor
-    flora_candidates.retain(|f| {
-        floor_layer
-            .sample(x, y, grid::SampleShape::Circle(f.floor_spacing))
-            .into_iter()
-            .all(|t| f.allowed_terrains.is_empty() || f.allowed_terrains.contains(t))
-    });
-
-    // Set the flora to spawn it.
-    if let Some(flora) = flora_candidates.first() {
-        map.tile[LayerIndex::Wall].set(x, y, flora.tile);
+        // Assert
+        let water = tiles.get_id_by_name("WATER");
+        let repair = biome.terrain_pallet.repair.as_ref().unwrap();
+        let floor = &map.tile[LayerIndex::Floor];
+        assert!(floor.contains(&water));
+        for y in 0..grid::DIMS.y as u16 {
+            for x in 0..grid::DIMS.x as u16 {
+                let tile = *floor.get(x, y);
+                if x + 1 < grid::DIMS.x as u16 {
+                    assert!(repair.rules.allows(tile, IVec2::X, *floor.get(x + 1, y)));
+                }
+                if y + 1 < grid::DIMS.y as u16 {
+                    assert!(repair.rules.allows(tile, IVec2::Y, *floor.get(x, y + 1)));
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,339 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use eternal_config::{
+    ConfigAssetLoaderError,
+    biome::BiomeRegistryConfig,
+    include::{self, IncludeError},
+    server::{ConfigRef, FromConfig, parse_config},
+    tile::TileConfigList,
+    validation::MapValidationConfig,
+};
+use eternal_grid::ecs::TileRegistry;
+
+use crate::{
+    biome::{Biome, BiomeConfigs, BiomeError, BiomeRegistry},
+    generator::{GeneratorContext, MapGeneratorError, MapGeneratorRegistry},
+    map::Map,
+    prefab::{PrefabError, PrefabRegistry},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LibraryError {
+    #[error("Failed to read config {path}. {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse config {path}. {source}")]
+    Parse {
+        path: String,
+        source: Box<ConfigAssetLoaderError>,
+    },
+    #[error("Config {0} is on another asset source, which isn't loaded without an App")]
+    Source(String),
+    #[error("Failed to build biome {name}. {source}")]
+    Biome {
+        name: String,
+        source: Box<BiomeError>,
+    },
+    #[error("Failed to build prefabs. {0}")]
+    Prefab(#[from] PrefabError),
+    #[error("Failed to build map generators. {0}")]
+    Generator(#[from] MapGeneratorError),
+}
+
+/// RON sources of every config of a single biome.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct BiomeSources<'a> {
+    pub terrain_noise: &'a str,
+    pub terrain_pallet: &'a str,
+    pub flora: &'a str,
+    pub flora_noise: &'a str,
+    pub structures: &'a str,
+    pub resources: &'a str,
+    pub resource_noise: &'a str,
+    pub roads: &'a str,
+}
+
+/// Parses a config from RON. `path` is only used on errors.
+pub fn parse<C: FromConfig>(path: &str, ron: &str) -> Result<C, LibraryError> {
+    parse_config(ron.as_bytes()).map_err(|source| LibraryError::Parse {
+        path: path.to_string(),
+        source: Box::new(source),
+    })
+}
+
+/// Reads and parses a config file, resolving the files it includes.
+pub fn read<C: FromConfig>(path: &Path) -> Result<C, LibraryError> {
+    let path_str = path.display().to_string();
+    let ron = std::fs::read_to_string(path).map_err(|source| LibraryError::Read {
+        path: path_str.clone(),
+        source,
+    })?;
+
+    let include_error = |source: IncludeError| LibraryError::Parse {
+        path: path_str.clone(),
+        source: Box::new(source.into()),
+    };
+    let sources = include::read_sources(&path_str, &ron, |path| std::fs::read_to_string(path))
+        .map_err(include_error)?;
+    let resolved = include::resolve(&path_str, &ron, &sources).map_err(include_error)?;
+
+    parse(&path_str, &resolved.text)
+}
+
+/// Reads a config referenced by the given asset path, like a biome config of
+/// `config/procgen/biomes.ron`. Mods aren't loaded, so configs on other asset sources, like
+/// `more_ores://config/resource_noise.ron`, are rejected.
+fn read_ref<C: FromConfig>(
+    assets: &Path,
+    parent: &str,
+    config_ref: &ConfigRef<C>,
+) -> Result<C, LibraryError> {
+    let path = config_ref
+        .resolve(parent)
+        .map_err(|source| LibraryError::Parse {
+            path: parent.to_string(),
+            source: Box::new(source.into()),
+        })?;
+    if path.contains("://") {
+        return Err(LibraryError::Source(path));
+    }
+
+    read(&assets.join(path))
+}
+
+/// Parses a tile config list. Atlas textures aren't loaded.
+pub fn parse_tiles(ron: &str) -> Result<TileRegistry, LibraryError> {
+    let config = parse::<TileConfigList>("tiles", ron)?;
+    Ok(TileRegistry::from_config(&config, |_| Handle::default()))
+}
+
+/// Builds a biome from in-memory configs.
+pub fn parse_biome(
+    name: &str,
+    sources: &BiomeSources,
+    tile_registry: &TileRegistry,
+) -> Result<Biome, LibraryError> {
+    let configs = BiomeConfigs {
+        terrain_noise: parse("terrain_noise", sources.terrain_noise)?,
+        terrain_pallet: parse("terrain_pallet", sources.terrain_pallet)?,
+        flora: parse("flora", sources.flora)?,
+        flora_noise: parse("flora_noise", sources.flora_noise)?,
+        structures: parse("structures", sources.structures)?,
+        resources: parse("resources", sources.resources)?,
+        resource_noise: parse("resource_noise", sources.resource_noise)?,
+        roads: parse("roads", sources.roads)?,
+    };
+
+    build_biome(name, &configs, tile_registry)
+}
+
+/// Builds a biome from a folder with its configs, like `assets/config/procgen/forest`.
+pub fn read_biome(
+    name: &str,
+    dir: &Path,
+    tile_registry: &TileRegistry,
+) -> Result<Biome, LibraryError> {
+    let configs = BiomeConfigs {
+        terrain_noise: read(&dir.join("terrain_noise.ron"))?,
+        terrain_pallet: read(&dir.join("terrain_pallet.ron"))?,
+        flora: read(&dir.join("flora.ron"))?,
+        flora_noise: read(&dir.join("flora_noise.ron"))?,
+        structures: read(&dir.join("structures.ron"))?,
+        resources: read(&dir.join("resources.ron"))?,
+        resource_noise: read(&dir.join("resource_noise.ron"))?,
+        roads: read(&dir.join("roads.ron"))?,
+    };
+
+    build_biome(name, &configs, tile_registry)
+}
+
+fn build_biome(
+    name: &str,
+    configs: &BiomeConfigs,
+    tile_registry: &TileRegistry,
+) -> Result<Biome, LibraryError> {
+    Biome::from_configs(name, configs, tile_registry).map_err(|source| LibraryError::Biome {
+        name: name.to_string(),
+        source: Box::new(source),
+    })
+}
+
+/// Everything needed to generate maps, loaded without an `App`. Procgen plugins build the same
+/// registries from config assets.
+#[derive(Debug)]
+pub struct Library {
+    pub tiles: TileRegistry,
+    pub biomes: BiomeRegistry,
+    pub prefabs: PrefabRegistry,
+    pub generators: MapGeneratorRegistry,
+}
+
+impl Library {
+    /// Loads every procgen config from the given asset folder.
+    pub fn load(assets: &Path) -> Result<Self, LibraryError> {
+        let tiles = TileRegistry::from_config(&read(&assets.join("config/tiles.ron"))?, |_| {
+            Handle::default()
+        });
+        let prefabs =
+            PrefabRegistry::from_config(&read(&assets.join("config/prefabs.ron"))?, &tiles)?;
+        let generators = MapGeneratorRegistry::from_config(
+            &read(&assets.join("config/procgen/generators.ron"))?,
+            &tiles,
+        )?;
+
+        const BIOMES: &str = "config/procgen/biomes.ron";
+        let biome_configs = read::<BiomeRegistryConfig>(&assets.join(BIOMES))?;
+        let biomes = biome_configs
+            .iter()
+            .map(|config| {
+                let configs = BiomeConfigs {
+                    terrain_noise: read_ref(assets, BIOMES, &config.terrain_noise)?,
+                    terrain_pallet: read_ref(assets, BIOMES, &config.terrain_pallet)?,
+                    flora: read_ref(assets, BIOMES, &config.flora)?,
+                    flora_noise: read_ref(assets, BIOMES, &config.flora_noise)?,
+                    structures: read_ref(assets, BIOMES, &config.structures)?,
+                    resources: read_ref(assets, BIOMES, &config.resources)?,
+                    resource_noise: read_ref(assets, BIOMES, &config.resource_noise)?,
+                    roads: read_ref(assets, BIOMES, &config.roads)?,
+                };
+                build_biome(&config.name, &configs, &tiles)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            tiles,
+            biomes: BiomeRegistry::new(biomes),
+            prefabs,
+            generators,
+        })
+    }
+
+    /// Context of maps which aren't part of an atlas.
+    pub fn context(&self, seed: u64) -> GeneratorContext<'_> {
+        GeneratorContext {
+            biomes: &self.biomes,
+            prefabs: &self.prefabs,
+            region: None,
+            seed,
+            spawn_clearance: MapValidationConfig::default().spawn_clearance,
+        }
+    }
+
+    /// Generates a map using the generator with the given name.
+    pub fn generate(&self, generator: &str, seed: u64) -> Result<Map, MapGeneratorError> {
+        self.generators.generate(generator, &self.context(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eternal_grid::grid::LayerIndex;
+
+    use super::*;
+
+    const TILES: &str = r##"
+[
+    (
+        kind: Terrain,
+        name: "GRASS",
+        atlas: "",
+        atlas_index: 0,
+        map_color: "#33cc33",
+        outline: false,
+        blend_tech: None,
+    ),
+    (
+        kind: Wall,
+        name: "TREE",
+        atlas: "",
+        atlas_index: 0,
+        map_color: "#116611",
+        outline: false,
+        blend_tech: None,
+    ),
+]
+    "##;
+
+    const NOISE: &str = r#"[("main", Fbm(seed: 1, frequency: 0.05, octaves: 2, lacunarity: 2.0, persistence: 0.5))]"#;
+
+    #[test]
+    fn generate_in_memory_biome() {
+        // Arrange
+        let tiles = parse_tiles(TILES).unwrap();
+        let sources = BiomeSources {
+            terrain_noise: NOISE,
+            terrain_pallet: r#"(
+                floor: (default: "GRASS"),
+                wall: (default: "NONE"),
+                river: "GRASS",
+                river_bank: "GRASS",
+            )"#,
+            flora: r#"[(
+                name: "TREE",
+                flora: "TREE",
+                threshold: 0.0,
+                wall_spacing: 1,
+                floor_spacing: 1,
+                elevation_range: None,
+                allowed_terrains: ["GRASS"],
+            )]"#,
+            flora_noise: NOISE,
+            structures: "[]",
+            resources: "[]",
+            resource_noise: NOISE,
+            roads: r#"(tile: "GRASS")"#,
+        };
+
+        // Act
+        let biome = parse_biome("meadow", &sources, &tiles).unwrap();
+        let map = crate::generate_map(&biome, &PrefabRegistry::default(), None, 42, 1);
+
+        // Assert
+        let grass = tiles.get_id_by_name("GRASS");
+        let tree = tiles.get_id_by_name("TREE");
+        assert_eq!(map.biome, "meadow");
+        assert!(
+            map.tile[LayerIndex::Floor]
+                .iter()
+                .all(|&tile| tile == grass)
+        );
+        assert!(map.tile[LayerIndex::Wall].contains(&tree));
+    }
+
+    #[test]
+    fn reject_refs_to_other_sources() {
+        // Arrange
+        let config_ref = ConfigRef::<TileConfigList>::new("more_ores://config/tiles.ron");
+
+        // Act
+        let result = read_ref(
+            Path::new("assets"),
+            "config/procgen/biomes.ron",
+            &config_ref,
+        );
+
+        // Assert
+        assert!(
+            matches!(result, Err(LibraryError::Source(path)) if path == "more_ores://config/tiles.ron")
+        );
+    }
+
+    #[test]
+    fn parse_error_has_path() {
+        // Arrange
+        let tiles = parse_tiles(TILES).unwrap();
+        let sources = BiomeSources {
+            terrain_noise: r#"[("main", Fbm(seed: 1"#,
+            ..default()
+        };
+
+        // Act
+        let result = parse_biome("broken", &sources, &tiles);
+
+        // Assert
+        assert!(matches!(result, Err(LibraryError::Parse { path, .. }) if path == "terrain_noise"));
+    }
+}
@@ -1,19 +1,332 @@
-use bevy::ecs::resource::Resource;
-use eternal_grid::grid::{Grid, GridElevation, GridId};
+use bevy::{
+    ecs::resource::Resource,
+    math::{U16Vec2, URect},
+    platform::collections::HashMap,
+    prelude::UVec2,
+};
+use eternal_grid::{
+    ecs::TileRegistry,
+    grid::{Grid, GridElevation, GridId, LAYER_SIZE, LAYERS, LAYERS_COUNT},
+    tile::{self, TileElevation, TileId},
+};
+use serde::{Deserialize, Serialize};
+
+/// A notable area of the map, like a prefab stamped by procgen or a dungeon room.
+#[derive(Default, Debug, Clone)]
+pub struct Structure {
+    /// Structure type, like "ruins", "camp" or "room".
+    pub name: String,
+    /// Prefab stamped on this structure. Empty when the structure isn't a prefab.
+    pub prefab: String,
+    /// Tiles covered by the structure. `max` is exclusive.
+    pub bounds: URect,
+    /// Player spawn marker of the stamped prefab, in map coordinates.
+    pub spawn: Option<U16Vec2>,
+    /// Road entrance marker of the stamped prefab, in map coordinates.
+    pub entrance: Option<U16Vec2>,
+}
+
+impl Structure {
+    pub fn contains(&self, pos: UVec2) -> bool {
+        pos.cmpge(self.bounds.min).all() && pos.cmplt(self.bounds.max).all()
+    }
+}
+
+/// A resource vein placed by procgen, like an ore vein or a berry patch.
+#[derive(Debug, Clone)]
+pub struct Deposit {
+    /// Resource type, like "iron" or "clay".
+    pub name: String,
+    pub tiles: Vec<U16Vec2>,
+}
 
 #[derive(Default, Debug, Clone, Resource)]
 pub struct Map {
     pub biome: String,
+    pub seed: u64,
     pub elevation: GridElevation,
     pub tile: GridId,
+    pub structures: Vec<Structure>,
+    pub deposits: Vec<Deposit>,
+    /// Where the player spawns. See [`crate::spawn::select_spawn`].
+    pub spawn: Option<U16Vec2>,
 }
 
 impl Map {
-    pub fn new(biome: String) -> Self {
+    pub fn new(biome: String, seed: u64) -> Self {
         Self {
             elevation: Grid::new(),
             biome,
+            seed,
             tile: GridId::new(),
+            structures: vec![],
+            deposits: vec![],
+            spawn: None,
+        }
+    }
+
+    /// The resource deposit covering the given tile, if any.
+    pub fn deposit_at(&self, pos: U16Vec2) -> Option<&Deposit> {
+        self.deposits.iter().find(|d| d.tiles.contains(&pos))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum MapLoadError {
+    #[error("Tile {0} not found")]
+    UnknownTile(String),
+    #[error("Tile index {0} is out of the saved tile names")]
+    InvalidTileIndex(u16),
+    #[error("Expected {LAYERS_COUNT} tile layers, but found {0}")]
+    InvalidLayers(usize),
+    #[error("Expected {LAYER_SIZE} values on every layer, but found {0}")]
+    InvalidSize(usize),
+}
+
+/// Serializable version of [`Map`]. Tiles are saved by name, so saves keep working when tiles
+/// are added to or reordered on the [`TileRegistry`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapSave {
+    pub biome: String,
+    pub seed: u64,
+    /// Tile names, indexed by [`Self::tiles`].
+    pub tile_names: Vec<String>,
+    /// Index on [`Self::tile_names`] of every tile, one list per layer.
+    pub tiles: Vec<Vec<u16>>,
+    pub elevation: Vec<f32>,
+    pub structures: Vec<StructureSave>,
+    pub deposits: Vec<DepositSave>,
+    pub spawn: Option<(u16, u16)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructureSave {
+    pub name: String,
+    pub prefab: String,
+    /// Min and max of [`Structure::bounds`].
+    pub bounds: ((u32, u32), (u32, u32)),
+    pub spawn: Option<(u16, u16)>,
+    pub entrance: Option<(u16, u16)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepositSave {
+    pub name: String,
+    pub tiles: Vec<(u16, u16)>,
+}
+
+fn to_tuple(pos: U16Vec2) -> (u16, u16) {
+    (pos.x, pos.y)
+}
+
+fn from_tuple((x, y): (u16, u16)) -> U16Vec2 {
+    U16Vec2::new(x, y)
+}
+
+impl Map {
+    /// Converts the map into its serializable version, using the names of the given tiles.
+    pub fn save(&self, tile_registry: &TileRegistry) -> MapSave {
+        let mut tile_names = Vec::<String>::new();
+        let mut indices = HashMap::<TileId, u16>::new();
+        let tiles = LAYERS
+            .iter()
+            .map(|&layer| {
+                self.tile[layer]
+                    .iter()
+                    .map(|&tile_id| {
+                        *indices.entry(tile_id).or_insert_with(|| {
+                            // Tiles missing on the registry are saved as no tile at all.
+                            tile_names.push(
+                                tile_registry
+                                    .get(&tile_id)
+                                    .unwrap_or(&tile::NONE_INFO)
+                                    .name
+                                    .to_string(),
+                            );
+                            (tile_names.len() - 1) as u16
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        MapSave {
+            biome: self.biome.clone(),
+            seed: self.seed,
+            tile_names,
+            tiles,
+            elevation: self.elevation.iter().map(|e| **e).collect(),
+            structures: self
+                .structures
+                .iter()
+                .map(|structure| StructureSave {
+                    name: structure.name.clone(),
+                    prefab: structure.prefab.clone(),
+                    bounds: (structure.bounds.min.into(), structure.bounds.max.into()),
+                    spawn: structure.spawn.map(to_tuple),
+                    entrance: structure.entrance.map(to_tuple),
+                })
+                .collect(),
+            deposits: self
+                .deposits
+                .iter()
+                .map(|deposit| DepositSave {
+                    name: deposit.name.clone(),
+                    tiles: deposit.tiles.iter().copied().map(to_tuple).collect(),
+                })
+                .collect(),
+            spawn: self.spawn.map(to_tuple),
+        }
+    }
+
+    /// Builds a map back from its serializable version, resolving tile names on the given tiles.
+    pub fn load(save: &MapSave, tile_registry: &TileRegistry) -> Result<Self, MapLoadError> {
+        let tile_ids = save
+            .tile_names
+            .iter()
+            .map(|name| {
+                tile_registry
+                    .find_id_by_name(name)
+                    .ok_or_else(|| MapLoadError::UnknownTile(name.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if save.tiles.len() != LAYERS_COUNT {
+            return Err(MapLoadError::InvalidLayers(save.tiles.len()));
+        }
+
+        if save.elevation.len() != LAYER_SIZE {
+            return Err(MapLoadError::InvalidSize(save.elevation.len()));
         }
+
+        let mut map = Map::new(save.biome.clone(), save.seed);
+
+        for (&layer, indices) in LAYERS.iter().zip(&save.tiles) {
+            if indices.len() != LAYER_SIZE {
+                return Err(MapLoadError::InvalidSize(indices.len()));
+            }
+
+            for (tile, &index) in map.tile[layer].iter_mut().zip(indices) {
+                *tile = *tile_ids
+                    .get(index as usize)
+                    .ok_or(MapLoadError::InvalidTileIndex(index))?;
+            }
+        }
+
+        for (elevation, &value) in map.elevation.iter_mut().zip(&save.elevation) {
+            *elevation = TileElevation::new(value);
+        }
+
+        map.structures = save
+            .structures
+            .iter()
+            .map(|structure| Structure {
+                name: structure.name.clone(),
+                prefab: structure.prefab.clone(),
+                bounds: URect::from_corners(structure.bounds.0.into(), structure.bounds.1.into()),
+                spawn: structure.spawn.map(from_tuple),
+                entrance: structure.entrance.map(from_tuple),
+            })
+            .collect();
+        map.deposits = save
+            .deposits
+            .iter()
+            .map(|deposit| Deposit {
+                name: deposit.name.clone(),
+                tiles: deposit.tiles.iter().copied().map(from_tuple).collect(),
+            })
+            .collect();
+        map.spawn = save.spawn.map(from_tuple);
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eternal_grid::{grid::LayerIndex, tile::TileInfo};
+
+    use super::*;
+
+    fn tile_registry(names: &[&str]) -> TileRegistry {
+        TileRegistry::new(
+            names
+                .iter()
+                .enumerate()
+                .map(|(id, &name)| {
+                    (
+                        TileId::new(id as u16 + 1),
+                        TileInfo {
+                            name: name.to_string().into(),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn map() -> Map {
+        let mut map = Map::new("Forest".to_string(), 42);
+        map.tile[LayerIndex::Floor].set(1, 2, TileId::new(1));
+        map.tile[LayerIndex::Wall].set(3, 4, TileId::new(2));
+        map.elevation.set(1, 2, TileElevation::new(0.25));
+        map.structures.push(Structure {
+            name: "ruins".to_string(),
+            prefab: "ruins_small".to_string(),
+            bounds: URect::new(10, 10, 15, 20),
+            spawn: Some(U16Vec2::new(12, 12)),
+            entrance: None,
+        });
+        map.deposits.push(Deposit {
+            name: "iron".to_string(),
+            tiles: vec![U16Vec2::new(5, 5), U16Vec2::new(5, 6)],
+        });
+        map.spawn = Some(U16Vec2::new(12, 12));
+        map
+    }
+
+    #[test]
+    fn save_round_trip() {
+        // Arrange
+        let map = map();
+        let saved = map.save(&tile_registry(&["GRASS", "TREE"]));
+        let source = ron::to_string(&saved).unwrap();
+
+        // Tiles were reordered since the map was saved.
+        let tile_registry = tile_registry(&["STONE", "TREE", "GRASS"]);
+
+        // Act
+        let loaded = Map::load(&ron::from_str(&source).unwrap(), &tile_registry).unwrap();
+
+        // Assert
+        assert_eq!(loaded.save(&tile_registry), saved);
+        assert_eq!(
+            loaded.tile[LayerIndex::Floor].get(1, 2),
+            &tile_registry.get_id_by_name("GRASS")
+        );
+        assert_eq!(
+            loaded.tile[LayerIndex::Wall].get(3, 4),
+            &tile_registry.get_id_by_name("TREE")
+        );
+        assert_eq!(**loaded.elevation.get(1, 2), 0.25);
+        assert_eq!(loaded.structures[0].bounds, map.structures[0].bounds);
+        assert_eq!(loaded.deposits[0].tiles, map.deposits[0].tiles);
+        assert_eq!(loaded.spawn, map.spawn);
+    }
+
+    #[test]
+    fn load_unknown_tile() {
+        // Arrange
+        let saved = map().save(&tile_registry(&["GRASS", "TREE"]));
+
+        // Act
+        let loaded = Map::load(&saved, &tile_registry(&["GRASS"]));
+
+        // Assert
+        assert_eq!(
+            loaded.err(),
+            Some(MapLoadError::UnknownTile("TREE".to_string()))
+        );
     }
 }
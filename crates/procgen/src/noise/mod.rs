@@ -1,3 +1,3 @@
 mod send_worley;
 mod stack;
-pub(crate) use stack::NoiseStack;
+pub use stack::{NoiseStack, NoiseStackParserError};
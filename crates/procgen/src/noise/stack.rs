@@ -17,7 +17,7 @@ fn from_worley_config(spec: WorleyConfigReturnType) -> worley::ReturnType {
 }
 
 #[derive(Debug, thiserror::Error)]
-pub(crate) enum NoiseStackParserError {
+pub enum NoiseStackParserError {
     #[error("Failed to load noise stack: Noise stack is empty")]
     Empty,
     #[error("Failed to load noise stack: No main layer was found")]
@@ -55,9 +55,7 @@ impl Clone for NoiseStack {
 }
 
 impl NoiseStack {
-    pub(crate) fn from_config(
-        specs: &NoiseStackConfig,
-    ) -> Result<NoiseStack, NoiseStackParserError> {
+    pub fn from_config(specs: &NoiseStackConfig) -> Result<NoiseStack, NoiseStackParserError> {
         if specs.is_empty() {
             return Err(NoiseStackParserError::Empty);
         }
@@ -289,6 +287,22 @@ impl NoiseStack {
         self.specs.is_empty()
     }
 
+    /// Names of all layers on this stack, sorted.
+    pub fn layers(&self) -> Vec<&str> {
+        let mut names = self.specs.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Builds a stack which outputs the given layer instead of `main`.
+    pub fn layer(&self, name: &str) -> Option<NoiseStack> {
+        let main = Self::build(&self.specs, name).ok()?;
+        Some(NoiseStack {
+            specs: self.specs.clone(),
+            main: Some(main),
+        })
+    }
+
     // pub(crate) fn rebuild(&mut self) -> Result<(), NoiseStackParserError> {
     //     self.main = Some(Self::build(&self.specs, "main")?);
     //     Ok(())
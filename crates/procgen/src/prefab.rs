@@ -0,0 +1,380 @@
+use bevy::{math::U16Vec2, prelude::*};
+use eternal_config::{
+    prefab::{PrefabCellConfig, PrefabConfig, PrefabRegistryConfig},
+    server::{ConfigAssetUpdated, ConfigServer, Configs},
+};
+use eternal_grid::{
+    ecs::TileRegistry,
+    grid::{self, LayerIndex},
+    tile::TileId,
+};
+
+use crate::{
+    map::Map,
+    rng::Rng,
+    wfc::{self, AdjacencyRules},
+};
+
+/// How many times a prefab floor is generated before falling back to the example floor.
+const FLOOR_ATTEMPTS: u32 = 10;
+
+pub(crate) struct PrefabPlugin;
+
+impl Plugin for PrefabPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PrefabRegistry>()
+            .add_systems(Startup, setup);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PrefabError {
+    #[error("Tile {1} of prefab {0} not found")]
+    UnknownTile(String, String),
+}
+
+#[derive(Debug, Clone, Reflect)]
+pub enum PrefabCell {
+    Tile(TileId),
+    Optional(TileId, f32),
+    Random(Vec<(TileId, f32)>),
+}
+
+impl PrefabCell {
+    fn from_config(
+        config: &PrefabCellConfig,
+        prefab: &str,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, PrefabError> {
+        let tile = |name: &str| {
+            tile_registry
+                .find_id_by_name(name)
+                .ok_or_else(|| PrefabError::UnknownTile(prefab.to_string(), name.to_string()))
+        };
+
+        Ok(match config {
+            PrefabCellConfig::Tile(name) => Self::Tile(tile(name)?),
+            PrefabCellConfig::Optional(name, chance) => Self::Optional(tile(name)?, *chance),
+            PrefabCellConfig::Random(tiles) => Self::Random(
+                tiles
+                    .iter()
+                    .map(|(name, weight)| Ok((tile(name)?, *weight)))
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+
+    /// Resolves which tile should be placed, if any.
+    fn resolve(&self, rng: &mut Rng) -> Option<TileId> {
+        match self {
+            &PrefabCell::Tile(tile) => Some(tile),
+            &PrefabCell::Optional(tile, chance) => rng.chance(chance).then_some(tile),
+            PrefabCell::Random(tiles) => rng.pick_weighted(tiles, |(_, w)| *w).map(|(t, _)| *t),
+        }
+    }
+}
+
+/// Compiled version of [`PrefabConfig`]. Cells are stored row by row, starting from the bottom
+/// row, so they match map coordinates.
+#[derive(Default, Debug, Clone, Reflect)]
+pub struct Prefab {
+    pub name: String,
+    pub size: U16Vec2,
+    /// Player spawn marker, relative to the bottom left corner.
+    pub spawn: Option<U16Vec2>,
+    /// Road entrance marker, relative to the bottom left corner.
+    pub entrance: Option<U16Vec2>,
+    floor: Vec<Option<PrefabCell>>,
+    wall: Vec<Option<PrefabCell>>,
+    /// Rules learned from the floor, used to generate a new one on each stamp.
+    floor_rules: Option<AdjacencyRules>,
+}
+
+impl Prefab {
+    pub fn from_config(
+        config: &PrefabConfig,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, PrefabError> {
+        let width = config
+            .floor
+            .iter()
+            .chain(config.wall.iter())
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or_default();
+        let height = config.floor.len().max(config.wall.len());
+
+        let legend = config
+            .legend
+            .iter()
+            .map(|(c, cell)| {
+                Ok((
+                    *c,
+                    PrefabCell::from_config(cell, &config.name, tile_registry)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let layer = |rows: &[String]| {
+            let mut cells = vec![None; width * height];
+            for (row_idx, row) in rows.iter().enumerate() {
+                let y = height - 1 - row_idx;
+                for (x, c) in row.chars().enumerate() {
+                    cells[y * width + x] = legend
+                        .iter()
+                        .find(|(key, _)| *key == c)
+                        .map(|(_, cell)| cell.clone());
+                }
+            }
+            cells
+        };
+
+        let floor = layer(&config.floor);
+        let floor_rules = config.generate_floor.then(|| {
+            let example = floor
+                .iter()
+                .map(|cell| match cell {
+                    &Some(PrefabCell::Tile(tile)) => Some(tile),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            AdjacencyRules::from_example(&example, width)
+        });
+
+        Ok(Self {
+            name: config.name.clone(),
+            size: U16Vec2::new(width as u16, height as u16),
+            spawn: config
+                .spawn
+                .map(|(x, row)| U16Vec2::new(x, (height as u16).saturating_sub(row + 1))),
+            entrance: config
+                .entrance
+                .map(|(x, row)| U16Vec2::new(x, (height as u16).saturating_sub(row + 1))),
+            floor,
+            wall: layer(&config.wall),
+            floor_rules,
+        })
+    }
+
+    /// Generates new tiles for the `Tile` cells of the floor, when enabled.
+    fn generate_floor(&self, rng: &mut Rng) -> Option<Vec<Option<TileId>>> {
+        let rules = self.floor_rules.as_ref()?;
+        let mask = self
+            .floor
+            .iter()
+            .map(|cell| matches!(cell, Some(PrefabCell::Tile(_))))
+            .collect::<Vec<_>>();
+
+        let floor = (0..FLOOR_ATTEMPTS)
+            .find_map(|_| wfc::generate(&mask, self.size.x as usize, rules, rng));
+        if floor.is_none() {
+            warn!("Failed to generate floor of prefab {}", self.name);
+        }
+
+        floor
+    }
+
+    /// Stamps this prefab into the map, with the bottom left corner at `origin`. Cells outside
+    /// the map are ignored.
+    pub fn stamp(&self, origin: U16Vec2, map: &mut Map, rng: &mut Rng) {
+        let generated = self.generate_floor(rng);
+
+        for (layer, cells) in [
+            (LayerIndex::Floor, &self.floor),
+            (LayerIndex::Wall, &self.wall),
+        ] {
+            for (idx, cell) in cells.iter().enumerate() {
+                let Some(cell) = cell else {
+                    continue;
+                };
+
+                let offset = U16Vec2::new(idx as u16 % self.size.x, idx as u16 / self.size.x);
+                let pos = origin.as_uvec2() + offset.as_uvec2();
+                if pos.cmpge(grid::DIMS).any() {
+                    continue;
+                }
+
+                let generated = generated
+                    .as_ref()
+                    .filter(|_| layer == LayerIndex::Floor)
+                    .and_then(|floor| floor[idx]);
+                if let Some(tile) = generated.or_else(|| cell.resolve(rng)) {
+                    map.tile[layer].set(pos.x as u16, pos.y as u16, tile);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Resource, Reflect, Deref)]
+pub struct PrefabRegistry(Vec<Prefab>);
+
+impl PrefabRegistry {
+    pub fn new(prefabs: Vec<Prefab>) -> Self {
+        Self(prefabs)
+    }
+
+    pub fn from_config(
+        config: &PrefabRegistryConfig,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, PrefabError> {
+        Ok(Self(
+            config
+                .iter()
+                .map(|prefab| Prefab::from_config(prefab, tile_registry))
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.0.iter().find(|p| p.name == name)
+    }
+}
+
+fn setup(mut config_server: ConfigServer) {
+    config_server
+        .load::<PrefabRegistryConfig>("config/prefabs.ron")
+        .observe(on_prefab_config_updated);
+}
+
+fn on_prefab_config_updated(
+    updated: On<ConfigAssetUpdated>,
+    configs: Configs<PrefabRegistryConfig>,
+    tile_registry: Res<TileRegistry>,
+    mut commands: Commands,
+) {
+    let Some(config) = configs.get(updated.id()) else {
+        error!("Prefab registry config not found.");
+        return;
+    };
+
+    debug!("Updating prefab registry!");
+
+    match PrefabRegistry::from_config(config, &tile_registry) {
+        Ok(registry) => commands.insert_resource(registry),
+        Err(err) => error!("Failed to build prefab registry. {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashMap;
+    use eternal_grid::tile::TileInfo;
+
+    use super::*;
+
+    fn tile_registry() -> TileRegistry {
+        let tiles = ["DIRT", "STONE_WALL"]
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| {
+                (
+                    TileId::new(id as u16),
+                    TileInfo {
+                        name: name.into(),
+                        ..default()
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        TileRegistry::new(tiles)
+    }
+
+    #[test]
+    fn stamp_prefab() {
+        // Arrange
+        let config = PrefabConfig {
+            name: "HUT".to_string(),
+            floor: vec!["ddd".to_string(), "d d".to_string()],
+            wall: vec!["#".to_string()],
+            legend: vec![
+                ('d', PrefabCellConfig::Tile("DIRT".to_string())),
+                (
+                    '#',
+                    PrefabCellConfig::Optional("STONE_WALL".to_string(), 1.0),
+                ),
+            ],
+            spawn: Some((1, 0)),
+            entrance: Some((2, 1)),
+            generate_floor: false,
+        };
+        let prefab = Prefab::from_config(&config, &tile_registry()).unwrap();
+        let mut map = Map::new("test".to_string(), 0);
+
+        // Act
+        prefab.stamp(U16Vec2::new(10, 20), &mut map, &mut Rng::new(0));
+
+        // Assert
+        let dirt = TileId::new(0);
+        let wall = TileId::new(1);
+        assert_eq!(prefab.size, U16Vec2::new(3, 2));
+        assert_eq!(prefab.spawn, Some(U16Vec2::new(1, 1)));
+        assert_eq!(prefab.entrance, Some(U16Vec2::new(2, 0)));
+        let floor = &map.tile[LayerIndex::Floor];
+        assert_eq!(*floor.get(10, 21), dirt);
+        assert_eq!(*floor.get(12, 21), dirt);
+        assert_eq!(*floor.get(10, 20), dirt);
+        assert!(floor.get(11, 20).is_none());
+        assert_eq!(*map.tile[LayerIndex::Wall].get(10, 21), wall);
+        assert!(map.tile[LayerIndex::Wall].get(11, 21).is_none());
+    }
+
+    #[test]
+    fn prefab_unknown_tile() {
+        // Arrange
+        let config = PrefabConfig {
+            name: "HUT".to_string(),
+            floor: vec!["d".to_string()],
+            legend: vec![(
+                'd',
+                PrefabCellConfig::Random(vec![
+                    ("DIRT".to_string(), 1.0),
+                    ("DIRTT".to_string(), 1.0),
+                ]),
+            )],
+            ..default()
+        };
+
+        // Act
+        let result = Prefab::from_config(&config, &tile_registry());
+
+        // Assert
+        assert_eq!(
+            result.unwrap_err(),
+            PrefabError::UnknownTile("HUT".to_string(), "DIRTT".to_string())
+        );
+    }
+
+    #[test]
+    fn generate_prefab_floor() {
+        // Arrange
+        let config = PrefabConfig {
+            name: "STRIPES".to_string(),
+            floor: vec!["dddd".to_string(), "ssss".to_string(), "d  d".to_string()],
+            legend: vec![
+                ('d', PrefabCellConfig::Tile("DIRT".to_string())),
+                ('s', PrefabCellConfig::Tile("STONE_WALL".to_string())),
+            ],
+            generate_floor: true,
+            ..default()
+        };
+        let prefab = Prefab::from_config(&config, &tile_registry()).unwrap();
+        let mut map = Map::new("test".to_string(), 0);
+
+        // Act
+        prefab.stamp(U16Vec2::ZERO, &mut map, &mut Rng::new(0));
+
+        // Assert
+        let floor = &map.tile[LayerIndex::Floor];
+        // Cells not on the example are kept
+        assert!(floor.get(1, 0).is_none());
+        assert!(floor.get(2, 0).is_none());
+        // Rows only have a single tile and dirt is never next to dirt vertically
+        for y in 0..3 {
+            let row = (0..4).map(|x| *floor.get(x, y)).collect::<Vec<_>>();
+            assert!(row.iter().all(|&tile| tile == row[0] || tile.is_none()));
+        }
+        assert_ne!(*floor.get(0, 0), *floor.get(0, 1));
+        assert_ne!(*floor.get(0, 1), *floor.get(0, 2));
+    }
+}
@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use eternal_grid::{
+    ecs::TileRegistry,
+    grid::{self, LayerIndex},
+    tile::{NONE_INFO, TileId},
+};
+use image::{Rgba, RgbaImage, imageops};
+
+use crate::{
+    atlas::{self, Atlas},
+    biome::{BiomePallet, PalletChannel},
+    map::Map,
+    noise::NoiseStack,
+    rng::Rng,
+};
+
+fn tile_color(tile_id: TileId, tiles: &TileRegistry) -> Rgba<u8> {
+    Rgba(
+        tiles
+            .get(&tile_id)
+            .unwrap_or(&NONE_INFO)
+            .map_color
+            .to_u8_array(),
+    )
+}
+
+/// Renders the map using the `map_color` of each tile. Walls are drawn over floors.
+pub fn map_image(map: &Map, tiles: &TileRegistry) -> RgbaImage {
+    RgbaImage::from_fn(grid::DIMS.x, grid::DIMS.y, |x, y| {
+        let (x, y) = (x as u16, y as u16);
+        let wall = *map.tile[LayerIndex::Wall].get(x, y);
+        let tile_id = if wall.is_none() {
+            *map.tile[LayerIndex::Floor].get(x, y)
+        } else {
+            wall
+        };
+
+        tile_color(tile_id, tiles)
+    })
+}
+
+/// Renders the atlas, collapsing each cell elevation on the given pallet, the same way maps are
+/// generated. Only the elevation channel is available, so pallet entries using other channels
+/// never match. Atlas rivers are drawn using the pallet river tile.
+pub fn atlas_image(atlas: &Atlas, pallet: &BiomePallet, tiles: &TileRegistry) -> RgbaImage {
+    let size = atlas::ATLAS_AXIS_SIZE as u32;
+    let mut rng = Rng::from_stream(0, "preview");
+    let mut image = RgbaImage::from_fn(size, size, |x, y| {
+        let elevation = atlas.elevation[atlas::to_index(x as u16, y as u16)];
+        let values = pallet
+            .channels()
+            .iter()
+            .map(|channel| match channel {
+                PalletChannel::Elevation => elevation,
+                _ => f32::NAN,
+            })
+            .collect::<Vec<_>>();
+        let wall = pallet.collapse(LayerIndex::Wall, &values, &mut rng);
+        let tile_id = if wall.is_none() {
+            pallet.collapse(LayerIndex::Floor, &values, &mut rng)
+        } else {
+            wall
+        };
+
+        tile_color(tile_id, tiles)
+    });
+
+    let river = tile_color(pallet.river, tiles);
+    for cell in atlas.rivers.iter().flat_map(|r| r.path.iter()) {
+        image.put_pixel(cell.x as u32, cell.y as u32, river);
+    }
+
+    image
+}
+
+/// Renders the values as a heatmap, from blue on the lowest value to red on the highest one.
+pub fn heatmap(width: u32, height: u32, values: &[f32]) -> RgbaImage {
+    let (min, max) = values.iter().fold((f32::MAX, f32::MIN), |(min, max), &v| {
+        (min.min(v), max.max(v))
+    });
+    let range = (max - min).max(f32::EPSILON);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let t = (values[(y * width + x) as usize] - min) / range;
+        Rgba(Srgba::from(Color::hsl((1.0 - t) * 240.0, 1.0, 0.5)).to_u8_array())
+    })
+}
+
+/// Renders the map elevation as a heatmap.
+pub fn elevation_image(map: &Map) -> RgbaImage {
+    let values = map.elevation.iter().map(|e| **e).collect::<Vec<_>>();
+    heatmap(grid::DIMS.x, grid::DIMS.y, &values)
+}
+
+/// Renders the atlas elevation as a heatmap.
+pub fn atlas_elevation_image(atlas: &Atlas) -> RgbaImage {
+    let size = atlas::ATLAS_AXIS_SIZE as u32;
+    heatmap(size, size, &atlas.elevation)
+}
+
+/// Renders the noise stack output over a map area as a heatmap.
+pub fn noise_image(stack: &NoiseStack) -> RgbaImage {
+    let values = (0..grid::LAYER_SIZE)
+        .map(|idx| {
+            let x = idx % grid::DIMS.x as usize;
+            let y = idx / grid::DIMS.x as usize;
+            stack.get(x as f32, y as f32)
+        })
+        .collect::<Vec<_>>();
+    heatmap(grid::DIMS.x, grid::DIMS.y, &values)
+}
+
+/// Places images side by side on a grid with the given number of columns, row by row.
+pub fn contact_sheet(images: &[RgbaImage], columns: u32) -> RgbaImage {
+    let columns = columns.max(1);
+    let rows = (images.len() as u32).div_ceil(columns);
+    let cell = images.iter().fold(UVec2::ZERO, |size, image| {
+        size.max(UVec2::new(image.width(), image.height()))
+    });
+
+    let mut sheet = RgbaImage::new(cell.x * columns, cell.y * rows);
+    for (i, image) in images.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        imageops::replace(
+            &mut sheet,
+            image,
+            (column * cell.x) as i64,
+            (row * cell.y) as i64,
+        );
+    }
+
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contact_sheet_layout() {
+        // Arrange
+        let images = (0..5u8)
+            .map(|i| RgbaImage::from_pixel(4, 2, Rgba([i, 0, 0, 255])))
+            .collect::<Vec<_>>();
+
+        // Act
+        let sheet = contact_sheet(&images, 3);
+
+        // Assert
+        assert_eq!(sheet.dimensions(), (12, 4));
+        assert_eq!(sheet.get_pixel(0, 0).0[0], 0);
+        assert_eq!(sheet.get_pixel(8, 1).0[0], 2);
+        assert_eq!(sheet.get_pixel(4, 2).0[0], 4);
+        // Empty cells are left transparent
+        assert_eq!(sheet.get_pixel(8, 2).0[3], 0);
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::VecDeque;
+
+use bevy::{math::U16Vec2, prelude::*};
+use eternal_grid::grid::{self, LayerIndex};
+
+use crate::{
+    biome::{Biome, ResourceSpawn, VeinShape},
+    map::{Deposit, Map},
+    rng::Rng,
+    rule::{self, RuleContext},
+    structure::PLACEMENT_ATTEMPTS,
+};
+
+const DIRECTIONS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+/// Distance, in tiles, from each tile to the nearest tile without a wall. Diagonals count as a
+/// single step, so a 3x3 wall block has a depth of 1 on its center.
+fn wall_depths(map: &Map) -> Vec<u8> {
+    let walls = &map.tile[LayerIndex::Wall];
+    let mut depths = vec![u8::MAX; grid::DIMS.element_product() as usize];
+    let mut queue = VecDeque::new();
+
+    for y in 0..grid::DIMS.y as u16 {
+        for x in 0..grid::DIMS.x as u16 {
+            if walls.get(x, y).is_none() {
+                depths[grid::to_index(x, y)] = 0;
+                queue.push_back(U16Vec2::new(x, y));
+            }
+        }
+    }
+
+    while let Some(pos) = queue.pop_front() {
+        let depth = depths[grid::to_index(pos.x, pos.y)].saturating_add(1);
+        for p in grid::SampleShape::Square(1).range(pos) {
+            let idx = grid::to_index(p.x, p.y);
+            if depths[idx] > depth {
+                depths[idx] = depth;
+                queue.push_back(p);
+            }
+        }
+    }
+
+    depths
+}
+
+/// Checks if the given tile can be part of a vein of the given resource.
+fn can_host(
+    resource: &ResourceSpawn,
+    pos: IVec2,
+    map: &Map,
+    depths: &[u8],
+    taken: &[bool],
+) -> bool {
+    if pos.cmplt(IVec2::ZERO).any() || pos.cmpge(grid::DIMS.as_ivec2()).any() {
+        return false;
+    }
+
+    let (x, y) = (pos.x as u16, pos.y as u16);
+    let idx = grid::to_index(x, y);
+    let (min, max) = resource.depth;
+
+    if taken[idx]
+        || !(min..=max).contains(&depths[idx])
+        || map.structures.iter().any(|s| s.contains(pos.as_uvec2()))
+    {
+        return false;
+    }
+
+    if resource.hosts.is_empty() {
+        return true;
+    }
+
+    let wall = map.tile[LayerIndex::Wall].get(x, y);
+    let host = if wall.is_none() {
+        map.tile[LayerIndex::Floor].get(x, y)
+    } else {
+        wall
+    };
+
+    resource.hosts.contains(host)
+}
+
+/// Grows a vein from the given origin, only on tiles accepted by `can_host`.
+fn grow_vein(
+    shape: VeinShape,
+    origin: IVec2,
+    can_host: impl Fn(IVec2) -> bool,
+    rng: &mut Rng,
+) -> Vec<IVec2> {
+    let mut tiles = vec![origin];
+
+    match shape {
+        VeinShape::RandomWalk { steps: (min, max) } => {
+            let mut pos = origin;
+            for _ in 0..rng.range_u32(min, max + 1) {
+                let next = pos + DIRECTIONS[rng.range_u32(0, 4) as usize];
+                if !can_host(next) {
+                    continue;
+                }
+
+                pos = next;
+                if !tiles.contains(&pos) {
+                    tiles.push(pos);
+                }
+            }
+        }
+        VeinShape::Blob { radius: (min, max) } => {
+            let radius = rng.range_u32(min as u32, max as u32 + 1) as i32;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let offset = IVec2::new(dx, dy);
+                    if offset == IVec2::ZERO {
+                        continue;
+                    }
+
+                    // Roughen the border, so patches aren't perfect circles.
+                    let reach = radius as f32 + 0.5 - rng.next_f32();
+                    let pos = origin + offset;
+                    if offset.as_vec2().length() <= reach && can_host(pos) {
+                        tiles.push(pos);
+                    }
+                }
+            }
+        }
+    }
+
+    tiles
+}
+
+fn place_resource(
+    resource: &ResourceSpawn,
+    biome: &Biome,
+    map: &mut Map,
+    depths: &[u8],
+    taken: &mut [bool],
+    distances: &rule::DistanceFields,
+    rng: &mut Rng,
+) {
+    let (min, max) = resource.count;
+    let count = rng.range_u32(min, max.saturating_add(1));
+
+    let mut placed = 0;
+    for _ in 0..count.saturating_mul(PLACEMENT_ATTEMPTS) {
+        if placed >= count {
+            break;
+        }
+
+        let origin = IVec2::new(
+            rng.range_u32(0, grid::DIMS.x) as i32,
+            rng.range_u32(0, grid::DIMS.y) as i32,
+        );
+
+        let ctx = RuleContext {
+            map,
+            noise: &biome.resource_noise,
+            distances,
+        };
+
+        if !can_host(resource, origin, map, depths, taken)
+            || !resource.rule.check(origin.x as u16, origin.y as u16, &ctx)
+        {
+            continue;
+        }
+
+        let tiles = grow_vein(
+            resource.shape,
+            origin,
+            |pos| can_host(resource, pos, map, depths, taken),
+            rng,
+        )
+        .into_iter()
+        .map(|pos| pos.as_u16vec2())
+        .collect::<Vec<_>>();
+
+        for pos in &tiles {
+            taken[grid::to_index(pos.x, pos.y)] = true;
+            if let Some(tile) = resource.tile {
+                map.tile[LayerIndex::Wall].set(pos.x, pos.y, tile);
+            }
+        }
+
+        map.deposits.push(Deposit {
+            name: resource.name.clone(),
+            tiles,
+        });
+
+        placed += 1;
+    }
+
+    if placed < min {
+        debug!(
+            "Only {placed} of {min} {} veins could be placed",
+            resource.name
+        );
+    }
+}
+
+/// Places the biome resources, like ore veins inside mountains or clay near rivers. Veins are
+/// recorded on [`Map::deposits`] and, when the resource has a tile, placed on the wall layer.
+pub(crate) fn place_resources(biome: &Biome, map: &mut Map, rng: &mut Rng) {
+    if biome.resources.is_empty() {
+        return;
+    }
+
+    let depths = wall_depths(map);
+    let distances = rule::distance_fields(biome.resources.iter().map(|r| &r.rule), map);
+    let mut taken = vec![false; depths.len()];
+
+    for resource in &biome.resources {
+        place_resource(resource, biome, map, &depths, &mut taken, &distances, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eternal_grid::tile::TileId;
+
+    use super::*;
+
+    #[test]
+    fn ore_veins_inside_mountains() {
+        // Arrange
+        let grass = TileId::new(0);
+        let stone = TileId::new(1);
+        let iron = TileId::new(2);
+        let mut map = Map::new("test".to_string(), 0);
+        map.tile[LayerIndex::Floor].fill(grass);
+        // A mountain covering the left half of the map.
+        for y in 0..grid::DIMS.y as u16 {
+            for x in 0..128 {
+                map.tile[LayerIndex::Wall].set(x, y, stone);
+            }
+        }
+        let biome = Biome {
+            resources: vec![
+                ResourceSpawn {
+                    name: "iron".to_string(),
+                    tile: Some(iron),
+                    hosts: vec![stone],
+                    shape: VeinShape::RandomWalk { steps: (10, 20) },
+                    count: (5, 5),
+                    depth: (3, u8::MAX),
+                    ..default()
+                },
+                ResourceSpawn {
+                    name: "clay".to_string(),
+                    hosts: vec![grass],
+                    shape: VeinShape::Blob { radius: (2, 3) },
+                    count: (3, 3),
+                    ..default()
+                },
+            ],
+            ..default()
+        };
+        let depths = wall_depths(&map);
+        let before = map.tile[LayerIndex::Wall].to_vec();
+
+        // Act
+        place_resources(&biome, &mut map, &mut Rng::new(0));
+
+        // Assert
+        assert_eq!(map.deposits.len(), 8);
+
+        let walls = &map.tile[LayerIndex::Wall];
+        for deposit in map.deposits.iter().filter(|d| d.name == "iron") {
+            assert!(deposit.tiles.len() > 1);
+            for pos in &deposit.tiles {
+                assert_eq!(walls.get(pos.x, pos.y), &iron);
+                assert!(depths[grid::to_index(pos.x, pos.y)] >= 3);
+            }
+        }
+
+        for deposit in map.deposits.iter().filter(|d| d.name == "clay") {
+            assert!(deposit.tiles.len() > 1);
+            for pos in &deposit.tiles {
+                assert!(pos.x >= 128);
+                assert_eq!(map.deposit_at(*pos).unwrap().name, "clay");
+            }
+        }
+
+        // Clay has no tile, so only iron veins changed the wall layer.
+        let changed = walls
+            .iter()
+            .zip(&before)
+            .filter(|(after, before)| after != before)
+            .count();
+        let iron_tiles = map
+            .deposits
+            .iter()
+            .filter(|d| d.name == "iron")
+            .map(|d| d.tiles.len())
+            .sum::<usize>();
+        assert_eq!(changed, iron_tiles);
+    }
+}
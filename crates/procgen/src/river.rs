@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use eternal_grid::{
+    grid::{self, LayerIndex},
+    tile::TileId,
+};
+
+use crate::{
+    atlas::{self, AtlasRegion},
+    map::Map,
+    rng::{self, Rng},
+};
+
+/// How many times each river segment is split in half to make it meander.
+const MEANDER_DEPTH: u32 = 3;
+
+/// Maximum sideways displacement of a meander, relative to the segment length.
+const MEANDER_STRENGTH: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum RiverTile {
+    None,
+    Bank,
+    Water,
+}
+
+/// A river point in map local tile coordinates.
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    pos: Vec2,
+    width: f32,
+}
+
+/// Converts an atlas cell center to the local tile coordinates of the given map.
+fn to_local(region: &AtlasRegion, cell: UVec2) -> Vec2 {
+    let tiles_per_cell = grid::DIMS.as_vec2() / atlas::MAP_RESOLUTION as f32;
+    let map_origin = region.map.as_vec2() * atlas::MAP_RESOLUTION as f32;
+    (cell.as_vec2() + 0.5 - map_origin) * tiles_per_cell
+}
+
+/// Recursively splits the segment, moving each midpoint sideways. The randomness is seeded by
+/// the segment atlas cells, so neighbor maps agree on the river shape.
+fn meander(a: Point, b: Point, depth: u32, rng: &mut Rng, points: &mut Vec<Point>) {
+    if depth == 0 {
+        points.push(b);
+        return;
+    }
+
+    let dir = b.pos - a.pos;
+    let offset = dir.perp() * rng.range_f32(-MEANDER_STRENGTH, MEANDER_STRENGTH);
+    let mid = Point {
+        pos: a.pos.midpoint(b.pos) + offset,
+        width: (a.width + b.width) / 2.0,
+    };
+
+    meander(a, mid, depth - 1, rng, points);
+    meander(mid, b, depth - 1, rng, points);
+}
+
+fn rasterize(a: Point, b: Point, bank_width: f32, tiles: &mut [RiverTile]) {
+    let reach = a.width.max(b.width) / 2.0 + bank_width;
+    let min = (a.pos.min(b.pos) - reach).floor().max(Vec2::ZERO);
+    let max = (a.pos.max(b.pos) + reach)
+        .ceil()
+        .min(grid::DIMS.as_vec2() - 1.0);
+
+    if min.cmpgt(max).any() {
+        return;
+    }
+
+    let segment = b.pos - a.pos;
+    let length_squared = segment.length_squared().max(f32::EPSILON);
+
+    for y in min.y as u16..=max.y as u16 {
+        for x in min.x as u16..=max.x as u16 {
+            let tile = Vec2::new(x as f32, y as f32) + 0.5;
+            let t = ((tile - a.pos).dot(segment) / length_squared).clamp(0.0, 1.0);
+            let distance = tile.distance(a.pos + segment * t);
+            let half_width = a.width.lerp(b.width, t) / 2.0;
+
+            let kind = if distance <= half_width {
+                RiverTile::Water
+            } else if distance <= half_width + bank_width {
+                RiverTile::Bank
+            } else {
+                continue;
+            };
+
+            let idx = grid::to_index(x, y);
+            if kind > tiles[idx] {
+                tiles[idx] = kind;
+            }
+        }
+    }
+}
+
+/// Projects the atlas rivers crossing the given region into the map floor, clearing any walls on
+/// the way. Rivers get wider as more water flows through them. Meanders are seeded by the atlas,
+/// so neighbor maps draw the same river across their shared edge.
+pub(crate) fn project_rivers(
+    region: &AtlasRegion,
+    river: TileId,
+    river_bank: TileId,
+    map: &mut Map,
+) {
+    let atlas = region.atlas;
+    let config = &atlas.river;
+
+    let threshold = config.flow_threshold.max(1.0);
+    let max_flow = atlas
+        .rivers
+        .iter()
+        .flat_map(|r| r.path.iter())
+        .map(|p| atlas.flow[atlas::to_index(p.x, p.y)])
+        .fold(threshold, f32::max);
+
+    // Width grows logarithmically, otherwise only the river mouths would be wide.
+    let flow_range = (max_flow / threshold).ln();
+    let width = |flow: f32| {
+        let t = if flow_range > 0.0 {
+            (flow / threshold).ln() / flow_range
+        } else {
+            1.0
+        };
+        config.min_width.lerp(config.max_width, t.clamp(0.0, 1.0))
+    };
+
+    // Skip segments which can't reach this map, even after meandering.
+    let tiles_per_cell = grid::DIMS.x as f32 / atlas::MAP_RESOLUTION as f32;
+    let margin = tiles_per_cell * 2.0 + config.max_width + config.bank_width;
+    let bounds = Rect::from_corners(Vec2::splat(-margin), grid::DIMS.as_vec2() + margin);
+
+    let mut tiles = vec![RiverTile::None; grid::LAYER_SIZE];
+
+    for path in atlas.rivers.iter().map(|r| &r.path) {
+        for pair in path.windows(2) {
+            let [a, b] = [pair[0], pair[1]].map(|cell| Point {
+                pos: to_local(region, cell.as_uvec2()),
+                width: width(atlas.flow[atlas::to_index(cell.x, cell.y)]),
+            });
+
+            if !bounds.contains(a.pos) && !bounds.contains(b.pos) {
+                continue;
+            }
+
+            let cells = [pair[0].x, pair[0].y, pair[1].x, pair[1].y].map(u16::to_le_bytes);
+            let mut rng = Rng::new(rng::hash(atlas.seed, cells.as_flattened()));
+
+            let mut points = vec![a];
+            meander(a, b, MEANDER_DEPTH, &mut rng, &mut points);
+
+            for segment in points.windows(2) {
+                rasterize(segment[0], segment[1], config.bank_width, &mut tiles);
+            }
+        }
+    }
+
+    for (idx, kind) in tiles.into_iter().enumerate() {
+        let tile = match kind {
+            RiverTile::None => continue,
+            RiverTile::Bank => river_bank,
+            RiverTile::Water => river,
+        };
+
+        let x = (idx % grid::DIMS.x as usize) as u16;
+        let y = (idx / grid::DIMS.x as usize) as u16;
+        map.tile[LayerIndex::Floor].set(x, y, tile);
+        map.tile[LayerIndex::Wall].set(x, y, TileId::none());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::U16Vec2;
+    use eternal_config::erosion::RiverConfig;
+
+    use super::*;
+    use crate::atlas::{Atlas, River};
+
+    #[test]
+    fn project_river_crossing_map() {
+        // Arrange
+        let map_coord = U16Vec2::splat(10);
+        let first_cell = map_coord * atlas::MAP_RESOLUTION;
+        let mut atlas = Atlas {
+            river: RiverConfig {
+                flow_threshold: 1.0,
+                min_width: 3.0,
+                max_width: 3.0,
+                bank_width: 1.0,
+                ..default()
+            },
+            ..Atlas::new()
+        };
+        atlas.flow.fill(1.0);
+        atlas.rivers.push(River {
+            path: (0..atlas::MAP_RESOLUTION + 2)
+                .map(|x| U16Vec2::new(first_cell.x - 1 + x, first_cell.y + 1))
+                .collect(),
+        });
+        let region = AtlasRegion {
+            atlas: &atlas,
+            map: map_coord,
+        };
+        let (water, sand) = (TileId::new(0), TileId::new(1));
+        let mut map = Map::new("test".to_string(), 0);
+
+        // Act
+        project_rivers(&region, water, sand, &mut map);
+
+        // Assert
+        let floor = &map.tile[LayerIndex::Floor];
+        let water_count = floor.iter().filter(|&&t| t == water).count();
+        let sand_count = floor.iter().filter(|&&t| t == sand).count();
+        assert!(water_count >= grid::DIMS.x as usize * 2);
+        assert!(sand_count > 0);
+        // River goes from left to right edges
+        assert!((0..grid::DIMS.y as u16).any(|y| *floor.get(0, y) == water));
+        assert!((0..grid::DIMS.y as u16).any(|y| *floor.get(grid::DIMS.x as u16 - 1, y) == water));
+    }
+}
@@ -0,0 +1,122 @@
+pub use eternal_grid::hash::hash;
+use eternal_grid::hash::mix;
+
+/// Small deterministic random number generator (`SplitMix64`).
+///
+/// Procgen must produce the same world for the same seed on every platform and across dependency
+/// upgrades, so we don't rely on external RNG crates whose algorithms may change between versions.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates an independent RNG stream for a given generation pass, so adding or removing random
+    /// calls on one pass doesn't shift the results of the others.
+    pub fn from_stream(seed: u64, stream: &str) -> Self {
+        Self::new(hash(seed, stream.as_bytes()))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        mix(self.state)
+    }
+
+    /// Returns a value in the range `[0.0, 1.0)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Returns a value in the range `[min, max)`.
+    pub fn range_u32(&mut self, min: u32, max: u32) -> u32 {
+        if max <= min {
+            return min;
+        }
+
+        min + (self.next_u64() % (max - min) as u64) as u32
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Picks an item with a chance proportional to its weight. Items with non-positive weights
+    /// are never picked.
+    pub fn pick_weighted<'a, T>(
+        &mut self,
+        items: &'a [T],
+        weight: impl Fn(&T) -> f32,
+    ) -> Option<&'a T> {
+        let total = items.iter().map(|i| weight(i).max(0.0)).sum::<f32>();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut roll = self.next_f32() * total;
+        for item in items {
+            let w = weight(item).max(0.0);
+            if w > 0.0 && roll < w {
+                return Some(item);
+            }
+            roll -= w;
+        }
+
+        // Float rounding may leave a tiny remainder, so fallback to the last valid item.
+        items.iter().rev().find(|i| weight(i) > 0.0)
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.range_u32(0, i as u32 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        // Arrange
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+
+        // Act
+        let seq_a = (0..100).map(|_| a.next_u64()).collect::<Vec<_>>();
+        let seq_b = (0..100).map(|_| b.next_u64()).collect::<Vec<_>>();
+
+        // Assert
+        assert_eq!(seq_a, seq_b);
+        assert_ne!(
+            Rng::from_stream(42, "flora").next_u64(),
+            Rng::new(42).next_u64()
+        );
+    }
+
+    #[test]
+    fn pick_weighted_skips_zero_weights() {
+        // Arrange
+        let mut rng = Rng::new(7);
+        let items = [(0, 0.0), (1, 1.0), (2, 0.0)];
+
+        // Act
+        let picks = (0..100)
+            .filter_map(|_| rng.pick_weighted(&items, |(_, w)| *w))
+            .map(|(i, _)| *i)
+            .collect::<Vec<_>>();
+
+        // Assert
+        assert_eq!(picks.len(), 100);
+        assert!(picks.into_iter().all(|i| i == 1));
+    }
+}
@@ -0,0 +1,499 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{math::U16Vec2, prelude::*};
+use eternal_config::road::RoadConfig;
+use eternal_grid::{
+    ecs::TileRegistry,
+    grid::{self, LayerIndex},
+    tile::TileId,
+};
+
+use crate::{
+    atlas::{self, AtlasRegion},
+    biome::Biome,
+    map::{Map, Structure},
+    rng::{self, Rng},
+};
+
+/// Cost multiplier of tiles which already have a road, so roads merge instead of running side by
+/// side.
+const ROAD_REUSE_FACTOR: f32 = 0.5;
+
+/// Distance, in tiles, between map exits and map corners.
+const EXIT_MARGIN: u32 = 16;
+
+const DIRECTIONS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+/// Compiled version of [`RoadConfig`], with tile names resolved.
+#[derive(Default, Debug, Clone, Reflect)]
+pub struct Roads {
+    pub tile: TileId,
+    pub width: u8,
+    pub slope_cost: f32,
+    pub terrain_costs: Vec<(TileId, f32)>,
+    pub flora_cost: f32,
+    pub exit_chance: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RoadError {
+    #[error("Tile {0} not found")]
+    UnknownTile(String),
+}
+
+impl Roads {
+    pub fn from_config(
+        config: &RoadConfig,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, RoadError> {
+        let tile = |name: &str| {
+            tile_registry
+                .find_id_by_name(name)
+                .ok_or_else(|| RoadError::UnknownTile(name.to_string()))
+        };
+
+        Ok(Self {
+            tile: tile(&config.tile)?,
+            width: config.width.max(1),
+            slope_cost: config.slope_cost.max(0.0),
+            terrain_costs: config
+                .terrain_costs
+                .iter()
+                .map(|(name, cost)| Ok((tile(name)?, cost.max(0.0))))
+                .collect::<Result<_, _>>()?,
+            flora_cost: config.flora_cost.max(0.0),
+            exit_chance: config.exit_chance,
+        })
+    }
+}
+
+/// Exits on the map edges shared with neighbor maps. Each shared edge is seeded by the atlas and
+/// the map on its lower side, so both maps place the exit on the same tile.
+pub(crate) fn exits(region: &AtlasRegion, exit_chance: f32) -> Vec<U16Vec2> {
+    let map = region.map.as_ivec2();
+    let last = grid::DIMS.as_ivec2() - 1;
+
+    DIRECTIONS
+        .into_iter()
+        .filter_map(|dir| {
+            let neighbor = map + dir;
+            if neighbor.cmplt(IVec2::ZERO).any()
+                || neighbor.cmpge(IVec2::splat(atlas::MAP_COUNT as i32)).any()
+            {
+                return None;
+            }
+
+            let lower = map.min(neighbor).as_u16vec2();
+            let axis = if dir.x != 0 { 0u16 } else { 1 };
+            let bytes = [axis, lower.x, lower.y].map(u16::to_le_bytes);
+            let mut rng = Rng::new(rng::hash(region.atlas.seed, bytes.as_flattened()));
+
+            if !rng.chance(exit_chance) {
+                return None;
+            }
+
+            let length = if dir.x != 0 {
+                grid::DIMS.y
+            } else {
+                grid::DIMS.x
+            };
+            let offset = rng.range_u32(EXIT_MARGIN, length - EXIT_MARGIN) as i32;
+
+            let pos = match dir {
+                IVec2::X => IVec2::new(last.x, offset),
+                IVec2::NEG_X => IVec2::new(0, offset),
+                IVec2::Y => IVec2::new(offset, last.y),
+                _ => IVec2::new(offset, 0),
+            };
+
+            Some(pos.as_u16vec2())
+        })
+        .collect()
+}
+
+/// The structure entrance marker, or the tile around it closest to the map center.
+fn entrance(structure: &Structure) -> Option<U16Vec2> {
+    if structure.entrance.is_some() {
+        return structure.entrance;
+    }
+
+    let min = structure.bounds.min.as_ivec2() - 1;
+    let max = structure.bounds.max.as_ivec2();
+    let center = grid::DIMS.as_ivec2() / 2;
+
+    (min.y..=max.y)
+        .flat_map(|y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+        .filter(|p| p.x == min.x || p.x == max.x || p.y == min.y || p.y == max.y)
+        .filter(|p| p.cmpge(IVec2::ZERO).all() && p.cmplt(grid::DIMS.as_ivec2()).all())
+        .min_by_key(|p| (p.distance_squared(center), p.y, p.x))
+        .map(|p| p.as_u16vec2())
+}
+
+/// Cost of walking on each tile. Structures, terrain walls and unwalkable floors, like rivers,
+/// can't be crossed, so they cost [`f32::INFINITY`].
+fn tile_costs(roads: &Roads, floras: &[TileId], unwalkable: &[TileId], map: &Map) -> Vec<f32> {
+    let floor = &map.tile[LayerIndex::Floor];
+    let wall = &map.tile[LayerIndex::Wall];
+
+    (0..grid::LAYER_SIZE)
+        .map(|idx| {
+            let (x, y) = grid::from_index(idx);
+            let wall = wall[idx];
+
+            if (!wall.is_none() && !floras.contains(&wall))
+                || unwalkable.contains(&floor[idx])
+                || map
+                    .structures
+                    .iter()
+                    .any(|s| s.contains(UVec2::new(x as u32, y as u32)))
+            {
+                return f32::INFINITY;
+            }
+
+            let terrain = roads
+                .terrain_costs
+                .iter()
+                .find(|(tile, _)| *tile == floor[idx])
+                .map_or(1.0, |(_, cost)| *cost);
+
+            if wall.is_none() {
+                terrain
+            } else {
+                terrain + roads.flora_cost
+            }
+        })
+        .collect()
+}
+
+/// Cheapest path between two tiles, using A*. Both ends are allowed even if they can't be
+/// crossed, like entrances inside structures.
+fn find_path(
+    from: U16Vec2,
+    to: U16Vec2,
+    costs: &[f32],
+    slope_cost: f32,
+    map: &Map,
+) -> Option<Vec<U16Vec2>> {
+    let start = grid::to_index(from.x, from.y);
+    let goal = grid::to_index(to.x, to.y);
+    let min_cost = costs.iter().copied().fold(1.0, f32::min).max(0.0);
+    let heuristic = |pos: IVec2| (pos - to.as_ivec2()).abs().element_sum() as f32 * min_cost;
+
+    let mut best = vec![f32::INFINITY; costs.len()];
+    let mut came_from = vec![usize::MAX; costs.len()];
+    // Costs are never negative, so their bits are ordered the same way as their values.
+    let mut open = BinaryHeap::new();
+
+    best[start] = 0.0;
+    open.push(Reverse((heuristic(from.as_ivec2()).to_bits(), start)));
+
+    while let Some(Reverse((_, idx))) = open.pop() {
+        if idx == goal {
+            let mut path = vec![to];
+            let mut current = goal;
+            while current != start {
+                current = came_from[current];
+                let (x, y) = grid::from_index(current);
+                path.push(U16Vec2::new(x, y));
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let (x, y) = grid::from_index(idx);
+        let pos = IVec2::new(x as i32, y as i32);
+        let elevation = **map.elevation.get(x, y);
+
+        for dir in DIRECTIONS {
+            let next = pos + dir;
+            if next.cmplt(IVec2::ZERO).any() || next.cmpge(grid::DIMS.as_ivec2()).any() {
+                continue;
+            }
+
+            let (nx, ny) = (next.x as u16, next.y as u16);
+            let next_idx = grid::to_index(nx, ny);
+            let step = match costs[next_idx] {
+                cost if next_idx == goal && !cost.is_finite() => 1.0,
+                cost => cost,
+            };
+
+            if !step.is_finite() {
+                continue;
+            }
+
+            let climb = (**map.elevation.get(nx, ny) - elevation).abs();
+            let cost = best[idx] + step + climb * slope_cost;
+
+            if cost < best[next_idx] {
+                best[next_idx] = cost;
+                came_from[next_idx] = idx;
+                open.push(Reverse(((cost + heuristic(next)).to_bits(), next_idx)));
+            }
+        }
+    }
+
+    None
+}
+
+/// Pairs of points connecting all of them with the shortest total straight distance, using
+/// Prim's algorithm.
+fn spanning_tree(points: &[U16Vec2]) -> Vec<(usize, usize)> {
+    let mut connected = vec![false; points.len()];
+    let mut edges = vec![];
+
+    if points.is_empty() {
+        return edges;
+    }
+
+    connected[0] = true;
+    while edges.len() + 1 < points.len() {
+        let next = (0..points.len())
+            .filter(|&a| connected[a])
+            .flat_map(|a| {
+                (0..points.len())
+                    .filter(|&b| !connected[b])
+                    .map(move |b| (a, b))
+            })
+            .min_by_key(|&(a, b)| points[a].as_ivec2().distance_squared(points[b].as_ivec2()));
+
+        let Some((a, b)) = next else {
+            break;
+        };
+
+        connected[b] = true;
+        edges.push((a, b));
+    }
+
+    edges
+}
+
+/// Paints the road around the given tile. Unwalkable floors, terrain walls and structures are
+/// kept, while flora is cleared.
+fn paint(pos: U16Vec2, roads: &Roads, unwalkable: &[TileId], floras: &[TileId], map: &mut Map) {
+    let min = -((roads.width as i32 - 1) / 2);
+    let max = roads.width as i32 / 2;
+
+    for dy in min..=max {
+        for dx in min..=max {
+            let p = pos.as_ivec2() + IVec2::new(dx, dy);
+            if p.cmplt(IVec2::ZERO).any() || p.cmpge(grid::DIMS.as_ivec2()).any() {
+                continue;
+            }
+
+            let (x, y) = (p.x as u16, p.y as u16);
+            let wall = *map.tile[LayerIndex::Wall].get(x, y);
+            if (!wall.is_none() && !floras.contains(&wall))
+                || unwalkable.contains(map.tile[LayerIndex::Floor].get(x, y))
+                || map.structures.iter().any(|s| s.contains(p.as_uvec2()))
+            {
+                continue;
+            }
+
+            map.tile[LayerIndex::Wall].set(x, y, TileId::none());
+            map.tile[LayerIndex::Floor].set(x, y, roads.tile);
+        }
+    }
+}
+
+/// Connects structure entrances, the player spawn and map exits with roads. Points are connected
+/// by a spanning tree and each connection follows the cheapest path, preferring existing roads.
+pub(crate) fn place_roads(
+    roads: &Roads,
+    biome: &Biome,
+    region: Option<&AtlasRegion>,
+    map: &mut Map,
+) {
+    let mut points = map
+        .structures
+        .iter()
+        .filter_map(entrance)
+        .collect::<Vec<_>>();
+
+    points.extend(map.spawn);
+
+    if let Some(region) = region {
+        points.extend(exits(region, roads.exit_chance));
+    }
+
+    points.sort_by_key(|p| (p.y, p.x));
+    points.dedup();
+    if points.len() < 2 {
+        return;
+    }
+
+    let floras = biome
+        .flora_registry
+        .iter()
+        .map(|flora| flora.tile)
+        .collect::<Vec<_>>();
+    let unwalkable = &biome.terrain_pallet.unwalkable;
+    let mut costs = tile_costs(roads, &floras, unwalkable, map);
+
+    for (a, b) in spanning_tree(&points) {
+        let Some(path) = find_path(points[a], points[b], &costs, roads.slope_cost, map) else {
+            debug!("No road found from {} to {}", points[a], points[b]);
+            continue;
+        };
+
+        for pos in path {
+            let idx = grid::to_index(pos.x, pos.y);
+            if map.tile[LayerIndex::Floor][idx] != roads.tile {
+                costs[idx] *= ROAD_REUSE_FACTOR;
+            }
+            paint(pos, roads, unwalkable, &floras, map);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::URect;
+
+    use super::*;
+    use crate::{
+        atlas::Atlas,
+        biome::{Flora, FloraRegistry},
+    };
+
+    #[test]
+    fn neighbor_maps_share_exits() {
+        // Arrange
+        let atlas = Atlas::new();
+        let region = |x, y| AtlasRegion {
+            atlas: &atlas,
+            map: U16Vec2::new(x, y),
+        };
+
+        // Act
+        let left = exits(&region(10, 10), 1.0);
+        let right = exits(&region(11, 10), 1.0);
+        let corner = exits(&region(0, 0), 1.0);
+
+        // Assert
+        let last = grid::DIMS.x as u16 - 1;
+        let east = left.iter().find(|p| p.x == last).unwrap();
+        let west = right.iter().find(|p| p.x == 0).unwrap();
+        assert_eq!(east.y, west.y);
+        assert_eq!(left.len(), 4);
+        assert_eq!(corner.len(), 2);
+        assert!(exits(&region(10, 10), 0.0).is_empty());
+    }
+
+    #[test]
+    fn negative_costs_are_clamped() {
+        // Arrange
+        let config = RoadConfig {
+            tile: "NONE".to_string(),
+            slope_cost: -2.0,
+            terrain_costs: vec![("NONE".to_string(), -1.0)],
+            flora_cost: -3.0,
+            ..default()
+        };
+
+        // Act
+        let roads = Roads::from_config(&config, &TileRegistry::default()).unwrap();
+
+        // Assert
+        assert_eq!(roads.slope_cost, 0.0);
+        assert_eq!(roads.terrain_costs, vec![(TileId::none(), 0.0)]);
+        assert_eq!(roads.flora_cost, 0.0);
+    }
+
+    #[test]
+    fn roads_connect_structures() {
+        // Arrange
+        let grass = TileId::new(0);
+        let stone = TileId::new(1);
+        let tree = TileId::new(2);
+        let dirt = TileId::new(3);
+        let mut map = Map::new("test".to_string(), 0);
+        map.tile[LayerIndex::Floor].fill(grass);
+        // A mountain splitting the map, with a single pass.
+        for y in 0..grid::DIMS.y as u16 {
+            if !(100..103).contains(&y) {
+                map.tile[LayerIndex::Wall].set(128, y, stone);
+            }
+        }
+        // A forest column every road must cross.
+        for y in 0..grid::DIMS.y as u16 {
+            map.tile[LayerIndex::Wall].set(60, y, tree);
+        }
+        let hut = |x: u32| Structure {
+            name: "hut".to_string(),
+            bounds: URect::new(x, 100, x + 4, 104),
+            entrance: Some(U16Vec2::new(x as u16, 101)),
+            ..default()
+        };
+        map.structures = vec![hut(40), hut(200)];
+        let biome = Biome {
+            flora_registry: FloraRegistry::new(vec![Flora {
+                tile: tree,
+                ..default()
+            }]),
+            ..default()
+        };
+        let roads = Roads {
+            tile: dirt,
+            width: 1,
+            ..default()
+        };
+
+        // Act
+        place_roads(&roads, &biome, None, &mut map);
+
+        // Assert
+        let floor = &map.tile[LayerIndex::Floor];
+        let wall = &map.tile[LayerIndex::Wall];
+        let road = |x: u16, y: u16| floor.get(x, y) == &dirt && wall.get(x, y).is_none();
+        assert!((100..103).any(|y| road(128, y)));
+        assert!((0..grid::DIMS.y as u16).any(|y| road(60, y)));
+        assert!(
+            (0..grid::DIMS.y as u16).all(|y| (100..103).contains(&y) || wall.get(128, y) == &stone)
+        );
+        // Structures aren't painted over.
+        assert_eq!(floor.get(40, 101), &grass);
+        assert_eq!(floor.get(39, 101), &dirt);
+    }
+
+    #[test]
+    fn roads_avoid_rivers() {
+        // Arrange
+        let grass = TileId::new(0);
+        let water = TileId::new(1);
+        let dirt = TileId::new(2);
+        let mut map = Map::new("test".to_string(), 0);
+        map.tile[LayerIndex::Floor].fill(grass);
+        // A river splitting the map, with a single land bridge.
+        for y in 0..grid::DIMS.y as u16 {
+            if !(200..203).contains(&y) {
+                map.tile[LayerIndex::Floor].set(128, y, water);
+            }
+        }
+        let hut = |x: u32| Structure {
+            name: "hut".to_string(),
+            bounds: URect::new(x, 100, x + 4, 104),
+            entrance: Some(U16Vec2::new(x as u16, 101)),
+            ..default()
+        };
+        map.structures = vec![hut(40), hut(200)];
+        let mut biome = Biome::default();
+        biome.terrain_pallet.unwalkable = vec![water];
+        let roads = Roads {
+            tile: dirt,
+            width: 1,
+            ..default()
+        };
+
+        // Act
+        place_roads(&roads, &biome, None, &mut map);
+
+        // Assert
+        let floor = &map.tile[LayerIndex::Floor];
+        // The road goes around, using the bridge, instead of leaving a gap on the river.
+        assert!((200..203).any(|y| floor.get(128, y) == &dirt));
+        assert!(
+            (0..grid::DIMS.y as u16)
+                .all(|y| (200..203).contains(&y) || floor.get(128, y) == &water)
+        );
+    }
+}
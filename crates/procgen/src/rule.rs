@@ -0,0 +1,368 @@
+use bevy::{math::U16Vec2, platform::collections::HashMap, prelude::*};
+use eternal_config::rule::PlacementRuleConfig;
+use eternal_grid::{
+    ecs::TileRegistry,
+    grid::{self, LayerIndex},
+    tile::TileId,
+};
+
+use crate::{map::Map, noise::NoiseStack};
+
+/// Compiled version of [`PlacementRuleConfig`], with tile names resolved to [`TileId`].
+#[derive(Debug, Clone, Reflect)]
+#[reflect(no_field_bounds)]
+pub enum PlacementRule {
+    All(Vec<PlacementRule>),
+    Any(Vec<PlacementRule>),
+    Not(Vec<PlacementRule>),
+    Noise {
+        min: f32,
+        max: f32,
+    },
+    Elevation {
+        min: f32,
+        max: f32,
+    },
+    Slope {
+        min: f32,
+        max: f32,
+    },
+    DistanceTo {
+        tile: TileId,
+        min: u8,
+        max: u8,
+    },
+    Neighbors {
+        tile: TileId,
+        radius: u8,
+        min: u8,
+        max: u8,
+    },
+    EdgeDistance {
+        min: u16,
+        max: u16,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PlacementRuleError {
+    #[error("Tile {0} not found")]
+    UnknownTile(String),
+}
+
+impl Default for PlacementRule {
+    fn default() -> Self {
+        Self::All(vec![])
+    }
+}
+
+impl PlacementRule {
+    pub fn from_config(
+        config: &PlacementRuleConfig,
+        tile_registry: &TileRegistry,
+    ) -> Result<Self, PlacementRuleError> {
+        let compile = |rules: &[PlacementRuleConfig]| {
+            rules
+                .iter()
+                .map(|r| Self::from_config(r, tile_registry))
+                .collect::<Result<_, _>>()
+        };
+        let tile = |name: &str| {
+            tile_registry
+                .find_id_by_name(name)
+                .ok_or_else(|| PlacementRuleError::UnknownTile(name.to_string()))
+        };
+
+        Ok(match config {
+            PlacementRuleConfig::All(rules) => Self::All(compile(rules)?),
+            PlacementRuleConfig::Any(rules) => Self::Any(compile(rules)?),
+            PlacementRuleConfig::Not(rules) => Self::Not(compile(rules)?),
+            &PlacementRuleConfig::Noise { min, max } => Self::Noise { min, max },
+            &PlacementRuleConfig::Elevation { min, max } => Self::Elevation { min, max },
+            &PlacementRuleConfig::Slope { min, max } => Self::Slope { min, max },
+            PlacementRuleConfig::DistanceTo {
+                tile: name,
+                min,
+                max,
+            } => Self::DistanceTo {
+                tile: tile(name)?,
+                min: *min,
+                max: *max,
+            },
+            PlacementRuleConfig::Neighbors {
+                tile: name,
+                radius,
+                min,
+                max,
+            } => Self::Neighbors {
+                tile: tile(name)?,
+                radius: *radius,
+                min: *min,
+                max: *max,
+            },
+            &PlacementRuleConfig::EdgeDistance { min, max } => Self::EdgeDistance { min, max },
+        })
+    }
+
+    /// Checks if this rule passes on the given tile position.
+    pub fn check(&self, x: u16, y: u16, ctx: &RuleContext) -> bool {
+        match self {
+            PlacementRule::All(rules) => rules.iter().all(|r| r.check(x, y, ctx)),
+            PlacementRule::Any(rules) => rules.iter().any(|r| r.check(x, y, ctx)),
+            PlacementRule::Not(rules) => !rules.iter().any(|r| r.check(x, y, ctx)),
+            &PlacementRule::Noise { min, max } => {
+                (min..=max).contains(&ctx.noise.get(x as f32, y as f32))
+            }
+            &PlacementRule::Elevation { min, max } => {
+                (min..=max).contains(&**ctx.map.elevation.get(x, y))
+            }
+            &PlacementRule::Slope { min, max } => (min..=max).contains(&slope(x, y, ctx.map)),
+            PlacementRule::DistanceTo { tile, min, max } => {
+                let distance = ctx
+                    .distances
+                    .get(tile)
+                    .map(|field| field[grid::to_index(x, y)])
+                    .unwrap_or(f32::INFINITY);
+                (*min as f32..=*max as f32).contains(&distance)
+            }
+            PlacementRule::Neighbors {
+                tile,
+                radius,
+                min,
+                max,
+            } => {
+                let count = grid::SampleShape::Square(*radius)
+                    .range(U16Vec2::new(x, y))
+                    .into_iter()
+                    .filter(|p| (p.x, p.y) != (x, y) && has_tile(ctx.map, p.x, p.y, *tile))
+                    .count();
+                (*min as usize..=*max as usize).contains(&count)
+            }
+            &PlacementRule::EdgeDistance { min, max } => {
+                let distance = x
+                    .min(y)
+                    .min(grid::DIMS.x as u16 - 1 - x)
+                    .min(grid::DIMS.y as u16 - 1 - y);
+                (min..=max).contains(&distance)
+            }
+        }
+    }
+
+    /// Tiles which needs a distance field to be checked.
+    fn distance_tiles(&self, tiles: &mut Vec<TileId>) {
+        match self {
+            PlacementRule::All(rules) | PlacementRule::Any(rules) | PlacementRule::Not(rules) => {
+                rules.iter().for_each(|r| r.distance_tiles(tiles));
+            }
+            PlacementRule::DistanceTo { tile, .. } if !tiles.contains(tile) => {
+                tiles.push(*tile);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Distance, in tiles, from each tile position to the nearest tile of a given type.
+pub type DistanceFields = HashMap<TileId, Vec<f32>>;
+
+/// Data needed to evaluate a [`PlacementRule`].
+pub struct RuleContext<'a> {
+    pub map: &'a Map,
+    pub noise: &'a NoiseStack,
+    pub distances: &'a DistanceFields,
+}
+
+/// Computes the distance fields needed by the given rules. Fields are computed once, so tiles
+/// placed afterwards aren't taken into account.
+pub fn distance_fields<'a>(
+    rules: impl IntoIterator<Item = &'a PlacementRule>,
+    map: &Map,
+) -> DistanceFields {
+    let mut tiles = vec![];
+    rules.into_iter().for_each(|r| r.distance_tiles(&mut tiles));
+
+    tiles
+        .into_iter()
+        .map(|tile| (tile, distance_field(map, tile)))
+        .collect()
+}
+
+fn has_tile(map: &Map, x: u16, y: u16, tile: TileId) -> bool {
+    *map.tile[LayerIndex::Floor].get(x, y) == tile || *map.tile[LayerIndex::Wall].get(x, y) == tile
+}
+
+/// Elevation gradient magnitude, using central differences.
+pub(crate) fn slope(x: u16, y: u16, map: &Map) -> f32 {
+    let elevation = |x: u16, y: u16| **map.elevation.get(x, y);
+    let (max_x, max_y) = (grid::DIMS.x as u16 - 1, grid::DIMS.y as u16 - 1);
+
+    let dx = elevation((x + 1).min(max_x), y) - elevation(x.saturating_sub(1), y);
+    let dy = elevation(x, (y + 1).min(max_y)) - elevation(x, y.saturating_sub(1));
+
+    Vec2::new(dx, dy).length() / 2.0
+}
+
+/// Two-pass chamfer distance transform, which approximates the euclidean distance.
+fn distance_field(map: &Map, tile: TileId) -> Vec<f32> {
+    const DIAGONAL: f32 = std::f32::consts::SQRT_2;
+    const FORWARD: [(i32, i32, f32); 4] = [
+        (-1, 0, 1.0),
+        (-1, -1, DIAGONAL),
+        (0, -1, 1.0),
+        (1, -1, DIAGONAL),
+    ];
+    const BACKWARD: [(i32, i32, f32); 4] = [
+        (1, 0, 1.0),
+        (1, 1, DIAGONAL),
+        (0, 1, 1.0),
+        (-1, 1, DIAGONAL),
+    ];
+
+    let (width, height) = (grid::DIMS.x as i32, grid::DIMS.y as i32);
+
+    let mut field = (0..grid::LAYER_SIZE)
+        .map(|i| {
+            let (x, y) = ((i as i32 % width) as u16, (i as i32 / width) as u16);
+            if has_tile(map, x, y, tile) {
+                0.0
+            } else {
+                f32::INFINITY
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut relax = |x: i32, y: i32, kernel: &[(i32, i32, f32)]| {
+        let idx = (y * width + x) as usize;
+        for &(dx, dy, cost) in kernel {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                continue;
+            }
+            let candidate = field[(ny * width + nx) as usize] + cost;
+            if candidate < field[idx] {
+                field[idx] = candidate;
+            }
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            relax(x, y, &FORWARD);
+        }
+    }
+
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            relax(x, y, &BACKWARD);
+        }
+    }
+
+    field
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_with_water(water: TileId, at: (u16, u16)) -> Map {
+        let mut map = Map::new("test".to_string(), 0);
+        map.tile[LayerIndex::Floor].set(at.0, at.1, water);
+        map
+    }
+
+    #[test]
+    fn distance_to_tile() {
+        // Arrange
+        let water = TileId::new(1);
+        let map = map_with_water(water, (10, 10));
+        let rule = PlacementRule::DistanceTo {
+            tile: water,
+            min: 0,
+            max: 2,
+        };
+        let distances = distance_fields([&rule], &map);
+        let noise = NoiseStack::default();
+        let ctx = RuleContext {
+            map: &map,
+            noise: &noise,
+            distances: &distances,
+        };
+
+        // Act & Assert
+        assert!(rule.check(10, 10, &ctx));
+        assert!(rule.check(12, 10, &ctx));
+        assert!(rule.check(11, 11, &ctx));
+        assert!(!rule.check(13, 10, &ctx));
+        assert!(!rule.check(12, 12, &ctx));
+    }
+
+    #[test]
+    fn combine_rules() {
+        // Arrange
+        let water = TileId::new(1);
+        let map = map_with_water(water, (0, 0));
+        let near_water = PlacementRule::Neighbors {
+            tile: water,
+            radius: 1,
+            min: 1,
+            max: 8,
+        };
+        let rule = PlacementRule::All(vec![
+            near_water,
+            PlacementRule::Not(vec![PlacementRule::EdgeDistance { min: 0, max: 0 }]),
+        ]);
+        let distances = distance_fields([&rule], &map);
+        let noise = NoiseStack::default();
+        let ctx = RuleContext {
+            map: &map,
+            noise: &noise,
+            distances: &distances,
+        };
+
+        // Act & Assert
+        assert!(rule.check(1, 1, &ctx));
+        assert!(!rule.check(1, 0, &ctx));
+        assert!(!rule.check(0, 0, &ctx));
+        assert!(!rule.check(2, 2, &ctx));
+        assert!(PlacementRule::default().check(2, 2, &ctx));
+        assert!(!PlacementRule::Any(vec![]).check(2, 2, &ctx));
+    }
+
+    #[test]
+    fn unknown_rule_tile() {
+        // Arrange
+        let tile_registry = TileRegistry::new(
+            [(
+                TileId::new(0),
+                eternal_grid::tile::TileInfo {
+                    name: "WATER".into(),
+                    ..default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let config = PlacementRuleConfig::Not(vec![
+            PlacementRuleConfig::DistanceTo {
+                tile: "WATER".to_string(),
+                min: 0,
+                max: 2,
+            },
+            PlacementRuleConfig::Neighbors {
+                tile: "WATRE".to_string(),
+                radius: 1,
+                min: 1,
+                max: 8,
+            },
+        ]);
+
+        // Act
+        let result = PlacementRule::from_config(&config, &tile_registry);
+
+        // Assert
+        assert_eq!(
+            result.unwrap_err(),
+            PlacementRuleError::UnknownTile("WATRE".to_string())
+        );
+    }
+}
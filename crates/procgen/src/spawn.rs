@@ -0,0 +1,155 @@
+use bevy::{math::U16Vec2, prelude::*};
+use eternal_grid::{
+    grid::{self, LayerIndex},
+    tile::TileId,
+};
+
+use crate::{map::Map, validation};
+
+/// Picks where the player spawns, inside the largest walkable region of the map. Tiles with a
+/// wall or an `unwalkable` floor, like water, are never picked.
+///
+/// Spawn markers of stamped prefabs are used first, in the order structures were placed. Then
+/// the tile closest to the map center with `clearance` free tiles around it is picked, preferring
+/// the `preferred` floor tiles, if any of them is available.
+pub fn select_spawn(
+    map: &Map,
+    preferred: &[TileId],
+    unwalkable: &[TileId],
+    clearance: u8,
+) -> Option<U16Vec2> {
+    let walkable = validation::walkable_tiles(map, unwalkable);
+    let region = validation::largest_region(&walkable);
+    find_spawn(map, &walkable, &region, preferred, clearance)
+}
+
+/// Same as [`select_spawn`], reusing an already computed largest region.
+pub(crate) fn find_spawn(
+    map: &Map,
+    walkable: &[bool],
+    region: &[usize],
+    preferred: &[TileId],
+    clearance: u8,
+) -> Option<U16Vec2> {
+    let mut reachable = vec![false; walkable.len()];
+    region.iter().for_each(|&idx| reachable[idx] = true);
+
+    let marker = map
+        .structures
+        .iter()
+        .filter_map(|structure| structure.spawn)
+        .find(|pos| {
+            pos.as_uvec2().cmplt(grid::DIMS).all() && reachable[grid::to_index(pos.x, pos.y)]
+        });
+    if marker.is_some() {
+        return marker;
+    }
+
+    let clearance = clearance as i32;
+    let center = grid::DIMS.as_ivec2() / 2;
+    let floor = &map.tile[LayerIndex::Floor];
+
+    let cleared = region
+        .iter()
+        .map(|&idx| {
+            IVec2::new(
+                (idx % grid::DIMS.x as usize) as i32,
+                (idx / grid::DIMS.x as usize) as i32,
+            )
+        })
+        .filter(|pos| {
+            (-clearance..=clearance).all(|dy| {
+                (-clearance..=clearance).all(|dx| {
+                    let p = pos + IVec2::new(dx, dy);
+                    p.cmpge(IVec2::ZERO).all()
+                        && p.cmplt(grid::DIMS.as_ivec2()).all()
+                        && walkable[grid::to_index(p.x as u16, p.y as u16)]
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let distance = |pos: &&IVec2| (pos.distance_squared(center), pos.y, pos.x);
+
+    cleared
+        .iter()
+        .filter(|pos| preferred.contains(floor.get(pos.x as u16, pos.y as u16)))
+        .min_by_key(distance)
+        .or_else(|| cleared.iter().min_by_key(distance))
+        .map(|pos| pos.as_u16vec2())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::URect;
+
+    use super::*;
+    use crate::map::Structure;
+
+    #[test]
+    fn spawn_selection() {
+        // Arrange
+        let wall = TileId::new(1);
+        let grass = TileId::new(2);
+        let mut map = Map::new("test".to_string(), 0);
+        // A wall splits the map in a left region of 100 columns and a right one of 155.
+        for y in 0..grid::DIMS.y as u16 {
+            map.tile[LayerIndex::Wall].set(100, y, wall);
+        }
+        map.tile[LayerIndex::Floor].set(200, 50, grass);
+        map.tile[LayerIndex::Floor].set(10, 128, grass);
+        let marker = |x, y| Structure {
+            name: "camp".to_string(),
+            prefab: "CAMP".to_string(),
+            bounds: URect::new(x, y, x + 1, y + 1),
+            spawn: Some(U16Vec2::new(x as u16, y as u16)),
+            entrance: None,
+        };
+
+        // Act
+        let spawn = select_spawn(&map, &[], &[], 1);
+
+        // Assert
+        assert_eq!(spawn, Some(U16Vec2::new(128, 128)));
+
+        // Act
+        let spawn = select_spawn(&map, &[grass], &[], 1);
+
+        // Assert
+        // Preferred tiles outside of the largest region are ignored
+        assert_eq!(spawn, Some(U16Vec2::new(200, 50)));
+
+        // Arrange
+        map.structures = vec![marker(10, 10), marker(150, 150)];
+
+        // Act
+        let spawn = select_spawn(&map, &[grass], &[], 1);
+
+        // Assert
+        assert_eq!(spawn, Some(U16Vec2::new(150, 150)));
+    }
+
+    #[test]
+    fn spawn_never_on_water() {
+        // Arrange
+        let water = TileId::new(1);
+        let grass = TileId::new(2);
+        let dirt = TileId::new(3);
+        let mut map = Map::new("test".to_string(), 0);
+        // A lake covering the whole map, but a small dirt island.
+        map.tile[LayerIndex::Floor].fill(water);
+        for y in 20..30 {
+            for x in 20..30 {
+                map.tile[LayerIndex::Floor].set(x, y, dirt);
+            }
+        }
+
+        // Act
+        let spawn = select_spawn(&map, &[grass], &[water], 1);
+
+        // Assert
+        // None of the preferred tiles exist, but the fallback still avoids water.
+        assert_eq!(spawn, Some(U16Vec2::new(28, 28)));
+        assert_eq!(select_spawn(&map, &[grass], &[water, dirt], 1), None);
+    }
+}
@@ -0,0 +1,197 @@
+use bevy::{math::U16Vec2, prelude::*};
+use eternal_grid::grid::{self, LayerIndex};
+
+use crate::{
+    biome::{Biome, StructureSpawn},
+    map::{Map, Structure},
+    prefab::{Prefab, PrefabRegistry},
+    rng::Rng,
+    rule::{self, RuleContext},
+};
+
+/// How many random sites are tried for each structure or vein which should be placed.
+pub(crate) const PLACEMENT_ATTEMPTS: u32 = 50;
+
+/// Checks if the given site is flat and clear enough, and passes the structure rule.
+fn can_place(spawn: &StructureSpawn, bounds: URect, ctx: &RuleContext) -> bool {
+    let center = bounds.center();
+    if !spawn.rule.check(center.x as u16, center.y as u16, ctx) {
+        return false;
+    }
+
+    let (mut min, mut max) = (f32::MAX, f32::MIN);
+    for y in bounds.min.y..bounds.max.y {
+        for x in bounds.min.x..bounds.max.x {
+            let elevation = **ctx.map.elevation.get(x as u16, y as u16);
+            min = min.min(elevation);
+            max = max.max(elevation);
+        }
+    }
+
+    if max - min > spawn.flatness {
+        return false;
+    }
+
+    let clearance = URect::from_corners(
+        bounds
+            .min
+            .saturating_sub(UVec2::splat(spawn.clearance as u32)),
+        (bounds.max + spawn.clearance as u32).min(grid::DIMS),
+    );
+
+    if ctx
+        .map
+        .structures
+        .iter()
+        .any(|s| !s.bounds.intersect(clearance).is_empty())
+    {
+        return false;
+    }
+
+    let walls = &ctx.map.tile[LayerIndex::Wall];
+    (clearance.min.y..clearance.max.y).all(|y| {
+        (clearance.min.x..clearance.max.x).all(|x| walls.get(x as u16, y as u16).is_none())
+    })
+}
+
+fn place_structure(
+    spawn: &StructureSpawn,
+    prefab: &Prefab,
+    biome: &Biome,
+    map: &mut Map,
+    distances: &rule::DistanceFields,
+    rng: &mut Rng,
+) {
+    let size = prefab.size.as_uvec2();
+    if size.cmpeq(UVec2::ZERO).any() || size.cmpgt(grid::DIMS).any() {
+        warn!("Prefab {} has an invalid size {size}", prefab.name);
+        return;
+    }
+
+    let (min, max) = spawn.count;
+    let count = rng.range_u32(min, max.saturating_add(1));
+    let max_origin = grid::DIMS - size;
+
+    let mut placed = 0;
+    for _ in 0..count.saturating_mul(PLACEMENT_ATTEMPTS) {
+        if placed >= count {
+            break;
+        }
+
+        let origin = UVec2::new(
+            rng.range_u32(0, max_origin.x + 1),
+            rng.range_u32(0, max_origin.y + 1),
+        );
+        let bounds = URect::from_corners(origin, origin + size);
+
+        let ctx = RuleContext {
+            map,
+            noise: &biome.flora_noise,
+            distances,
+        };
+
+        if !can_place(spawn, bounds, &ctx) {
+            continue;
+        }
+
+        let origin = U16Vec2::new(origin.x as u16, origin.y as u16);
+        prefab.stamp(origin, map, rng);
+        map.structures.push(Structure {
+            name: spawn.name.clone(),
+            prefab: prefab.name.clone(),
+            bounds,
+            spawn: prefab.spawn.map(|offset| origin + offset),
+            entrance: prefab.entrance.map(|offset| origin + offset),
+        });
+
+        placed += 1;
+    }
+
+    if placed < min {
+        debug!(
+            "Only {placed} of {min} {} structures could be placed",
+            spawn.name
+        );
+    }
+}
+
+/// Stamps the biome structures into the map, picking random sites which are flat and clear
+/// enough. Placed structures are recorded on [`Map::structures`].
+pub(crate) fn place_structures(
+    biome: &Biome,
+    prefabs: &PrefabRegistry,
+    map: &mut Map,
+    rng: &mut Rng,
+) {
+    let distances = rule::distance_fields(biome.structures.iter().map(|s| &s.rule), map);
+
+    for spawn in &biome.structures {
+        let Some(prefab) = prefabs.get(&spawn.prefab) else {
+            warn!(
+                "Prefab {} not found for structure {}",
+                spawn.prefab, spawn.name
+            );
+            continue;
+        };
+
+        place_structure(spawn, prefab, biome, map, &distances, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eternal_config::prefab::{PrefabCellConfig, PrefabConfig};
+    use eternal_grid::{
+        ecs::TileRegistry,
+        tile::{TileId, TileInfo},
+    };
+
+    use super::*;
+
+    #[test]
+    fn structures_do_not_overlap() {
+        // Arrange
+        let config = PrefabConfig {
+            name: "HUT".to_string(),
+            floor: vec!["dddd".to_string(); 4],
+            legend: vec![('d', PrefabCellConfig::Tile("DIRT".to_string()))],
+            ..default()
+        };
+        let tiles = TileRegistry::new(
+            [(
+                TileId::new(1),
+                TileInfo {
+                    name: "DIRT".into(),
+                    ..default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let prefabs = PrefabRegistry::new(vec![Prefab::from_config(&config, &tiles).unwrap()]);
+        let biome = Biome {
+            structures: vec![StructureSpawn {
+                name: "hut".to_string(),
+                prefab: "HUT".to_string(),
+                count: (10, 10),
+                flatness: 0.0,
+                clearance: 1,
+                ..default()
+            }],
+            ..default()
+        };
+        let mut map = Map::new("test".to_string(), 0);
+
+        // Act
+        place_structures(&biome, &prefabs, &mut map, &mut Rng::new(0));
+
+        // Assert
+        assert_eq!(map.structures.len(), 10);
+        for (i, a) in map.structures.iter().enumerate() {
+            assert_eq!(a.bounds.size(), UVec2::splat(4));
+            for b in map.structures.iter().skip(i + 1) {
+                assert!(a.bounds.intersect(b.bounds).is_empty());
+            }
+        }
+    }
+}
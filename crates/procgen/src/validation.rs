@@ -0,0 +1,361 @@
+use std::collections::VecDeque;
+
+use bevy::{math::U16Vec2, platform::collections::HashMap, prelude::*};
+use eternal_config::{
+    server::{ConfigAssetUpdated, ConfigServer, Configs},
+    validation::{MapValidationConfig, MapValidationRegistryConfig},
+};
+use eternal_grid::{
+    grid::{self, LayerIndex},
+    tile::TileId,
+};
+
+use crate::{biome::Biome, map::Map, spawn};
+
+/// Number of buckets of [`MapReport::elevation_histogram`].
+pub const ELEVATION_BINS: usize = 10;
+
+pub(crate) struct ValidationPlugin;
+
+impl Plugin for ValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapValidation>()
+            .add_systems(Startup, setup);
+    }
+}
+
+/// Invariants checked on generated maps.
+#[derive(Default, Debug, Clone, Resource)]
+pub struct MapValidation(pub MapValidationRegistryConfig);
+
+impl MapValidation {
+    /// Validation of the given map generator.
+    pub fn get(&self, generator: &str) -> &MapValidationConfig {
+        self.0
+            .generators
+            .iter()
+            .find_map(|(name, config)| (name == generator).then_some(config))
+            .unwrap_or(&self.0.default)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum MapIssue {
+    #[error("Largest walkable region covers {:.1}% of the map, less than {:.1}%", area * 100.0, min * 100.0)]
+    SmallWalkableArea { area: f32, min: f32 },
+    #[error("{0} flora placed on disallowed terrain")]
+    MisplacedFlora(usize),
+    #[error("No valid spawn found")]
+    NoSpawn,
+}
+
+/// Statistics of a generated map and the invariants it breaks, if any.
+#[derive(Default, Debug, Clone, Resource)]
+pub struct MapReport {
+    /// Seed which generated the map. It differs from the requested one when the map was retried,
+    /// while the atlas seed is kept.
+    pub seed: u64,
+    pub attempts: u32,
+    /// How many times each floor tile is used, from the most used to the least one.
+    pub floor_tiles: Vec<(TileId, usize)>,
+    /// How many times each wall tile is used, from the most used to the least one.
+    pub wall_tiles: Vec<(TileId, usize)>,
+    /// How many floras of each species were placed.
+    pub flora: Vec<(String, usize)>,
+    pub elevation_range: (f32, f32),
+    /// Tile count on each elevation bucket, evenly split across the elevation range.
+    pub elevation_histogram: [usize; ELEVATION_BINS],
+    /// Fraction of the map covered by the largest walkable region.
+    pub walkable_area: f32,
+    pub spawn: Option<U16Vec2>,
+    pub issues: Vec<MapIssue>,
+}
+
+impl MapReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+fn tile_usage(tiles: &[TileId]) -> Vec<(TileId, usize)> {
+    let mut usage = tiles
+        .iter()
+        .fold(HashMap::new(), |mut usage, &tile| {
+            *usage.entry(tile).or_insert(0) += 1;
+            usage
+        })
+        .into_iter()
+        .collect::<Vec<_>>();
+    usage.sort_unstable_by_key(|&(tile, count)| (std::cmp::Reverse(count), *tile));
+    usage
+}
+
+fn elevation_histogram(map: &Map) -> ((f32, f32), [usize; ELEVATION_BINS]) {
+    let (min, max) = map
+        .elevation
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), e| {
+            (min.min(**e), max.max(**e))
+        });
+    let range = (max - min).max(f32::EPSILON);
+
+    let mut histogram = [0; ELEVATION_BINS];
+    for e in map.elevation.iter() {
+        let bin = ((**e - min) / range * ELEVATION_BINS as f32) as usize;
+        histogram[bin.min(ELEVATION_BINS - 1)] += 1;
+    }
+
+    ((min, max), histogram)
+}
+
+/// Whether each tile of the map has no wall and a floor which can be walked on.
+pub(crate) fn walkable_tiles(map: &Map, unwalkable: &[TileId]) -> Vec<bool> {
+    map.tile[LayerIndex::Wall]
+        .iter()
+        .zip(map.tile[LayerIndex::Floor].iter())
+        .map(|(wall, floor)| wall.is_none() && !unwalkable.contains(floor))
+        .collect()
+}
+
+/// Indices of the largest 4-connected region of open tiles.
+pub(crate) fn largest_region(open: &[bool]) -> Vec<usize> {
+    let mut visited = vec![false; open.len()];
+    let mut largest = vec![];
+
+    for start in 0..open.len() {
+        if !open[start] || visited[start] {
+            continue;
+        }
+
+        let mut region = vec![];
+        let mut queue = VecDeque::from([start]);
+        visited[start] = true;
+
+        while let Some(idx) = queue.pop_front() {
+            region.push(idx);
+            let x = (idx % grid::DIMS.x as usize) as i32;
+            let y = (idx / grid::DIMS.x as usize) as i32;
+
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= grid::DIMS.x as i32 || ny >= grid::DIMS.y as i32 {
+                    continue;
+                }
+
+                let neighbor = grid::to_index(nx as u16, ny as u16);
+                if open[neighbor] && !visited[neighbor] {
+                    visited[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        if region.len() > largest.len() {
+            largest = region;
+        }
+    }
+
+    largest
+}
+
+/// Counts placed floras per species and how many of them are on terrains they aren't allowed.
+fn analyze_flora(map: &Map, biome: &Biome) -> (Vec<(String, usize)>, usize) {
+    let floor = &map.tile[LayerIndex::Floor];
+    let wall = &map.tile[LayerIndex::Wall];
+
+    let mut misplaced = 0;
+    let flora = biome
+        .flora_registry
+        .iter()
+        .map(|flora| {
+            let placed = wall
+                .iter()
+                .enumerate()
+                .filter(|&(_, &tile)| tile == flora.tile)
+                .inspect(|&(idx, _)| {
+                    if !flora.allowed_terrains.is_empty()
+                        && !flora.allowed_terrains.contains(&floor[idx])
+                    {
+                        misplaced += 1;
+                    }
+                })
+                .count();
+            (flora.name.clone(), placed)
+        })
+        .collect();
+
+    (flora, misplaced)
+}
+
+/// Collects the map statistics and checks it against the validation invariants. Flora is only
+/// checked, unwalkable floors excluded and spawn tiles preferred when the map biome is given. The
+/// map spawn is kept if it's inside the largest walkable region, otherwise another one is selected.
+pub fn analyze(map: &Map, biome: Option<&Biome>, validation: &MapValidationConfig) -> MapReport {
+    let unwalkable = biome
+        .map(|biome| biome.terrain_pallet.unwalkable.as_slice())
+        .unwrap_or_default();
+    let walkable = walkable_tiles(map, unwalkable);
+    let region = largest_region(&walkable);
+    let walkable_area = region.len() as f32 / grid::LAYER_SIZE as f32;
+    let preferred = biome
+        .map(|biome| biome.terrain_pallet.spawn.as_slice())
+        .unwrap_or_default();
+    let spawn = map
+        .spawn
+        .filter(|pos| region.contains(&grid::to_index(pos.x, pos.y)))
+        .or_else(|| {
+            spawn::find_spawn(
+                map,
+                &walkable,
+                &region,
+                preferred,
+                validation.spawn_clearance,
+            )
+        });
+
+    let (flora, misplaced) = biome
+        .map(|biome| analyze_flora(map, biome))
+        .unwrap_or_default();
+
+    let (elevation_range, elevation_histogram) = elevation_histogram(map);
+
+    let mut issues = vec![];
+    if walkable_area < validation.min_walkable_area {
+        issues.push(MapIssue::SmallWalkableArea {
+            area: walkable_area,
+            min: validation.min_walkable_area,
+        });
+    }
+    if misplaced > 0 {
+        issues.push(MapIssue::MisplacedFlora(misplaced));
+    }
+    if spawn.is_none() {
+        issues.push(MapIssue::NoSpawn);
+    }
+
+    MapReport {
+        seed: map.seed,
+        attempts: 1,
+        floor_tiles: tile_usage(&map.tile[LayerIndex::Floor]),
+        wall_tiles: tile_usage(&map.tile[LayerIndex::Wall]),
+        flora,
+        elevation_range,
+        elevation_histogram,
+        walkable_area,
+        spawn,
+        issues,
+    }
+}
+
+fn setup(mut config_server: ConfigServer) {
+    config_server
+        .load::<MapValidationRegistryConfig>("config/procgen/validation.ron")
+        .observe(on_validation_config_updated);
+}
+
+fn on_validation_config_updated(
+    updated: On<ConfigAssetUpdated>,
+    configs: Configs<MapValidationRegistryConfig>,
+    mut commands: Commands,
+) {
+    let Some(config) = configs.get(updated.id()) else {
+        error!("Map validation config not found.");
+        return;
+    };
+
+    commands.insert_resource(MapValidation(config.clone()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_split_map() {
+        // Arrange
+        let wall = TileId::new(1);
+        let mut map = Map::new("test".to_string(), 0);
+        // A wall splits the map in a left region of 100 columns and a right one of 155.
+        for y in 0..grid::DIMS.y as u16 {
+            map.tile[LayerIndex::Wall].set(100, y, wall);
+        }
+        let validation = MapValidationConfig {
+            min_walkable_area: 0.5,
+            spawn_clearance: 2,
+            max_attempts: 1,
+        };
+
+        // Act
+        let report = analyze(&map, None, &validation);
+
+        // Assert
+        let expected_area = 155.0 / grid::DIMS.x as f32;
+        assert!((report.walkable_area - expected_area).abs() < 0.001);
+        assert_eq!(report.wall_tiles[1], (wall, grid::DIMS.y as usize));
+        assert_eq!(report.elevation_histogram[0], grid::LAYER_SIZE);
+        // Closest tile to center on the right region, away from the wall.
+        assert_eq!(report.spawn, Some(U16Vec2::new(128, 128)));
+        assert!(report.is_valid());
+
+        // Act
+        let report = analyze(
+            &map,
+            None,
+            &MapValidationConfig {
+                min_walkable_area: 0.7,
+                ..validation
+            },
+        );
+
+        // Assert
+        assert!(matches!(
+            report.issues.as_slice(),
+            [MapIssue::SmallWalkableArea { .. }]
+        ));
+
+        // Act
+        map.spawn = Some(U16Vec2::new(200, 10));
+        let kept = analyze(&map, None, &validation).spawn;
+        map.spawn = Some(U16Vec2::new(50, 128));
+        let replaced = analyze(&map, None, &validation).spawn;
+
+        // Assert
+        // Spawns already selected are kept, unless they are outside of the largest region.
+        assert_eq!(kept, Some(U16Vec2::new(200, 10)));
+        assert_eq!(replaced, Some(U16Vec2::new(128, 128)));
+    }
+
+    #[test]
+    fn water_is_not_walkable() {
+        // Arrange
+        let water = TileId::new(1);
+        let grass = TileId::new(2);
+        let mut map = Map::new("test".to_string(), 0);
+        // A lake covering the left half of the map.
+        for y in 0..grid::DIMS.y as u16 {
+            for x in 0..grid::DIMS.x as u16 {
+                let floor = if x < 128 { water } else { grass };
+                map.tile[LayerIndex::Floor].set(x, y, floor);
+            }
+        }
+        let mut biome = Biome::default();
+        biome.terrain_pallet.unwalkable = vec![water];
+        let validation = MapValidationConfig {
+            min_walkable_area: 0.6,
+            spawn_clearance: 1,
+            max_attempts: 1,
+        };
+
+        // Act
+        let report = analyze(&map, Some(&biome), &validation);
+
+        // Assert
+        assert!((report.walkable_area - 0.5).abs() < 0.001);
+        assert!(matches!(
+            report.issues.as_slice(),
+            [MapIssue::SmallWalkableArea { .. }]
+        ));
+        let spawn = report.spawn.unwrap();
+        assert_eq!(map.tile[LayerIndex::Floor].get(spawn.x, spawn.y), &grass);
+    }
+}
@@ -0,0 +1,471 @@
+use bevy::prelude::*;
+use eternal_config::biome::AdjacencyRepairConfig;
+use eternal_grid::{
+    ecs::TileRegistry,
+    grid::{self, LayerIndex},
+    tile::TileId,
+};
+
+use crate::{map::Map, rng::Rng};
+
+/// Maximum number of tiles on [`AdjacencyRules`], since possible tiles are stored as bitmasks.
+pub const MAX_TILES: usize = 64;
+
+/// Neighbor offsets, ordered so the opposite direction of `d` is `d ^ 1`.
+const DIRECTIONS: [IVec2; 4] = [IVec2::NEG_X, IVec2::X, IVec2::NEG_Y, IVec2::Y];
+
+fn bits(mask: u64) -> impl Iterator<Item = usize> {
+    (0..MAX_TILES).filter(move |&bit| mask >> bit & 1 == 1)
+}
+
+/// Which tiles can be placed next to each other, on each direction.
+#[derive(Default, Debug, Clone, Reflect)]
+pub struct AdjacencyRules {
+    tiles: Vec<TileId>,
+    /// Bitmask of the tiles allowed next to each tile, on each direction.
+    allowed: Vec<[u64; 4]>,
+    /// Relative chance of picking each tile.
+    weights: Vec<f32>,
+}
+
+impl AdjacencyRules {
+    /// Builds rules using the neighbors declared on tile configs. Both tiles must allow each
+    /// other and tiles without declared neighbors allow any tile.
+    pub fn from_registry(tiles: &[TileId], registry: &TileRegistry) -> Self {
+        let mut rules = Self::with_tiles(tiles.iter().copied());
+
+        let declared = |tile: TileId| {
+            registry
+                .get(&tile)
+                .map(|info| info.neighbors.as_ref())
+                .unwrap_or_default()
+        };
+        let allows = |a: TileId, b: TileId| declared(a).is_empty() || declared(a).contains(&b);
+
+        for (i, &a) in rules.tiles.iter().enumerate() {
+            let mask = rules
+                .tiles
+                .iter()
+                .enumerate()
+                .filter(|&(_, &b)| a == b || (allows(a, b) && allows(b, a)))
+                .fold(0, |mask, (j, _)| mask | 1 << j);
+            rules.allowed[i] = [mask; 4];
+        }
+
+        rules
+    }
+
+    /// Learns rules from an example, allowing only adjacencies found on it. Tiles are weighted by
+    /// how many times they are used. `None` cells are ignored.
+    pub fn from_example(cells: &[Option<TileId>], width: usize) -> Self {
+        let mut rules = Self::with_tiles(cells.iter().flatten().copied());
+        rules.weights.fill(0.0);
+
+        for (idx, cell) in cells.iter().enumerate() {
+            let Some(tile) = cell.and_then(|tile| rules.index(tile)) else {
+                continue;
+            };
+            rules.weights[tile] += 1.0;
+
+            for (d, neighbor) in neighbors(idx, width, cells.len()) {
+                if let Some(neighbor) = cells[neighbor].and_then(|tile| rules.index(tile)) {
+                    rules.allowed[tile][d] |= 1 << neighbor;
+                    rules.allowed[neighbor][d ^ 1] |= 1 << tile;
+                }
+            }
+        }
+
+        rules
+    }
+
+    fn with_tiles(tiles: impl Iterator<Item = TileId>) -> Self {
+        let mut unique = vec![];
+        for tile in tiles {
+            if !unique.contains(&tile) {
+                unique.push(tile);
+            }
+        }
+
+        if unique.len() > MAX_TILES {
+            warn!(
+                "Adjacency rules support up to {MAX_TILES} tiles, ignoring {} tiles",
+                unique.len() - MAX_TILES
+            );
+            unique.truncate(MAX_TILES);
+        }
+
+        Self {
+            allowed: vec![[0; 4]; unique.len()],
+            weights: vec![1.0; unique.len()],
+            tiles: unique,
+        }
+    }
+
+    fn index(&self, tile: TileId) -> Option<usize> {
+        self.tiles.iter().position(|&t| t == tile)
+    }
+
+    fn all(&self) -> u64 {
+        if self.tiles.len() == MAX_TILES {
+            u64::MAX
+        } else {
+            (1 << self.tiles.len()) - 1
+        }
+    }
+
+    /// Whether `neighbor` can be placed next to `tile`, on the given offset. Tiles unknown to
+    /// these rules are always allowed.
+    pub fn allows(&self, tile: TileId, offset: IVec2, neighbor: TileId) -> bool {
+        let Some(d) = DIRECTIONS.iter().position(|&o| o == offset) else {
+            return true;
+        };
+
+        match (self.index(tile), self.index(neighbor)) {
+            (Some(tile), Some(neighbor)) => self.allowed[tile][d] >> neighbor & 1 == 1,
+            _ => true,
+        }
+    }
+}
+
+/// Neighbors of a cell on a grid of the given width, together with their direction.
+fn neighbors(idx: usize, width: usize, len: usize) -> impl Iterator<Item = (usize, usize)> {
+    let height = len / width;
+    let pos = IVec2::new((idx % width) as i32, (idx / width) as i32);
+
+    DIRECTIONS
+        .into_iter()
+        .enumerate()
+        .filter_map(move |(d, offset)| {
+            let n = pos + offset;
+            (n.x >= 0 && n.y >= 0 && n.x < width as i32 && n.y < height as i32)
+                .then(|| (d, n.y as usize * width + n.x as usize))
+        })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Cell {
+    /// Not part of the problem, so it doesn't constrain its neighbors.
+    Ignored,
+    /// Tile index which can't be changed.
+    Fixed(usize),
+    /// Bitmask of the possible tiles. The preferred tile is picked while it is still possible.
+    Open {
+        domain: u64,
+        preferred: Option<usize>,
+    },
+}
+
+impl Cell {
+    fn domain(&self) -> Option<u64> {
+        match *self {
+            Cell::Ignored => None,
+            Cell::Fixed(tile) => Some(1 << tile),
+            Cell::Open { domain, .. } => Some(domain),
+        }
+    }
+
+    /// Tile index of a solved cell.
+    fn tile(&self) -> Option<usize> {
+        match *self {
+            Cell::Ignored => None,
+            Cell::Fixed(tile) => Some(tile),
+            Cell::Open { domain, .. } => Some(domain.trailing_zeros() as usize),
+        }
+    }
+}
+
+/// Removes tiles which aren't allowed by any of the possible tiles of each neighbor. Returns
+/// `false` when a cell runs out of possible tiles.
+fn propagate(
+    cells: &mut [Cell],
+    width: usize,
+    rules: &AdjacencyRules,
+    queue: &mut Vec<usize>,
+) -> bool {
+    while let Some(idx) = queue.pop() {
+        let Some(domain) = cells[idx].domain() else {
+            continue;
+        };
+
+        for (d, neighbor) in neighbors(idx, width, cells.len()) {
+            let Cell::Open {
+                domain: neighbor_domain,
+                preferred,
+            } = cells[neighbor]
+            else {
+                continue;
+            };
+
+            let allowed = bits(domain).fold(0, |mask, tile| mask | rules.allowed[tile][d]);
+            let reduced = neighbor_domain & allowed;
+            if reduced == neighbor_domain {
+                continue;
+            }
+
+            if reduced == 0 {
+                return false;
+            }
+
+            cells[neighbor] = Cell::Open {
+                domain: reduced,
+                preferred,
+            };
+            queue.push(neighbor);
+        }
+    }
+
+    true
+}
+
+/// Collapses every open cell into a single tile, starting from the cells with fewer possible
+/// tiles. Returns `false` on a contradiction, leaving cells partially solved.
+fn solve(cells: &mut [Cell], width: usize, rules: &AdjacencyRules, rng: &mut Rng) -> bool {
+    let mut queue = (0..cells.len()).collect::<Vec<_>>();
+    if !propagate(cells, width, rules, &mut queue) {
+        return false;
+    }
+
+    loop {
+        let next = cells
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, cell)| match *cell {
+                Cell::Open { domain, preferred } if domain.count_ones() > 1 => {
+                    Some((domain.count_ones(), idx, domain, preferred))
+                }
+                _ => None,
+            })
+            .min_by_key(|&(count, idx, ..)| (count, idx));
+
+        let Some((_, idx, domain, preferred)) = next else {
+            return true;
+        };
+
+        let options = bits(domain).collect::<Vec<_>>();
+        let tile = preferred
+            .filter(|&tile| domain >> tile & 1 == 1)
+            .or_else(|| {
+                rng.pick_weighted(&options, |&tile| rules.weights[tile])
+                    .copied()
+            })
+            .unwrap_or(options[0]);
+
+        cells[idx] = Cell::Open {
+            domain: 1 << tile,
+            preferred,
+        };
+        queue.push(idx);
+
+        if !propagate(cells, width, rules, &mut queue) {
+            return false;
+        }
+    }
+}
+
+/// Generates tiles on the cells where `mask` is set, following the rules. Returns `None` on a
+/// contradiction.
+pub fn generate(
+    mask: &[bool],
+    width: usize,
+    rules: &AdjacencyRules,
+    rng: &mut Rng,
+) -> Option<Vec<Option<TileId>>> {
+    let mut cells = mask
+        .iter()
+        .map(|&open| {
+            if open {
+                Cell::Open {
+                    domain: rules.all(),
+                    preferred: None,
+                }
+            } else {
+                Cell::Ignored
+            }
+        })
+        .collect::<Vec<_>>();
+
+    solve(&mut cells, width, rules, rng).then(|| {
+        cells
+            .iter()
+            .map(|cell| cell.tile().map(|tile| rules.tiles[tile]))
+            .collect()
+    })
+}
+
+/// Compiled version of [`AdjacencyRepairConfig`].
+#[derive(Default, Debug, Clone, Reflect)]
+pub struct AdjacencyRepair {
+    pub rules: AdjacencyRules,
+    pub radius: u8,
+    pub max_attempts: u32,
+}
+
+impl AdjacencyRepair {
+    pub fn from_config(
+        config: &AdjacencyRepairConfig,
+        tiles: &[TileId],
+        tile_registry: &TileRegistry,
+    ) -> Self {
+        Self {
+            rules: AdjacencyRules::from_registry(tiles, tile_registry),
+            radius: config.radius,
+            max_attempts: config.max_attempts,
+        }
+    }
+}
+
+fn has_broken_adjacency(map: &Map, rules: &AdjacencyRules, pos: IVec2) -> bool {
+    let floor = &map.tile[LayerIndex::Floor];
+    let tile = *floor.get(pos.x as u16, pos.y as u16);
+
+    DIRECTIONS.into_iter().any(|offset| {
+        let n = pos + offset;
+        n.cmpge(IVec2::ZERO).all()
+            && n.cmplt(grid::DIMS.as_ivec2()).all()
+            && !rules.allows(tile, offset, *floor.get(n.x as u16, n.y as u16))
+    })
+}
+
+/// Generates again the floor around `center`, keeping the tiles just outside of the area and the
+/// locked ones.
+fn repair_area(
+    map: &mut Map,
+    rules: &AdjacencyRules,
+    locked: &[bool],
+    center: IVec2,
+    radius: i32,
+    rng: &mut Rng,
+) -> bool {
+    let dims = grid::DIMS.as_ivec2();
+    let area = IRect::from_corners(center - radius, center + radius + 1)
+        .intersect(IRect::from_corners(IVec2::ZERO, dims));
+    // Tiles around the area are fixed, so repaired tiles fit with them.
+    let bounds = area
+        .inflate(1)
+        .intersect(IRect::from_corners(IVec2::ZERO, dims));
+    let width = bounds.width() as usize;
+
+    let floor = &map.tile[LayerIndex::Floor];
+    let mut cells = (bounds.min.y..bounds.max.y)
+        .flat_map(|y| (bounds.min.x..bounds.max.x).map(move |x| IVec2::new(x, y)))
+        .map(|pos| {
+            let tile = rules.index(*floor.get(pos.x as u16, pos.y as u16));
+            let inside = pos.cmpge(area.min).all()
+                && pos.cmplt(area.max).all()
+                && !locked[grid::to_index(pos.x as u16, pos.y as u16)];
+            match tile {
+                None => Cell::Ignored,
+                Some(tile) if !inside => Cell::Fixed(tile),
+                Some(tile) => Cell::Open {
+                    domain: rules.all(),
+                    preferred: Some(tile),
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !solve(&mut cells, width, rules, rng) {
+        return false;
+    }
+
+    for (idx, cell) in cells.iter().enumerate() {
+        if let (Cell::Open { .. }, Some(tile)) = (cell, cell.tile()) {
+            let pos = bounds.min + IVec2::new((idx % width) as i32, (idx / width) as i32);
+            map.tile[LayerIndex::Floor].set(pos.x as u16, pos.y as u16, rules.tiles[tile]);
+        }
+    }
+
+    true
+}
+
+/// Regenerates the floor around tiles placed next to tiles they aren't allowed to, keeping as
+/// many of the original tiles as possible. Tiles set on `locked`, indexed like the map grid, are
+/// never changed. Returns how many tiles couldn't be repaired.
+pub fn repair(map: &mut Map, repair: &AdjacencyRepair, locked: &[bool], rng: &mut Rng) -> usize {
+    let mut unresolved = 0;
+
+    for y in 0..grid::DIMS.y as i32 {
+        for x in 0..grid::DIMS.x as i32 {
+            let pos = IVec2::new(x, y);
+            if !has_broken_adjacency(map, &repair.rules, pos) {
+                continue;
+            }
+
+            let repaired = (0..repair.max_attempts.max(1)).any(|attempt| {
+                let radius = repair.radius as i32 + attempt as i32;
+                repair_area(map, &repair.rules, locked, pos, radius, rng)
+            });
+
+            if !repaired {
+                unresolved += 1;
+            }
+        }
+    }
+
+    unresolved
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashMap;
+    use eternal_grid::tile::TileInfo;
+
+    use super::*;
+
+    #[test]
+    fn repair_water_next_to_grass() {
+        // Arrange
+        let (water, sand, grass) = (TileId::new(0), TileId::new(1), TileId::new(2));
+        let tiles = [("WATER", vec![sand]), ("SAND", vec![]), ("GRASS", vec![])]
+            .into_iter()
+            .enumerate()
+            .map(|(id, (name, neighbors))| {
+                (
+                    TileId::new(id as u16),
+                    TileInfo {
+                        name: name.into(),
+                        neighbors: neighbors.into(),
+                        ..default()
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let adjacency = AdjacencyRepair::from_config(
+            &AdjacencyRepairConfig {
+                radius: 1,
+                max_attempts: 3,
+            },
+            &[water, sand, grass],
+            &TileRegistry::new(tiles),
+        );
+        let mut map = Map::new("test".to_string(), 0);
+        let floor = &mut map.tile[LayerIndex::Floor];
+        floor.fill(grass);
+        for y in 0..grid::DIMS.y as u16 {
+            for x in 0..10 {
+                floor.set(x, y, water);
+            }
+        }
+
+        // Act
+        let unresolved = repair(
+            &mut map,
+            &adjacency,
+            &[false; grid::LAYER_SIZE],
+            &mut Rng::new(0),
+        );
+
+        // Assert
+        assert_eq!(unresolved, 0);
+        let floor = &map.tile[LayerIndex::Floor];
+        for y in 0..grid::DIMS.y as u16 {
+            for x in 0..grid::DIMS.x as u16 - 1 {
+                let (tile, right) = (*floor.get(x, y), *floor.get(x + 1, y));
+                assert!(adjacency.rules.allows(tile, IVec2::X, right));
+            }
+        }
+        // Tiles far from the border are kept
+        assert_eq!(*floor.get(0, 0), water);
+        assert_eq!(*floor.get(20, 0), grass);
+    }
+}
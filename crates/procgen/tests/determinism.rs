@@ -0,0 +1,232 @@
+//! Makes sure generation stays the same for a given seed and config. The real configs are loaded,
+//! a fixed set of seeds is generated and the output is compared against checked-in snapshots.
+//!
+//! When a change is intentional, accept the new snapshots with:
+//! `UPDATE_SNAPSHOTS=1 cargo test -p eternal_procgen --test determinism`
+
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use eternal_config::validation::MapValidationConfig;
+use eternal_grid::{ecs::TileRegistry, grid::LayerIndex, tile::TileId};
+use eternal_procgen::{
+    atlas::{ActiveMap, Atlas, AtlasRegion},
+    biome::BiomeRegistry,
+    generator::{GeneratorContext, MapGeneratorRegistry},
+    headless,
+    library::Library,
+    map::Map,
+    prefab::PrefabRegistry,
+};
+use serde::{Deserialize, Serialize};
+
+const SEEDS: [u64; 3] = [1, 42, 1337];
+const ASSETS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../assets");
+const SNAPSHOT_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/snapshots/determinism.ron"
+);
+const LOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct AtlasSnapshot {
+    seed: u64,
+    elevation: String,
+    rivers: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct MapSnapshot {
+    seed: u64,
+    generator: String,
+    tiles: String,
+    elevation: String,
+    structures: usize,
+    /// How many times each tile is used, on both floor and wall layers.
+    histogram: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Snapshots {
+    atlases: Vec<AtlasSnapshot>,
+    maps: Vec<MapSnapshot>,
+}
+
+/// FNV-1a, which is simple and stable across platforms and Rust versions.
+#[derive(Clone, Copy)]
+struct Hasher(u64);
+
+impl Hasher {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+fn hash_floats<'a>(values: impl Iterator<Item = &'a f32>) -> String {
+    let mut hasher = Hasher::new();
+    values.for_each(|v| hasher.write(&v.to_bits().to_le_bytes()));
+    hasher.finish()
+}
+
+fn tile_name(tile_id: TileId, tiles: &TileRegistry) -> String {
+    tiles
+        .get(&tile_id)
+        .map_or_else(|| format!("#{tile_id:?}"), |info| info.name.to_string())
+}
+
+fn atlas_snapshot(atlas: &Atlas, seed: u64) -> AtlasSnapshot {
+    AtlasSnapshot {
+        seed,
+        elevation: hash_floats(atlas.elevation.iter()),
+        rivers: atlas.rivers.len(),
+    }
+}
+
+/// Tiles are hashed by name, so adding new tiles to the registry doesn't change every snapshot.
+fn map_snapshot(map: &Map, generator: &str, tiles: &TileRegistry) -> MapSnapshot {
+    let mut hasher = Hasher::new();
+    let mut histogram = BTreeMap::new();
+
+    for layer in [LayerIndex::Floor, LayerIndex::Wall] {
+        for &tile_id in map.tile[layer].iter() {
+            let name = tile_name(tile_id, tiles);
+            hasher.write(name.as_bytes());
+            hasher.write(&[0]);
+            *histogram.entry(name).or_default() += 1;
+        }
+    }
+
+    MapSnapshot {
+        seed: map.seed,
+        generator: generator.to_string(),
+        tiles: hasher.finish(),
+        elevation: hash_floats(map.elevation.iter().map(|e| &**e)),
+        structures: map.structures.len(),
+        histogram,
+    }
+}
+
+fn generate_snapshots() -> Snapshots {
+    let assets = Path::new(ASSETS_PATH)
+        .canonicalize()
+        .expect("Assets folder to exist");
+    let mut app = headless::app(&assets);
+    let mut snapshots = Snapshots::default();
+
+    for seed in SEEDS {
+        assert!(
+            headless::generate_atlas(&mut app, seed, LOAD_TIMEOUT),
+            "Timed out loading configs"
+        );
+
+        let world = app.world();
+        let tiles = world.resource::<TileRegistry>();
+        let atlas = world.resource::<Atlas>();
+        let generators = world.resource::<MapGeneratorRegistry>();
+        let ctx = GeneratorContext {
+            biomes: world.resource::<BiomeRegistry>(),
+            prefabs: world.resource::<PrefabRegistry>(),
+            region: Some(AtlasRegion {
+                atlas,
+                map: *ActiveMap::default(),
+            }),
+            seed,
+            spawn_clearance: MapValidationConfig::default().spawn_clearance,
+        };
+
+        snapshots.atlases.push(atlas_snapshot(atlas, seed));
+
+        let mut names = generators.names().collect::<Vec<_>>();
+        names.sort_unstable();
+        for name in names {
+            let map = generators.generate(name, &ctx).unwrap();
+            snapshots.maps.push(map_snapshot(&map, name, tiles));
+        }
+    }
+
+    snapshots
+}
+
+#[test]
+fn generation_matches_snapshots() {
+    // Arrange
+    let pretty = ron::ser::PrettyConfig::default();
+
+    // Act
+    let snapshots = generate_snapshots();
+
+    // Assert
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        let content = ron::ser::to_string_pretty(&snapshots, pretty).unwrap();
+        std::fs::create_dir_all(Path::new(SNAPSHOT_PATH).parent().unwrap()).unwrap();
+        std::fs::write(SNAPSHOT_PATH, content + "\n").unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(SNAPSHOT_PATH)
+        .expect("Snapshots not found. Run with UPDATE_SNAPSHOTS=1 to create them");
+    let expected = ron::from_str::<Snapshots>(&expected).unwrap();
+
+    for (actual, expected) in snapshots.atlases.iter().zip(&expected.atlases) {
+        assert_eq!(actual, expected, "Atlas of seed {} changed", actual.seed);
+    }
+    for (actual, expected) in snapshots.maps.iter().zip(&expected.maps) {
+        assert_eq!(
+            actual, expected,
+            "Map of seed {} using {} generator changed",
+            actual.seed, actual.generator
+        );
+    }
+    assert_eq!(snapshots, expected);
+}
+
+#[test]
+fn library_matches_plugins() {
+    // Arrange
+    let seed = 42;
+    let assets = Path::new(ASSETS_PATH)
+        .canonicalize()
+        .expect("Assets folder to exist");
+    let mut app = headless::app(&assets);
+    assert!(
+        headless::generate_atlas(&mut app, seed, LOAD_TIMEOUT),
+        "Timed out loading configs"
+    );
+
+    // Act
+    let library = Library::load(&assets).unwrap();
+
+    // Assert
+    let world = app.world();
+    let generators = world.resource::<MapGeneratorRegistry>();
+    let ctx = GeneratorContext {
+        biomes: world.resource::<BiomeRegistry>(),
+        prefabs: world.resource::<PrefabRegistry>(),
+        region: None,
+        seed,
+        spawn_clearance: MapValidationConfig::default().spawn_clearance,
+    };
+
+    let mut names = library.generators.names().collect::<Vec<_>>();
+    names.sort_unstable();
+    assert_eq!(names.len(), generators.names().count());
+    for name in names {
+        let expected = generators.generate(name, &ctx).unwrap();
+        let actual = library.generate(name, seed).unwrap();
+        assert_eq!(
+            map_snapshot(&actual, name, &library.tiles),
+            map_snapshot(&expected, name, world.resource::<TileRegistry>()),
+            "Map using {name} generator differs from the plugin one"
+        );
+    }
+}